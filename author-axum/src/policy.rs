@@ -0,0 +1,42 @@
+use author::{AsyncPolicy, Decision, Resource, Subject};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tracing::debug;
+
+/// Rejection returned from handlers when a policy doesn't permit the request, which responds with
+/// `403 Forbidden`. The decision is kept so it can be logged, but isn't sent to the client.
+#[derive(Debug)]
+pub struct Forbidden(pub Decision);
+
+impl From<author::Error> for Forbidden {
+    fn from(error: author::Error) -> Self {
+        match error {
+            author::Error::Forbidden(decision) => Forbidden(decision),
+        }
+    }
+}
+
+impl IntoResponse for Forbidden {
+    fn into_response(self) -> Response {
+        debug!("Request forbidden: {}", self.0);
+
+        (StatusCode::FORBIDDEN, "Forbidden").into_response()
+    }
+}
+
+/// Checks the policy from within a handler, so that `authorise(...).await?` rejects the request
+/// unless the policy permits it.
+pub async fn authorise<P, Res, Subj>(
+    policy: &P,
+    resource: &Res,
+    subject: &Subj,
+    action: &Res::Action,
+) -> Result<(), Forbidden>
+where
+    P: AsyncPolicy<Res, Subj> + ?Sized,
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+{
+    Ok(policy.authorise(resource, subject, action).await?)
+}
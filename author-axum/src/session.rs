@@ -70,7 +70,7 @@ where
     pub fn new(inner: Inner, config: SessionConfig, store: Arc<Store>) -> Self {
         SessionManagerService {
             inner,
-            config: config.into(),
+            config,
             store,
         }
     }
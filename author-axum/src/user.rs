@@ -39,7 +39,7 @@ where
 
         trace!("Loaded user");
 
-        Ok(User(user, PhantomData::default()))
+        Ok(User(user, PhantomData))
     }
 }
 
@@ -67,7 +67,7 @@ where
             {
                 trace!("Loaded user");
 
-                Ok(Some(User(user, PhantomData::default())))
+                Ok(Some(User(user, PhantomData)))
             } else {
                 Ok(None)
             }
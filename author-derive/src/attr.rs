@@ -0,0 +1,132 @@
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Attribute, Ident, LitStr, Token, Type};
+
+/// A single setting from an `#[author(...)]` attribute.
+pub enum AuthorAttr {
+    Action(Type),
+    Role(Type),
+    Name(String),
+    Allow(Allow),
+    Roles(Vec<Ident>),
+    GlobalRoles,
+}
+
+/// `allow(Action, ..., roles = [Role, ...])`
+pub struct Allow {
+    pub actions: Vec<Ident>,
+    pub roles: Vec<Ident>,
+}
+
+impl AuthorAttr {
+    pub fn action(&self) -> Option<&Type> {
+        match self {
+            AuthorAttr::Action(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    pub fn role(&self) -> Option<&Type> {
+        match self {
+            AuthorAttr::Role(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<String> {
+        match self {
+            AuthorAttr::Name(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn allow(&self) -> Option<&Allow> {
+        match self {
+            AuthorAttr::Allow(allow) => Some(allow),
+            _ => None,
+        }
+    }
+
+    pub fn roles(&self) -> Option<&[Ident]> {
+        match self {
+            AuthorAttr::Roles(roles) => Some(roles),
+            _ => None,
+        }
+    }
+}
+
+/// Parses every `#[author(...)]` attribute, ignoring other attributes.
+pub fn parse_attrs(attrs: &[Attribute]) -> syn::Result<Vec<AuthorAttr>> {
+    let mut parsed = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("author")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("action") {
+                parsed.push(AuthorAttr::Action(meta.value()?.parse()?));
+            } else if meta.path.is_ident("role") {
+                parsed.push(AuthorAttr::Role(meta.value()?.parse()?));
+            } else if meta.path.is_ident("name") {
+                let name: LitStr = meta.value()?.parse()?;
+                parsed.push(AuthorAttr::Name(name.value()));
+            } else if meta.path.is_ident("global_roles") {
+                parsed.push(AuthorAttr::GlobalRoles);
+            } else if meta.path.is_ident("roles") {
+                meta.value()?;
+                parsed.push(AuthorAttr::Roles(parse_roles(meta.input)?));
+            } else if meta.path.is_ident("allow") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                parsed.push(AuthorAttr::Allow(parse_allow(&content)?));
+            } else {
+                return Err(meta.error("unknown author attribute"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(parsed)
+}
+
+fn parse_allow(input: ParseStream) -> syn::Result<Allow> {
+    let mut actions = Vec::new();
+
+    loop {
+        let ident: Ident = input.parse()?;
+
+        if ident == "roles" && input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            let roles = parse_roles(input)?;
+
+            if actions.is_empty() {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected at least one action",
+                ));
+            }
+
+            if !input.is_empty() {
+                return Err(input.error("expected roles to come last"));
+            }
+
+            return Ok(Allow { actions, roles });
+        }
+
+        actions.push(ident);
+
+        if input.is_empty() {
+            return Err(input.error("expected `roles = [...]`"));
+        }
+
+        input.parse::<Token![,]>()?;
+    }
+}
+
+/// `[Role, ...]`
+fn parse_roles(input: ParseStream) -> syn::Result<Vec<Ident>> {
+    let content;
+    bracketed!(content in input);
+    let roles = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+
+    Ok(roles.into_iter().collect())
+}
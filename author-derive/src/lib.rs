@@ -0,0 +1,407 @@
+//! Derive macros for the traits in `author`, configured with `#[author(...)]` attributes.
+//!
+//! ```ignore
+//! #[derive(Resource, RbacResource)]
+//! #[author(action = CustomerAction, role = GlobalRole)]
+//! #[author(allow(Read, roles = [Admin, User]))]
+//! #[author(allow(Write, Delete, roles = [Admin]))]
+//! struct Customer {
+//!     name: String,
+//! }
+//! ```
+//!
+//! Actions and roles are written as variant names and expanded to paths such as
+//! `CustomerAction::Read`, so a misspelt action or role is a compile error pointing at the name.
+//!
+//! Where an action is allowed to the same roles whatever resource it is performed on, the roles
+//! can instead be given on the variants of the action enum, and resources without any `allow`
+//! take their roles from it:
+//!
+//! ```ignore
+//! #[derive(PartialEq, Eq, Hash, RbacAction)]
+//! #[author(role = GlobalRole)]
+//! enum CustomerAction {
+//!     #[author(roles = [Admin, User])]
+//!     Read,
+//!     #[author(roles = [Admin])]
+//!     Write,
+//! }
+//! ```
+//!
+//! Every setting lives under `#[author(...)]`, since `#[allow(...)]` on its own would collide with
+//! the built-in lint attribute.
+
+mod attr;
+
+use crate::attr::{parse_attrs, AuthorAttr};
+use heck::ToSnakeCase;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident};
+
+/// Implements `Resource`, and `NamedResource` if a name is given.
+///
+/// Attributes:
+/// - `#[author(action = Type)]` (required) the type of actions on the resource.
+/// - `#[author(name = "customer")]` the name of the resource, implementing `NamedResource`.
+#[proc_macro_derive(Resource, attributes(author))]
+pub fn derive_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(resource(&input))
+}
+
+/// Implements `RbacResource<Role>` from the roles allowed to perform each action.
+///
+/// Attributes:
+/// - `#[author(role = Type)]` (required) the type of roles.
+/// - `#[author(action = Type)]` (required) the type of actions, usually shared with `Resource`.
+/// - `#[author(allow(Action, ..., roles = [Role, ...]))]` allows the listed roles to perform
+///   the listed actions. Repeat it for each group of actions. An action listed in several groups
+///   is allowed to the roles of all of them, and actions not mentioned are allowed to no one.
+///
+/// Without any `allow`, the roles are those the action type allows through `RbacAction<Role>`.
+#[proc_macro_derive(RbacResource, attributes(author))]
+pub fn derive_rbac_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(rbac_resource(&input))
+}
+
+/// Implements `RbacAction<Role>` for an action enum from the roles allowed each variant.
+///
+/// Attributes:
+/// - `#[author(role = Type)]` (required) the type of roles.
+/// - `#[author(roles = [Role, ...])]` on a variant allows the listed roles to perform it.
+///   Variants without it are allowed to no one.
+#[proc_macro_derive(RbacAction, attributes(author))]
+pub fn derive_rbac_action(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(rbac_action(&input))
+}
+
+/// Implements `Subject`.
+#[proc_macro_derive(Subject, attributes(author))]
+pub fn derive_subject(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::author::Subject for #name #ty_generics #where_clause {}
+    }
+    .into()
+}
+
+/// Implements `GlobalRbacSubject` from a field holding the subject's roles.
+///
+/// Attributes:
+/// - `#[author(role = Type)]` (required) the type of roles.
+/// - `#[author(global_roles)]` on the field holding the roles, which can be any collection of
+///   cloneable roles. Without it the subject holds no global roles.
+#[proc_macro_derive(GlobalRbacSubject, attributes(author))]
+pub fn derive_global_rbac_subject(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(global_rbac_subject(&input))
+}
+
+/// Implements `Named` for a fieldless enum, naming each variant in snake case unless given
+/// `#[author(name = "...")]`.
+#[proc_macro_derive(Named, attributes(author))]
+pub fn derive_named(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(named(&input))
+}
+
+/// Implements `Enumerable` for a fieldless enum, listing the variants in declaration order.
+#[proc_macro_derive(Enumerable, attributes(author))]
+pub fn derive_enumerable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(enumerable(&input))
+}
+
+fn expand(result: syn::Result<TokenStream2>) -> TokenStream {
+    result.unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn resource(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let action = attrs
+        .iter()
+        .find_map(AuthorAttr::action)
+        .ok_or_else(|| missing(input, "#[author(action = Type)]"))?;
+
+    let named = attrs
+        .iter()
+        .find_map(AuthorAttr::name)
+        .map(|resource_name| {
+            quote! {
+                impl #impl_generics ::author::NamedResource for #name #ty_generics #where_clause {
+                    const RESOURCE_NAME: &'static str = #resource_name;
+                }
+            }
+        });
+
+    Ok(quote! {
+        impl #impl_generics ::author::Resource for #name #ty_generics #where_clause {
+            type Action = #action;
+        }
+
+        #named
+    })
+}
+
+fn rbac_resource(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let action = attrs
+        .iter()
+        .find_map(AuthorAttr::action)
+        .ok_or_else(|| missing(input, "#[author(action = Type)]"))?;
+    let role = attrs
+        .iter()
+        .find_map(AuthorAttr::role)
+        .ok_or_else(|| missing(input, "#[author(role = Type)]"))?;
+
+    // Roles allowed each action, merged across every `allow` naming it
+    let mut allowed: Vec<(&Ident, Vec<&Ident>)> = Vec::new();
+
+    for allow in attrs.iter().filter_map(AuthorAttr::allow) {
+        for action in &allow.actions {
+            let index = match allowed.iter().position(|(existing, _)| *existing == action) {
+                Some(index) => index,
+                None => {
+                    allowed.push((action, Vec::new()));
+                    allowed.len() - 1
+                }
+            };
+            let roles = &mut allowed[index].1;
+
+            for role in &allow.roles {
+                if !roles.contains(&role) {
+                    roles.push(role);
+                }
+            }
+        }
+    }
+
+    let body = if allowed.is_empty() {
+        quote! {
+            ::author::rbac::RbacAction::<#role>::allowed_roles(action)
+        }
+    } else {
+        let checks = allowed.iter().map(|(allowed_action, roles)| {
+            quote! {
+                if matches!(action, #action::#allowed_action { .. }) {
+                    roles.extend([#(#role::#roles),*]);
+                }
+            }
+        });
+
+        quote! {
+            let mut roles = ::std::collections::HashSet::new();
+            #(#checks)*
+            roles
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::author::rbac::RbacResource<#role> for #name #ty_generics #where_clause {
+            fn allowed_roles(
+                &self,
+                action: &<Self as ::author::Resource>::Action,
+            ) -> ::std::collections::HashSet<#role> {
+                #body
+            }
+        }
+    })
+}
+
+fn rbac_action(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let role = attrs
+        .iter()
+        .find_map(AuthorAttr::role)
+        .ok_or_else(|| missing(input, "#[author(role = Type)]"))?;
+
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "RbacAction can only be derived for enums",
+        ));
+    };
+
+    let arms = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            let mut roles: Vec<Ident> = Vec::new();
+
+            for attr in parse_attrs(&variant.attrs)? {
+                for role in attr.roles().unwrap_or_default() {
+                    if !roles.contains(role) {
+                        roles.push(role.clone());
+                    }
+                }
+            }
+
+            Ok(quote! {
+                #name::#ident { .. } => ::std::collections::HashSet::from([#(#role::#roles),*]),
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::author::rbac::RbacAction<#role> for #name #ty_generics #where_clause {
+            fn allowed_roles(&self) -> ::std::collections::HashSet<#role> {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+fn global_rbac_subject(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let role = attrs
+        .iter()
+        .find_map(AuthorAttr::role)
+        .ok_or_else(|| missing(input, "#[author(role = Type)]"))?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "GlobalRbacSubject can only be derived for structs",
+        ));
+    };
+
+    let mut roles_field = None;
+
+    for (i, field) in data.fields.iter().enumerate() {
+        if parse_attrs(&field.attrs)?
+            .iter()
+            .any(|attr| matches!(attr, AuthorAttr::GlobalRoles))
+        {
+            if roles_field.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "only one field can be marked #[author(global_roles)]",
+                ));
+            }
+
+            roles_field = Some(match &field.ident {
+                Some(ident) => quote!(#ident),
+                None => {
+                    let index = syn::Index::from(i);
+                    quote!(#index)
+                }
+            });
+        }
+    }
+
+    let body = match roles_field {
+        Some(field) => quote! {
+            self.#field.iter().cloned().collect()
+        },
+        None => quote! {
+            ::std::collections::HashSet::new()
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::author::rbac::GlobalRbacSubject for #name #ty_generics #where_clause {
+            type GlobalRole = #role;
+
+            fn global_roles(&self) -> ::std::collections::HashSet<#role> {
+                #body
+            }
+        }
+    })
+}
+
+fn named(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let arms = fieldless_variants(input, "Named")?
+        .into_iter()
+        .map(|variant| {
+            let ident = &variant.ident;
+            let variant_name = parse_attrs(&variant.attrs)?
+                .iter()
+                .find_map(AuthorAttr::name)
+                .unwrap_or_else(|| ident.to_string().to_snake_case());
+
+            Ok(quote! {
+                #name::#ident => #variant_name,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::author::Named for #name #ty_generics #where_clause {
+            fn name(&self) -> &str {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+fn enumerable(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variants = fieldless_variants(input, "Enumerable")?
+        .into_iter()
+        .map(|variant| &variant.ident);
+
+    Ok(quote! {
+        impl #impl_generics ::author::Enumerable for #name #ty_generics #where_clause {
+            fn all() -> ::std::vec::Vec<Self> {
+                vec![#(#name::#variants),*]
+            }
+        }
+    })
+}
+
+fn fieldless_variants<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<Vec<&'a syn::Variant>> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            format!("{} can only be derived for enums", derive),
+        ));
+    };
+
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(variant),
+            _ => Err(Error::new(
+                variant.span(),
+                format!("{} can only be derived for enums without fields", derive),
+            )),
+        })
+        .collect()
+}
+
+fn missing(input: &DeriveInput, attr: &str) -> Error {
+    Error::new(input.ident.span(), format!("missing {} attribute", attr))
+}
@@ -0,0 +1,18 @@
+use author::rbac::RbacResource;
+use author::Resource;
+
+// Without any `allow`, the action has to say which roles may perform it
+#[derive(Resource, RbacResource)]
+#[author(action = Action, role = Role)]
+struct Customer;
+
+#[derive(PartialEq, Eq, Hash)]
+enum Action {
+    Read,
+}
+
+enum Role {
+    Admin,
+}
+
+fn main() {}
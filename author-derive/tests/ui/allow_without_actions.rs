@@ -0,0 +1,19 @@
+use author::rbac::RbacResource;
+use author::Resource;
+
+#[derive(Resource, RbacResource)]
+#[author(action = Action, role = Role)]
+#[author(allow(roles = [Admin]))]
+struct Customer;
+
+#[derive(PartialEq, Eq, Hash)]
+enum Action {
+    Read,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum Role {
+    Admin,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use author::rbac::RbacResource;
+use author::Resource;
+
+#[derive(Resource, RbacResource)]
+#[author(action = Action)]
+#[author(allow(Read, roles = [Admin]))]
+struct Customer;
+
+#[derive(PartialEq, Eq, Hash)]
+enum Action {
+    Read,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use author::rbac::RbacAction;
+
+#[derive(RbacAction)]
+#[author(role = Role)]
+enum Action {
+    #[author(roles = [Admn])]
+    Read,
+}
+
+enum Role {
+    Admin,
+}
+
+fn main() {}
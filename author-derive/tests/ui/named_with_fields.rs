@@ -0,0 +1,9 @@
+use author::Named;
+
+#[derive(Named)]
+enum Action {
+    Read,
+    Transfer { amount: u64 },
+}
+
+fn main() {}
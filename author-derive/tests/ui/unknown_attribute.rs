@@ -0,0 +1,12 @@
+use author::Resource;
+
+#[derive(Resource)]
+#[author(action = Action, label = "customer")]
+struct Customer;
+
+#[derive(PartialEq, Eq, Hash)]
+enum Action {
+    Read,
+}
+
+fn main() {}
@@ -20,6 +20,12 @@ impl<S, K> InMemorySessionStore<S, K> {
     }
 }
 
+impl<S, K> Default for InMemorySessionStore<S, K> {
+    fn default() -> Self {
+        InMemorySessionStore::new()
+    }
+}
+
 #[async_trait]
 impl<S, K> SessionStore for InMemorySessionStore<S, K>
 where
@@ -70,6 +76,12 @@ impl<K, V> InMemorySessionData<K, V> {
     }
 }
 
+impl<K, V> Default for InMemorySessionData<K, V> {
+    fn default() -> Self {
+        InMemorySessionData::new()
+    }
+}
+
 impl<K, V> CreateNew for InMemorySessionData<K, V>
 where
     K: Send + Sync,
@@ -21,10 +21,14 @@ where
     type User = U;
 
     async fn set_user(&self, user: U) -> anyhow::Result<()> {
+        // Impersonation started by a previous user mustn't carry over to the new one
+        self.unset_value("impersonated_user").await?;
         Ok(self.set_value("current_user", user).await?)
     }
 
     async fn unset_user(&self) -> anyhow::Result<()> {
+        // Logging out also ends any impersonation
+        self.unset_value("impersonated_user").await?;
         Ok(self.unset_value("current_user").await?)
     }
 
@@ -53,3 +57,182 @@ where
         Ok((&*self as &Sess).current_user().await?)
     }
 }
+
+/// A session in which the logged in user can impersonate another user, for example so that
+/// support staff can see what a customer sees. The logged in user remains the
+/// [`current_user`](UserSession::current_user) throughout, so requests can still be attributed to
+/// them.
+#[async_trait]
+pub trait ImpersonationSession: UserSession {
+    /// Starts impersonating a user, failing if nobody is logged in or if `may_impersonate`, called
+    /// with the logged in user and the user to impersonate, doesn't allow it.
+    async fn start_impersonating<F>(
+        &self,
+        user: Self::User,
+        may_impersonate: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnOnce(&Self::User, &Self::User) -> bool + Send;
+    /// Stops impersonating, returning the user that was being impersonated.
+    async fn stop_impersonating(&self) -> anyhow::Result<Option<Self::User>>;
+    async fn impersonated_user(&self) -> anyhow::Result<Option<Self::User>>;
+}
+
+#[cfg(feature = "in-memory")]
+#[async_trait]
+impl<U> ImpersonationSession for InMemorySessionData<String, U>
+where
+    U: Clone + Send,
+{
+    async fn start_impersonating<F>(&self, user: U, may_impersonate: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&U, &U) -> bool + Send,
+    {
+        let Some(current_user) = self.current_user().await? else {
+            anyhow::bail!("Can't impersonate a user without being logged in");
+        };
+
+        if !may_impersonate(&current_user, &user) {
+            anyhow::bail!("Not permitted to impersonate this user");
+        }
+
+        Ok(self.set_value("impersonated_user", user).await?)
+    }
+
+    async fn stop_impersonating(&self) -> anyhow::Result<Option<U>> {
+        let user = self.impersonated_user().await?;
+        self.unset_value("impersonated_user").await?;
+
+        Ok(user)
+    }
+
+    async fn impersonated_user(&self) -> anyhow::Result<Option<U>> {
+        Ok(self.get_value("impersonated_user").await?)
+    }
+}
+
+#[async_trait]
+impl<U, Sess> ImpersonationSession for Arc<Sess>
+where
+    Sess: ImpersonationSession<User = U> + Send + Sync,
+    U: Clone + Send + 'static,
+{
+    async fn start_impersonating<F>(&self, user: U, may_impersonate: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&U, &U) -> bool + Send,
+    {
+        Ok((&*self as &Sess)
+            .start_impersonating(user, may_impersonate)
+            .await?)
+    }
+
+    async fn stop_impersonating(&self) -> anyhow::Result<Option<U>> {
+        Ok((&*self as &Sess).stop_impersonating().await?)
+    }
+
+    async fn impersonated_user(&self) -> anyhow::Result<Option<U>> {
+        Ok((&*self as &Sess).impersonated_user().await?)
+    }
+}
+
+#[cfg(all(test, feature = "in-memory"))]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn start_and_stop_impersonating() {
+        block_on(async {
+            let session = InMemorySessionData::<String, String>::new();
+            session.set_user("agent".to_string()).await.unwrap();
+
+            session
+                .start_impersonating("customer".to_string(), |_, _| true)
+                .await
+                .unwrap();
+            assert_eq!(
+                session.impersonated_user().await.unwrap().as_deref(),
+                Some("customer")
+            );
+            assert_eq!(
+                session.current_user().await.unwrap().as_deref(),
+                Some("agent")
+            );
+
+            assert_eq!(
+                session.stop_impersonating().await.unwrap().as_deref(),
+                Some("customer")
+            );
+            assert_eq!(session.impersonated_user().await.unwrap(), None);
+            assert_eq!(session.stop_impersonating().await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn impersonating_requires_login() {
+        block_on(async {
+            let session = Arc::new(InMemorySessionData::<String, String>::new());
+
+            let error = session
+                .start_impersonating("customer".to_string(), |_, _| true)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "Can't impersonate a user without being logged in"
+            );
+            assert_eq!(session.impersonated_user().await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn impersonating_requires_permission() {
+        block_on(async {
+            let session = InMemorySessionData::<String, String>::new();
+            session.set_user("customer".to_string()).await.unwrap();
+
+            // Only support agents may impersonate other users
+            let may_impersonate = |current: &String, _: &String| current.starts_with("agent");
+
+            let error = session
+                .start_impersonating("other customer".to_string(), may_impersonate)
+                .await
+                .unwrap_err();
+            assert_eq!(error.to_string(), "Not permitted to impersonate this user");
+            assert_eq!(session.impersonated_user().await.unwrap(), None);
+
+            session.set_user("agent".to_string()).await.unwrap();
+            session
+                .start_impersonating("customer".to_string(), may_impersonate)
+                .await
+                .unwrap();
+            assert_eq!(
+                session.impersonated_user().await.unwrap().as_deref(),
+                Some("customer")
+            );
+        });
+    }
+
+    #[test]
+    fn login_and_logout_end_impersonation() {
+        block_on(async {
+            let session = InMemorySessionData::<String, String>::new();
+            session.set_user("agent".to_string()).await.unwrap();
+            session
+                .start_impersonating("customer".to_string(), |_, _| true)
+                .await
+                .unwrap();
+
+            session.set_user("other agent".to_string()).await.unwrap();
+            assert_eq!(session.impersonated_user().await.unwrap(), None);
+
+            session
+                .start_impersonating("customer".to_string(), |_, _| true)
+                .await
+                .unwrap();
+            session.unset_user().await.unwrap();
+            assert_eq!(session.impersonated_user().await.unwrap(), None);
+            assert_eq!(session.current_user().await.unwrap(), None);
+        });
+    }
+}
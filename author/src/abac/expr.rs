@@ -0,0 +1,998 @@
+//! A small expression language for attribute conditions, such as
+//! `subject.department == resource.department && resource.status in ["draft", "review"]`.
+//!
+//! Expressions support literals (strings, numbers, booleans, `null` and lists), dotted attribute
+//! paths, function calls, comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`), membership (`in`),
+//! the boolean operators `&&`, `||` and `!`, and parentheses.
+
+use crate::abac::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A range of byte offsets into the source of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+
+    /// Moves the span along by `offset` bytes, for expressions embedded in a larger source.
+    pub fn offset(self, offset: usize) -> Span {
+        Span::new(self.start + offset, self.end + offset)
+    }
+
+    /// The 1-based line and column of the start of the span within `source`.
+    pub fn line_column(self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+        (line, column)
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at {span}")]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        ParseError {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum EvalError {
+    #[error("Unknown attribute '{0}'")]
+    UnknownAttribute(String),
+    #[error("Unknown function '{0}'")]
+    UnknownFunction(String),
+    #[error("Type error: {0}")]
+    Type(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::In => "in",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Value),
+    /// A dotted attribute path such as `subject.department`.
+    Path(Vec<String>),
+    List(Vec<Expr>),
+    Call(String, Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    pub fn literal(value: impl Into<Value>) -> Self {
+        Expr::new(ExprKind::Literal(value.into()), Span::default())
+    }
+
+    pub fn parse(source: &str) -> Result<Expr, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            end: source.len(),
+            depth: 0,
+        };
+
+        let expr = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(expr),
+            Some((token, span)) => Err(ParseError::new(
+                format!("Unexpected {}", token.describe()),
+                *span,
+            )),
+        }
+    }
+
+    /// Evaluates the expression, resolving attribute paths from the context.
+    pub fn evaluate(
+        &self,
+        context: &dyn Context,
+        functions: &Functions,
+    ) -> Result<Value, EvalError> {
+        match &self.kind {
+            ExprKind::Literal(value) => Ok(value.clone()),
+            ExprKind::Path(path) => context
+                .resolve(path)
+                .ok_or_else(|| EvalError::UnknownAttribute(path.join("."))),
+            ExprKind::List(items) => items
+                .iter()
+                .map(|item| item.evaluate(context, functions))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::List),
+            ExprKind::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(context, functions))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                functions.call(name, &args)
+            }
+            ExprKind::Not(inner) => Ok(Value::Bool(!inner.evaluate_bool(context, functions)?)),
+            ExprKind::And(left, right) => Ok(Value::Bool(
+                left.evaluate_bool(context, functions)?
+                    && right.evaluate_bool(context, functions)?,
+            )),
+            ExprKind::Or(left, right) => Ok(Value::Bool(
+                left.evaluate_bool(context, functions)?
+                    || right.evaluate_bool(context, functions)?,
+            )),
+            ExprKind::Compare(left, op, right) => {
+                let left = left.evaluate(context, functions)?;
+                let right = right.evaluate(context, functions)?;
+
+                compare(&left, *op, &right).map(Value::Bool)
+            }
+        }
+    }
+
+    /// Evaluates the expression, requiring the result to be a boolean.
+    pub fn evaluate_bool(
+        &self,
+        context: &dyn Context,
+        functions: &Functions,
+    ) -> Result<bool, EvalError> {
+        let value = self.evaluate(context, functions)?;
+
+        value.as_bool().ok_or_else(|| {
+            EvalError::Type(format!(
+                "expected bool but '{}' is {}",
+                self,
+                value.type_name()
+            ))
+        })
+    }
+
+    /// Moves every span in the expression along by `offset` bytes, for expressions parsed from
+    /// part of a larger source.
+    pub fn offset(mut self, offset: usize) -> Expr {
+        self.span = self.span.offset(offset);
+        self.kind = match self.kind {
+            ExprKind::List(items) => {
+                ExprKind::List(items.into_iter().map(|e| e.offset(offset)).collect())
+            }
+            ExprKind::Call(name, args) => {
+                ExprKind::Call(name, args.into_iter().map(|e| e.offset(offset)).collect())
+            }
+            ExprKind::Not(inner) => ExprKind::Not(Box::new(inner.offset(offset))),
+            ExprKind::And(left, right) => ExprKind::And(
+                Box::new(left.offset(offset)),
+                Box::new(right.offset(offset)),
+            ),
+            ExprKind::Or(left, right) => ExprKind::Or(
+                Box::new(left.offset(offset)),
+                Box::new(right.offset(offset)),
+            ),
+            ExprKind::Compare(left, op, right) => ExprKind::Compare(
+                Box::new(left.offset(offset)),
+                op,
+                Box::new(right.offset(offset)),
+            ),
+            kind => kind,
+        };
+
+        self
+    }
+
+    /// Calls `f` on every attribute path in the expression.
+    pub fn visit_paths(&self, f: &mut dyn FnMut(&[String], Span)) {
+        match &self.kind {
+            ExprKind::Literal(_) => {}
+            ExprKind::Path(path) => f(path, self.span),
+            ExprKind::List(items) | ExprKind::Call(_, items) => {
+                items.iter().for_each(|item| item.visit_paths(f))
+            }
+            ExprKind::Not(inner) => inner.visit_paths(f),
+            ExprKind::And(left, right)
+            | ExprKind::Or(left, right)
+            | ExprKind::Compare(left, _, right) => {
+                left.visit_paths(f);
+                right.visit_paths(f);
+            }
+        }
+    }
+}
+
+/// Applies a comparison operator to two evaluated values.
+pub fn compare(left: &Value, op: CompareOp, right: &Value) -> Result<bool, EvalError> {
+    let ordering = |left: &Value, right: &Value| {
+        left.compare(right).ok_or_else(|| {
+            EvalError::Type(format!(
+                "can't compare {} with {}",
+                left.type_name(),
+                right.type_name()
+            ))
+        })
+    };
+
+    match op {
+        CompareOp::Eq => Ok(left.loose_eq(right)),
+        CompareOp::Ne => Ok(!left.loose_eq(right)),
+        CompareOp::Lt => Ok(ordering(left, right)?.is_lt()),
+        CompareOp::Le => Ok(ordering(left, right)?.is_le()),
+        CompareOp::Gt => Ok(ordering(left, right)?.is_gt()),
+        CompareOp::Ge => Ok(ordering(left, right)?.is_ge()),
+        CompareOp::In => match (left, right) {
+            (_, Value::List(items)) => Ok(items.iter().any(|item| item.loose_eq(left))),
+            (Value::String(needle), Value::String(haystack)) => Ok(haystack.contains(needle)),
+            (Value::String(key), Value::Map(map)) => Ok(map.contains_key(key)),
+            _ => Err(EvalError::Type(format!(
+                "can't check whether {} is in {}",
+                left.type_name(),
+                right.type_name()
+            ))),
+        },
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn fmt_operand(expr: &Expr, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match expr.kind {
+                ExprKind::And(..) | ExprKind::Or(..) | ExprKind::Compare(..) => {
+                    write!(f, "({})", expr)
+                }
+                _ => write!(f, "{}", expr),
+            }
+        }
+
+        fn fmt_list(items: &[Expr], f: &mut Formatter<'_>) -> std::fmt::Result {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            Ok(())
+        }
+
+        match &self.kind {
+            ExprKind::Literal(value) => write!(f, "{}", value),
+            ExprKind::Path(path) => write!(f, "{}", path.join(".")),
+            ExprKind::List(items) => {
+                write!(f, "[")?;
+                fmt_list(items, f)?;
+                write!(f, "]")
+            }
+            ExprKind::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                fmt_list(args, f)?;
+                write!(f, ")")
+            }
+            ExprKind::Not(inner) => {
+                write!(f, "!")?;
+                fmt_operand(inner, f)
+            }
+            ExprKind::And(left, right) => {
+                fmt_operand(left, f)?;
+                write!(f, " && ")?;
+                fmt_operand(right, f)
+            }
+            ExprKind::Or(left, right) => {
+                fmt_operand(left, f)?;
+                write!(f, " || ")?;
+                fmt_operand(right, f)
+            }
+            ExprKind::Compare(left, op, right) => {
+                fmt_operand(left, f)?;
+                write!(f, " {} ", op)?;
+                fmt_operand(right, f)
+            }
+        }
+    }
+}
+
+/// Resolves attribute paths to values during evaluation.
+pub trait Context {
+    fn resolve(&self, path: &[String]) -> Option<Value>;
+}
+
+impl Context for HashMap<String, Value> {
+    fn resolve(&self, path: &[String]) -> Option<Value> {
+        let (first, rest) = path.split_first()?;
+
+        resolve_fields(self.get(first)?, rest)
+    }
+}
+
+/// Follows the remaining fields of a path through nested map values.
+pub fn resolve_fields(value: &Value, fields: &[String]) -> Option<Value> {
+    let mut value = value;
+
+    for field in fields {
+        value = value.get(field)?;
+    }
+
+    Some(value.clone())
+}
+
+type Function = Arc<dyn Fn(&[Value]) -> Result<Value, EvalError> + Send + Sync>;
+
+/// The functions that can be called from expressions. A set of built-in functions is always
+/// available: `contains`, `starts_with`, `ends_with`, `len`, `lower` and `upper`.
+#[derive(Clone)]
+pub struct Functions {
+    functions: HashMap<String, Function>,
+}
+
+impl Functions {
+    pub fn new() -> Self {
+        let functions = Functions {
+            functions: HashMap::new(),
+        };
+
+        functions
+            .with("contains", |args| match args {
+                [haystack, needle] => compare(needle, CompareOp::In, haystack).map(Value::Bool),
+                _ => Err(arity("contains", 2)),
+            })
+            .with("starts_with", |args| match args {
+                [Value::String(s), Value::String(prefix)] => Ok(Value::Bool(s.starts_with(prefix))),
+                _ => Err(EvalError::Type(
+                    "starts_with expects two strings".to_string(),
+                )),
+            })
+            .with("ends_with", |args| match args {
+                [Value::String(s), Value::String(suffix)] => Ok(Value::Bool(s.ends_with(suffix))),
+                _ => Err(EvalError::Type("ends_with expects two strings".to_string())),
+            })
+            .with("len", |args| match args {
+                [Value::String(s)] => Ok(Value::Int(s.chars().count() as i64)),
+                [Value::List(items)] => Ok(Value::Int(items.len() as i64)),
+                [Value::Map(map)] => Ok(Value::Int(map.len() as i64)),
+                _ => Err(EvalError::Type(
+                    "len expects a string, list or map".to_string(),
+                )),
+            })
+            .with("lower", |args| match args {
+                [Value::String(s)] => Ok(Value::String(s.to_lowercase())),
+                _ => Err(EvalError::Type("lower expects a string".to_string())),
+            })
+            .with("upper", |args| match args {
+                [Value::String(s)] => Ok(Value::String(s.to_uppercase())),
+                _ => Err(EvalError::Type("upper expects a string".to_string())),
+            })
+    }
+
+    /// Registers a function, replacing any existing function with the same name.
+    pub fn with<F>(mut self, name: impl Into<String>, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, EvalError> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Arc::new(function));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, EvalError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+
+        function(args)
+    }
+}
+
+impl Default for Functions {
+    fn default() -> Self {
+        Functions::new()
+    }
+}
+
+fn arity(name: &str, expected: usize) -> EvalError {
+    EvalError::Type(format!("{} expects {} arguments", name, expected))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Dot,
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("'{}'", name),
+            Token::Str(s) => format!("string {:?}", s),
+            Token::Int(i) => format!("number {}", i),
+            Token::Float(x) => format!("number {}", x),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::LBracket => "'['".to_string(),
+            Token::RBracket => "']'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Dot => "'.'".to_string(),
+            Token::Op(op) => format!("'{}'", op),
+            Token::And => "'&&'".to_string(),
+            Token::Or => "'||'".to_string(),
+            Token::Not => "'!'".to_string(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut two_char = |second: char, token: Token, single: Option<Token>| {
+            chars.next();
+
+            match chars.peek() {
+                Some(&(_, c)) if c == second => {
+                    chars.next();
+                    Ok((token, Span::new(start, start + 2)))
+                }
+                _ => single
+                    .map(|t| (t, Span::new(start, start + 1)))
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            format!("Expected '{}' after '{}'", second, c),
+                            Span::new(start, start + 1),
+                        )
+                    }),
+            }
+        };
+
+        let token = match c {
+            '(' | ')' | '[' | ']' | ',' | '.' => {
+                chars.next();
+                let token = match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    ',' => Token::Comma,
+                    _ => Token::Dot,
+                };
+                (token, Span::new(start, start + 1))
+            }
+            '=' => two_char('=', Token::Op(CompareOp::Eq), None)?,
+            '!' => two_char('=', Token::Op(CompareOp::Ne), Some(Token::Not))?,
+            '<' => two_char(
+                '=',
+                Token::Op(CompareOp::Le),
+                Some(Token::Op(CompareOp::Lt)),
+            )?,
+            '>' => two_char(
+                '=',
+                Token::Op(CompareOp::Ge),
+                Some(Token::Op(CompareOp::Gt)),
+            )?,
+            '&' => two_char('&', Token::And, None)?,
+            '|' => two_char('|', Token::Or, None)?,
+            '"' | '\'' => {
+                let quote = c;
+                chars.next();
+                let mut value = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some((end, c)) if c == quote => {
+                            break (Token::Str(value), Span::new(start, end + 1));
+                        }
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, c)) => value.push(c),
+                            None => break Err(unterminated(start, source))?,
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => break Err(unterminated(start, source))?,
+                    }
+                }
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && source[start + 1..].starts_with(|c: char| c.is_ascii_digit())) =>
+            {
+                let mut end = start;
+                let mut is_float = false;
+
+                // Negative numbers are literals, as there is no arithmetic to negate anything else
+                if c == '-' {
+                    end += 1;
+                    chars.next();
+                }
+
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_digit() || c == '_' {
+                        end = i + 1;
+                        chars.next();
+                    } else if c == '.'
+                        && !is_float
+                        && source[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+                    {
+                        is_float = true;
+                        end = i + 1;
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                let text = source[start..end].replace('_', "");
+                let span = Span::new(start, end);
+
+                if is_float {
+                    let value = text
+                        .parse()
+                        .map_err(|_| ParseError::new("Invalid number", span))?;
+                    (Token::Float(value), span)
+                } else {
+                    let value = text
+                        .parse()
+                        .map_err(|_| ParseError::new("Invalid number", span))?;
+                    (Token::Int(value), span)
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start;
+
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                let ident = &source[start..end];
+                let token = match ident {
+                    "in" => Token::Op(CompareOp::In),
+                    _ => Token::Ident(ident.to_string()),
+                };
+
+                (token, Span::new(start, end))
+            }
+            c => {
+                return Err(ParseError::new(
+                    format!("Unexpected character '{}'", c),
+                    Span::new(start, start + c.len_utf8()),
+                ))
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn unterminated(start: usize, source: &str) -> ParseError {
+    ParseError::new("Unterminated string", Span::new(start, source.len()))
+}
+
+/// How deeply `!`, parentheses, lists and calls may be nested, so that pathological input is
+/// rejected rather than overflowing the stack.
+const MAX_NESTING: usize = 64;
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    position: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Token, Span)> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<(Token, Span)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek().map(|(t, _)| t) == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<Span, ParseError> {
+        match self.next() {
+            Some((t, span)) if t == token => Ok(span),
+            Some((t, span)) => Err(ParseError::new(
+                format!("Expected {} but found {}", token.describe(), t.describe()),
+                span,
+            )),
+            None => Err(self.unexpected_end(&format!("Expected {}", token.describe()))),
+        }
+    }
+
+    fn unexpected_end(&self, message: &str) -> ParseError {
+        ParseError::new(
+            format!("{} but reached end of expression", message),
+            Span::new(self.end, self.end),
+        )
+    }
+
+    fn nested<T>(
+        &mut self,
+        span: Span,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth == MAX_NESTING {
+            return Err(ParseError::new(
+                format!("Expression is nested more than {} levels deep", MAX_NESTING),
+                span,
+            ));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_and()?;
+
+        while self.eat(&Token::Or) {
+            let right = self.parse_and()?;
+            let span = left.span.to(right.span);
+            left = Expr::new(ExprKind::Or(Box::new(left), Box::new(right)), span);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_not()?;
+
+        while self.eat(&Token::And) {
+            let right = self.parse_not()?;
+            let span = left.span.to(right.span);
+            left = Expr::new(ExprKind::And(Box::new(left), Box::new(right)), span);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, ParseError> {
+        if let Some((Token::Not, span)) = self.peek() {
+            let span = *span;
+            self.position += 1;
+            let inner = self.nested(span, Self::parse_not)?;
+            let span = span.to(inner.span);
+
+            return Ok(Expr::new(ExprKind::Not(Box::new(inner)), span));
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
+        let left = self.parse_primary()?;
+
+        if let Some((Token::Op(op), _)) = self.peek() {
+            let op = *op;
+            self.position += 1;
+            let right = self.parse_primary()?;
+            let span = left.span.to(right.span);
+
+            return Ok(Expr::new(
+                ExprKind::Compare(Box::new(left), op, Box::new(right)),
+                span,
+            ));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let Some((token, span)) = self.next() else {
+            return Err(self.unexpected_end("Expected a value"));
+        };
+
+        match token {
+            Token::Str(s) => Ok(Expr::new(ExprKind::Literal(Value::String(s)), span)),
+            Token::Int(i) => Ok(Expr::new(ExprKind::Literal(Value::Int(i)), span)),
+            Token::Float(x) => Ok(Expr::new(ExprKind::Literal(Value::Float(x)), span)),
+            Token::LParen => {
+                let inner = self.nested(span, Self::parse_or)?;
+                let end = self.expect(Token::RParen)?;
+
+                Ok(Expr::new(inner.kind, span.to(end)))
+            }
+            Token::LBracket => {
+                let (items, end) = self.nested(span, |p| p.parse_list(Token::RBracket))?;
+
+                Ok(Expr::new(ExprKind::List(items), span.to(end)))
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Expr::new(ExprKind::Literal(Value::Bool(true)), span)),
+                "false" => Ok(Expr::new(ExprKind::Literal(Value::Bool(false)), span)),
+                "null" => Ok(Expr::new(ExprKind::Literal(Value::Null), span)),
+                _ if self.eat(&Token::LParen) => {
+                    let (args, end) = self.nested(span, |p| p.parse_list(Token::RParen))?;
+
+                    Ok(Expr::new(ExprKind::Call(ident, args), span.to(end)))
+                }
+                _ => {
+                    let mut path = vec![ident];
+                    let mut end = span;
+
+                    while self.eat(&Token::Dot) {
+                        match self.next() {
+                            Some((Token::Ident(field), field_span)) => {
+                                path.push(field);
+                                end = field_span;
+                            }
+                            Some((t, span)) => {
+                                return Err(ParseError::new(
+                                    format!("Expected attribute name but found {}", t.describe()),
+                                    span,
+                                ))
+                            }
+                            None => return Err(self.unexpected_end("Expected attribute name")),
+                        }
+                    }
+
+                    Ok(Expr::new(ExprKind::Path(path), span.to(end)))
+                }
+            },
+            t => Err(ParseError::new(
+                format!("Expected a value but found {}", t.describe()),
+                span,
+            )),
+        }
+    }
+
+    fn parse_list(&mut self, close: Token) -> Result<(Vec<Expr>, Span), ParseError> {
+        let mut items = Vec::new();
+
+        loop {
+            if let Some((t, span)) = self.peek() {
+                if *t == close {
+                    let span = *span;
+                    self.position += 1;
+                    return Ok((items, span));
+                }
+            }
+
+            items.push(self.parse_or()?);
+
+            if !self.eat(&Token::Comma) {
+                let span = self.expect(close)?;
+                return Ok((items, span));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> HashMap<String, Value> {
+        HashMap::from([
+            (
+                "subject".to_string(),
+                Value::Map(
+                    [
+                        ("department".to_string(), Value::from("sales")),
+                        ("level".to_string(), Value::from(3)),
+                        ("teams".to_string(), Value::from(vec!["a", "b"])),
+                    ]
+                    .into(),
+                ),
+            ),
+            (
+                "resource".to_string(),
+                Value::Map(
+                    [
+                        ("department".to_string(), Value::from("sales")),
+                        ("status".to_string(), Value::from("draft")),
+                        ("amount".to_string(), Value::from(99.5)),
+                    ]
+                    .into(),
+                ),
+            ),
+        ])
+    }
+
+    fn eval(source: &str) -> Result<Value, EvalError> {
+        Expr::parse(source)
+            .unwrap()
+            .evaluate(&context(), &Functions::new())
+    }
+
+    #[test]
+    fn evaluate() {
+        assert_eq!(
+            eval(r#"subject.department == resource.department && resource.status == "draft""#),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval(r#"resource.status in ["sent", 'paid'] || subject.level >= 3"#),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval("!(resource.amount < 100) || 'c' in subject.teams"),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(eval("subject.level == 3.0"), Ok(Value::Bool(true)));
+        assert_eq!(
+            eval("resource.amount > -100 && subject.level > -2.5"),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval(r#"contains(subject.teams, "b") && len(subject.teams) == 2"#),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            eval("subject.missing == 1"),
+            Err(EvalError::UnknownAttribute("subject.missing".to_string()))
+        );
+        assert!(matches!(
+            eval("subject.level < 'x'"),
+            Err(EvalError::Type(_))
+        ));
+        assert!(matches!(
+            eval("subject.level && true"),
+            Err(EvalError::Type(_))
+        ));
+    }
+
+    #[test]
+    fn precedence_and_display() {
+        let expr = Expr::parse("a == 1 || b == 2 && !c").unwrap();
+        assert_eq!(expr.to_string(), "(a == 1) || ((b == 2) && !c)");
+
+        let reparsed = Expr::parse(&expr.to_string()).unwrap();
+        assert_eq!(reparsed.to_string(), expr.to_string());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = Expr::parse("subject.level >= ").unwrap_err();
+        assert_eq!(error.span, Span::new(17, 17));
+
+        let error = Expr::parse("subject. == 1").unwrap_err();
+        assert_eq!(error.message, "Expected attribute name but found '=='");
+        assert_eq!(error.span, Span::new(9, 11));
+
+        let error = Expr::parse("a = b").unwrap_err();
+        assert_eq!(error.span, Span::new(2, 3));
+
+        let error = Expr::parse("a == 'b").unwrap_err();
+        assert_eq!(error.message, "Unterminated string");
+
+        let error = Expr::parse("(a == b").unwrap_err();
+        assert_eq!(error.message, "Expected ')' but reached end of expression");
+
+        let error = Expr::parse("a == b c").unwrap_err();
+        assert_eq!(error.span, Span::new(7, 8));
+
+        let error = Expr::parse("a == - 1").unwrap_err();
+        assert_eq!(error.message, "Unexpected character '-'");
+    }
+
+    #[test]
+    fn negative_numbers() {
+        assert_eq!(
+            Expr::parse("-9223372036854775808").unwrap().kind,
+            ExprKind::Literal(Value::Int(i64::MIN))
+        );
+
+        let expr = Expr::parse("a > -1.5").unwrap();
+        assert_eq!(expr.to_string(), "a > -1.5");
+        assert_eq!(Expr::parse(&expr.to_string()).unwrap().kind, expr.kind);
+    }
+
+    #[test]
+    fn limit_nesting() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}a{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert!(Expr::parse(&nested("(", ")", MAX_NESTING)).is_ok());
+        assert!(Expr::parse(&nested("!", "", MAX_NESTING)).is_ok());
+
+        for source in [
+            nested("(", ")", 100_000),
+            nested("!", "", 100_000),
+            nested("[", "]", 100_000),
+            nested("f(", ")", 100_000),
+        ] {
+            let error = Expr::parse(&source).unwrap_err();
+            assert_eq!(
+                error.message,
+                "Expression is nested more than 64 levels deep"
+            );
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Attribute-based access control, where rules are conditions over attributes of the subject, the
+//! resource, the action and the environment the request is made in.
+
+pub mod expr;
+mod query;
+mod value;
+
+use crate::abac::expr::{resolve_fields, Context, Expr, Functions, ParseError};
+use crate::{Decision, Named, Policy, Resource, Subject};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+pub use value::Value;
+
+/// A resource that exposes attributes to ABAC rules, referred to as `resource.<name>`.
+pub trait AbacResource: Resource {
+    fn attribute(&self, name: &str) -> Option<Value>;
+}
+
+/// A subject that exposes attributes to ABAC rules, referred to as `subject.<name>`.
+pub trait AbacSubject: Subject {
+    fn attribute(&self, name: &str) -> Option<Value>;
+}
+
+/// Attributes of the environment a request is made in, such as the time or the client's IP
+/// address, referred to as `environment.<name>` or `env.<name>`.
+pub type Environment = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleEffect {
+    Permit,
+    Forbid,
+}
+
+/// A named condition that permits or forbids actions when it evaluates to true.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    name: String,
+    effect: RuleEffect,
+    actions: Option<HashSet<String>>,
+    condition: Expr,
+}
+
+impl Rule {
+    pub fn new(
+        name: impl Into<String>,
+        effect: RuleEffect,
+        condition: &str,
+    ) -> Result<Self, ParseError> {
+        Ok(Rule {
+            name: name.into(),
+            effect,
+            actions: None,
+            condition: Expr::parse(condition)?,
+        })
+    }
+
+    pub fn permit(name: impl Into<String>, condition: &str) -> Result<Self, ParseError> {
+        Rule::new(name, RuleEffect::Permit, condition)
+    }
+
+    pub fn forbid(name: impl Into<String>, condition: &str) -> Result<Self, ParseError> {
+        Rule::new(name, RuleEffect::Forbid, condition)
+    }
+
+    /// Restricts the rule to the named actions. By default a rule applies to every action.
+    pub fn for_actions<I, A>(mut self, actions: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        self.actions = Some(actions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn effect(&self) -> RuleEffect {
+        self.effect
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn applies_to(&self, action: &str) -> bool {
+        self.actions
+            .as_ref()
+            .is_none_or(|actions| actions.contains(action))
+    }
+}
+
+type EnvironmentProvider = Arc<dyn Fn() -> Environment + Send + Sync>;
+
+/// Policy made up of attribute rules. If any applicable forbid rule matches the action is denied,
+/// otherwise if any applicable permit rule matches it is permitted, and if no rule matches the
+/// policy is not applicable. A rule that fails to evaluate, for example because it refers to a
+/// missing attribute, denies the action.
+#[derive(Clone)]
+pub struct AbacPolicy {
+    rules: Vec<Rule>,
+    functions: Functions,
+    environment: EnvironmentProvider,
+}
+
+impl AbacPolicy {
+    pub fn new(rules: impl IntoIterator<Item = Rule>) -> Self {
+        AbacPolicy {
+            rules: rules.into_iter().collect(),
+            functions: Functions::new(),
+            environment: Arc::new(Environment::new),
+        }
+    }
+
+    /// Sets the function called on each decision to provide the environment attributes.
+    pub fn with_environment<F>(mut self, environment: F) -> Self
+    where
+        F: Fn() -> Environment + Send + Sync + 'static,
+    {
+        self.environment = Arc::new(environment);
+        self
+    }
+
+    /// Replaces the functions that rules may call.
+    pub fn with_functions(mut self, functions: Functions) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+struct RequestContext<'a, Res, Subj> {
+    resource: &'a Res,
+    subject: &'a Subj,
+    action: &'a str,
+    environment: Environment,
+}
+
+impl<Res, Subj> Context for RequestContext<'_, Res, Subj>
+where
+    Res: AbacResource,
+    Subj: AbacSubject,
+{
+    fn resolve(&self, path: &[String]) -> Option<Value> {
+        match path {
+            [root] if root == "action" => Some(Value::from(self.action)),
+            [root, name, fields @ ..] => {
+                let value = match root.as_str() {
+                    "subject" => self.subject.attribute(name)?,
+                    "resource" => self.resource.attribute(name)?,
+                    "environment" | "env" => self.environment.get(name)?.clone(),
+                    _ => return None,
+                };
+
+                resolve_fields(&value, fields)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<Res, Subj> Policy<Res, Subj> for AbacPolicy
+where
+    Res: AbacResource<Action: Named>,
+    Subj: AbacSubject,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let context = RequestContext {
+            resource,
+            subject,
+            action: action.name(),
+            environment: (self.environment)(),
+        };
+
+        let mut permitted_by = None;
+
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(context.action))
+        {
+            match rule.condition.evaluate_bool(&context, &self.functions) {
+                Ok(true) if rule.effect == RuleEffect::Forbid => {
+                    return Decision::deny(format!("Forbidden by condition {}", rule.condition))
+                        .with_rule(rule.name.clone());
+                }
+                Ok(true) => {
+                    permitted_by.get_or_insert(rule);
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    return Decision::deny(format!("Failed to evaluate condition: {}", e))
+                        .with_rule(rule.name.clone());
+                }
+            }
+        }
+
+        match permitted_by {
+            Some(rule) => Decision::permit(format!("Permitted by condition {}", rule.condition))
+                .with_rule(rule.name.clone()),
+            None => {
+                Decision::not_applicable(format!("No rule matched action '{}'", context.action))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Invoice {
+        department: &'static str,
+        status: &'static str,
+    }
+
+    impl Resource for Invoice {
+        type Action = InvoiceAction;
+    }
+
+    impl AbacResource for Invoice {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "department" => Some(self.department.into()),
+                "status" => Some(self.status.into()),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum InvoiceAction {
+        Read,
+        Edit,
+    }
+
+    impl Named for InvoiceAction {
+        fn name(&self) -> &str {
+            match self {
+                InvoiceAction::Read => "read",
+                InvoiceAction::Edit => "edit",
+            }
+        }
+    }
+
+    struct User {
+        department: &'static str,
+        suspended: bool,
+    }
+
+    impl Subject for User {}
+
+    impl AbacSubject for User {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "department" => Some(self.department.into()),
+                "suspended" => Some(self.suspended.into()),
+                _ => None,
+            }
+        }
+    }
+
+    fn policy() -> AbacPolicy {
+        AbacPolicy::new([
+            Rule::permit(
+                "edit-own-department-drafts",
+                r#"subject.department == resource.department && resource.status == "draft""#,
+            )
+            .unwrap()
+            .for_actions(["edit"]),
+            Rule::forbid("outside-hours", "env.hour < 8 || env.hour >= 18").unwrap(),
+            Rule::forbid("suspended", "subject.suspended").unwrap(),
+        ])
+        .with_environment(|| Environment::from([("hour".to_string(), Value::from(10))]))
+    }
+
+    #[test]
+    fn decide() {
+        let policy = policy();
+        let user = User {
+            department: "sales",
+            suspended: false,
+        };
+        let draft = Invoice {
+            department: "sales",
+            status: "draft",
+        };
+        let sent = Invoice {
+            department: "sales",
+            status: "sent",
+        };
+
+        let decision = policy.decide(&draft, &user, &InvoiceAction::Edit);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("edit-own-department-drafts"));
+
+        assert!(policy
+            .decide(&sent, &user, &InvoiceAction::Edit)
+            .is_not_applicable());
+        assert!(policy
+            .decide(&draft, &user, &InvoiceAction::Read)
+            .is_not_applicable());
+
+        let suspended = User {
+            department: "sales",
+            suspended: true,
+        };
+        let decision = policy.decide(&draft, &suspended, &InvoiceAction::Edit);
+        assert!(decision.is_deny());
+        assert_eq!(decision.rule(), Some("suspended"));
+
+        let after_hours = policy
+            .clone()
+            .with_environment(|| Environment::from([("hour".to_string(), Value::from(20))]));
+        let decision = after_hours.decide(&draft, &user, &InvoiceAction::Edit);
+        assert_eq!(decision.rule(), Some("outside-hours"));
+    }
+
+    #[test]
+    fn evaluation_errors_deny() {
+        let policy = AbacPolicy::new([Rule::permit("level", "subject.level > 2").unwrap()]);
+        let user = User {
+            department: "sales",
+            suspended: false,
+        };
+        let invoice = Invoice {
+            department: "sales",
+            status: "draft",
+        };
+
+        let decision = policy.decide(&invoice, &user, &InvoiceAction::Read);
+        assert!(decision.is_deny());
+        assert_eq!(
+            decision.reason(),
+            "Failed to evaluate condition: Unknown attribute 'subject.level'"
+        );
+    }
+}
@@ -0,0 +1,566 @@
+use crate::abac::expr::{compare, resolve_fields, CompareOp, EvalError, Expr, ExprKind, Functions};
+use crate::abac::{AbacPolicy, AbacSubject, Environment, RuleEffect, Value};
+use crate::query::{Operator, Predicate, QueryError, QueryPolicy, QueryResource, Residual};
+use crate::Named;
+use std::marker::PhantomData;
+
+/// The result of partially evaluating part of an expression.
+enum Partial {
+    Known(Value),
+    Column(String),
+    Condition(Condition),
+}
+
+/// A condition on the resource, split into where it holds and where evaluating it would fail,
+/// such as when ordering a null column. The two never both hold, and where neither does the
+/// condition is false.
+struct Condition {
+    holds: Predicate,
+    fails: Predicate,
+}
+
+impl Condition {
+    fn new(holds: Predicate) -> Self {
+        Condition {
+            holds,
+            fails: Predicate::False,
+        }
+    }
+
+    fn not(self) -> Self {
+        Condition {
+            holds: self.holds.not().and(self.fails.clone().not()),
+            fails: self.fails,
+        }
+    }
+
+    /// Like `&&` in memory, the right side is only evaluated where the left holds.
+    fn and(self, other: Condition) -> Self {
+        Condition {
+            fails: self.fails.or(self.holds.clone().and(other.fails)),
+            holds: self.holds.and(other.holds),
+        }
+    }
+
+    /// Like `||` in memory, the right side is only evaluated where the left is false.
+    fn or(self, other: Condition) -> Self {
+        Condition {
+            fails: self
+                .fails
+                .clone()
+                .or(self.holds.clone().not().and(other.fails)),
+            holds: self.holds.or(self.fails.not().and(other.holds)),
+        }
+    }
+}
+
+/// Partially evaluates expressions with the subject, action and environment known but the
+/// resource left as columns.
+struct PartialEvaluator<'a, Res, Subj> {
+    subject: &'a Subj,
+    action: &'a str,
+    environment: Environment,
+    functions: &'a Functions,
+    resource: PhantomData<Res>,
+}
+
+impl<Res, Subj> PartialEvaluator<'_, Res, Subj>
+where
+    Res: QueryResource,
+    Subj: AbacSubject,
+{
+    fn condition(&self, expr: &Expr) -> Result<Condition, QueryError> {
+        match self.evaluate(expr)? {
+            Partial::Known(Value::Bool(true)) => Ok(Condition::new(Predicate::True)),
+            Partial::Known(Value::Bool(false)) => Ok(Condition::new(Predicate::False)),
+            Partial::Known(value) => Err(EvalError::Type(format!(
+                "expected bool but '{}' is {}",
+                expr,
+                value.type_name()
+            ))
+            .into()),
+            // A null column isn't a bool, so fails to evaluate just as it would in memory
+            Partial::Column(column) => Ok(Condition {
+                holds: Predicate::eq(column.clone(), true),
+                fails: Predicate::eq(column, Value::Null),
+            }),
+            Partial::Condition(condition) => Ok(condition),
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<Partial, QueryError> {
+        match &expr.kind {
+            ExprKind::Literal(value) => Ok(Partial::Known(value.clone())),
+            ExprKind::Path(path) => self.resolve(path),
+            ExprKind::List(items) => items
+                .iter()
+                .map(|item| self.known(item, "lists can't contain resource attributes"))
+                .collect::<Result<_, _>>()
+                .map(|items| Partial::Known(Value::List(items))),
+            ExprKind::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.known(arg, "functions can't be called on resource attributes"))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Partial::Known(self.functions.call(name, &args)?))
+            }
+            ExprKind::Not(inner) => Ok(Partial::Condition(self.condition(inner)?.not())),
+            ExprKind::And(left, right) => Ok(Partial::Condition(
+                self.condition(left)?.and(self.condition(right)?),
+            )),
+            ExprKind::Or(left, right) => Ok(Partial::Condition(
+                self.condition(left)?.or(self.condition(right)?),
+            )),
+            ExprKind::Compare(left, op, right) => {
+                match (self.evaluate(left)?, self.evaluate(right)?) {
+                    (Partial::Known(left), Partial::Known(right)) => {
+                        Ok(Partial::Known(Value::Bool(compare(&left, *op, &right)?)))
+                    }
+                    (Partial::Column(column), Partial::Known(value)) => {
+                        column_condition(column, *op, value, false).map(Partial::Condition)
+                    }
+                    (Partial::Known(value), Partial::Column(column)) if *op != CompareOp::In => {
+                        column_condition(column, *op, value, true).map(Partial::Condition)
+                    }
+                    _ => Err(QueryError::Unsupported(format!(
+                        "'{}' compares resource attributes in a way that can't be expressed as a \
+                         column condition",
+                        expr
+                    ))),
+                }
+            }
+        }
+    }
+
+    fn known(&self, expr: &Expr, message: &str) -> Result<Value, QueryError> {
+        match self.evaluate(expr)? {
+            Partial::Known(value) => Ok(value),
+            _ => Err(QueryError::Unsupported(format!(
+                "{} in '{}'",
+                message, expr
+            ))),
+        }
+    }
+
+    fn resolve(&self, path: &[String]) -> Result<Partial, QueryError> {
+        let unknown = || EvalError::UnknownAttribute(path.join("."));
+
+        match path {
+            [root] if root == "action" => Ok(Partial::Known(Value::from(self.action))),
+            [root, fields @ ..] if root == "resource" && !fields.is_empty() => {
+                let attribute = fields.join(".");
+
+                Res::column(&attribute)
+                    .map(Partial::Column)
+                    .ok_or(QueryError::UnknownColumn(attribute))
+            }
+            [root, name, fields @ ..] => {
+                let value = match root.as_str() {
+                    "subject" => self.subject.attribute(name),
+                    "environment" | "env" => self.environment.get(name).cloned(),
+                    _ => None,
+                }
+                .ok_or_else(unknown)?;
+
+                resolve_fields(&value, fields)
+                    .map(Partial::Known)
+                    .ok_or_else(|| unknown().into())
+            }
+            _ => Err(unknown().into()),
+        }
+    }
+}
+
+/// Builds the condition for comparing a column with a known value, where `swapped` means the
+/// value was on the left of the comparison. A null column is handled as [`compare`] handles
+/// [`Value::Null`]: it equals only null and can't be ordered.
+fn column_condition(
+    column: String,
+    op: CompareOp,
+    value: Value,
+    swapped: bool,
+) -> Result<Condition, QueryError> {
+    let op = match op {
+        CompareOp::In => {
+            return match value {
+                Value::List(values) => {
+                    let (nulls, values): (Vec<_>, Vec<_>) =
+                        values.into_iter().partition(|value| *value == Value::Null);
+                    let null = if nulls.is_empty() {
+                        Predicate::False
+                    } else {
+                        Predicate::eq(column.clone(), Value::Null)
+                    };
+
+                    Ok(Condition::new(Predicate::is_in(column, values).or(null)))
+                }
+                value => Err(QueryError::Unsupported(format!(
+                    "can't check whether {} is in {}",
+                    column,
+                    value.type_name()
+                ))),
+            }
+        }
+        CompareOp::Eq => Operator::Eq,
+        CompareOp::Ne => Operator::Ne,
+        CompareOp::Lt => Operator::Lt,
+        CompareOp::Le => Operator::Le,
+        CompareOp::Gt => Operator::Gt,
+        CompareOp::Ge => Operator::Ge,
+    };
+
+    match value {
+        Value::List(_) | Value::Map(_) => Err(QueryError::Unsupported(format!(
+            "can't compare {} with {}",
+            column,
+            value.type_name()
+        ))),
+        Value::Null if matches!(op, Operator::Eq | Operator::Ne) => {
+            Ok(Condition::new(Predicate::compare(column, op, Value::Null)))
+        }
+        Value::Null => Ok(Condition {
+            holds: Predicate::False,
+            fails: Predicate::True,
+        }),
+        // Unlike the predicate, a null column is unequal to every other value
+        value if op == Operator::Ne => Ok(Condition::new(Predicate::eq(column, value).not())),
+        value if op == Operator::Eq => Ok(Condition::new(Predicate::eq(column, value))),
+        value => {
+            let op = if swapped { op.flip() } else { op };
+
+            Ok(Condition {
+                holds: Predicate::compare(column.clone(), op, value),
+                fails: Predicate::eq(column, Value::Null),
+            })
+        }
+    }
+}
+
+impl<Res, Subj> QueryPolicy<Res, Subj> for AbacPolicy
+where
+    Res: QueryResource<Action: Named>,
+    Subj: AbacSubject,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let evaluator = PartialEvaluator::<Res, Subj> {
+            subject,
+            action: action.name(),
+            environment: (self.environment)(),
+            functions: &self.functions,
+            resource: PhantomData,
+        };
+
+        let mut permit = Vec::new();
+        let mut forbid = Vec::new();
+        let mut fails = Vec::new();
+
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(evaluator.action))
+        {
+            let condition = evaluator.condition(&rule.condition)?;
+
+            match rule.effect {
+                RuleEffect::Permit => permit.push(condition.holds),
+                RuleEffect::Forbid => forbid.push(condition.holds),
+            }
+            fails.push(condition.fails);
+        }
+
+        // Rules that fail to evaluate deny, as they do in memory
+        let deny = Predicate::any(forbid).or(Predicate::any(fails));
+        let permit = Predicate::any(permit).and(deny.clone().not());
+
+        Ok(Residual::new(permit, deny))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abac::{AbacResource, Rule};
+    use crate::{Policy, Resource, Subject};
+
+    struct Document {
+        owner: Value,
+        team: Value,
+        status: Value,
+        archived: Value,
+        amount: Value,
+    }
+
+    impl Resource for Document {
+        type Action = DocumentAction;
+    }
+
+    impl AbacResource for Document {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "owner" => Some(self.owner.clone()),
+                "team" => Some(self.team.clone()),
+                "status" => Some(self.status.clone()),
+                "archived" => Some(self.archived.clone()),
+                "amount" => Some(self.amount.clone()),
+                _ => None,
+            }
+        }
+    }
+
+    impl QueryResource for Document {
+        fn column(attribute: &str) -> Option<String> {
+            match attribute {
+                "owner" => Some("owner_id".to_string()),
+                "team" => Some("team_id".to_string()),
+                "status" | "archived" | "amount" => Some(attribute.to_string()),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum DocumentAction {
+        Read,
+        Edit,
+    }
+
+    impl Named for DocumentAction {
+        fn name(&self) -> &str {
+            match self {
+                DocumentAction::Read => "read",
+                DocumentAction::Edit => "edit",
+            }
+        }
+    }
+
+    struct User {
+        id: i64,
+        teams: Vec<i64>,
+        admin: bool,
+    }
+
+    impl Subject for User {}
+
+    impl AbacSubject for User {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "id" => Some(self.id.into()),
+                "teams" => Some(self.teams.clone().into()),
+                "admin" => Some(self.admin.into()),
+                _ => None,
+            }
+        }
+    }
+
+    fn policy() -> AbacPolicy {
+        AbacPolicy::new([
+            Rule::permit(
+                "owner-or-team",
+                "resource.owner == subject.id || resource.team in subject.teams",
+            )
+            .unwrap(),
+            Rule::permit("admin", "subject.admin").unwrap(),
+            Rule::forbid("archived", "resource.archived")
+                .unwrap()
+                .for_actions(["edit"]),
+        ])
+    }
+
+    #[test]
+    fn partially_evaluate() {
+        let user = User {
+            id: 7,
+            teams: vec![1, 2],
+            admin: false,
+        };
+
+        let read = QueryPolicy::<Document, _>::query(&policy(), &user, &DocumentAction::Read);
+        assert_eq!(
+            read.unwrap().to_string(),
+            "owner_id = 7 OR team_id IN (1, 2)"
+        );
+
+        let edit = QueryPolicy::<Document, _>::query(&policy(), &user, &DocumentAction::Edit);
+        assert_eq!(
+            edit.unwrap().to_string(),
+            "(owner_id = 7 OR team_id IN (1, 2)) AND NOT (archived = TRUE OR archived IS NULL)"
+        );
+
+        let admin = User {
+            id: 1,
+            teams: vec![],
+            admin: true,
+        };
+        let read = QueryPolicy::<Document, _>::query(&policy(), &admin, &DocumentAction::Read);
+        assert_eq!(read.unwrap(), Predicate::True);
+    }
+
+    #[test]
+    fn null_columns_behave_as_in_memory() {
+        let user = User {
+            id: 7,
+            teams: vec![1, 2],
+            admin: false,
+        };
+        let policy = AbacPolicy::new([
+            Rule::forbid("published", "resource.status != \"draft\"").unwrap(),
+            Rule::forbid("large", "resource.amount > 1000").unwrap(),
+        ]);
+
+        // A null status isn't "draft", and a null amount can't be compared so fails closed
+        let residual =
+            QueryPolicy::<Document, _>::residual(&policy, &user, &DocumentAction::Read).unwrap();
+        assert_eq!(
+            residual.deny.to_string(),
+            "NOT (status = 'draft') OR amount > 1000 OR amount IS NULL"
+        );
+    }
+
+    #[test]
+    fn unsupported_conditions() {
+        let user = User {
+            id: 7,
+            teams: vec![],
+            admin: false,
+        };
+
+        let policy = AbacPolicy::new([Rule::permit("secret", "resource.secret == 1").unwrap()]);
+        let result = QueryPolicy::<Document, _>::query(&policy, &user, &DocumentAction::Read);
+        assert!(matches!(result, Err(QueryError::UnknownColumn(c)) if c == "secret"));
+
+        let policy =
+            AbacPolicy::new([Rule::permit("same", "resource.owner == resource.team").unwrap()]);
+        let result = QueryPolicy::<Document, _>::query(&policy, &user, &DocumentAction::Read);
+        assert!(matches!(result, Err(QueryError::Unsupported(_))));
+    }
+
+    /// Runs the same rules in memory and as SQL over every combination of values, including
+    /// nulls, and checks that they agree on which documents are permitted and denied.
+    #[cfg(feature = "sea-query")]
+    #[test]
+    fn sql_agrees_with_memory() {
+        use ::sea_query::{Alias, Order, Query, SqliteQueryBuilder};
+        use rusqlite::types::Value as SqlValue;
+        use rusqlite::Connection;
+
+        fn sql_value(value: &Value) -> SqlValue {
+            match value {
+                Value::Null => SqlValue::Null,
+                Value::Bool(b) => SqlValue::Integer(*b as i64),
+                Value::Int(i) => SqlValue::Integer(*i),
+                Value::String(s) => SqlValue::Text(s.clone()),
+                value => panic!("unexpected value {}", value),
+            }
+        }
+
+        fn select(connection: &Connection, predicate: &Predicate) -> Vec<usize> {
+            let sql = Query::select()
+                .column(Alias::new("id"))
+                .from(Alias::new("document"))
+                .and_where(predicate.to_sea_query())
+                .order_by(Alias::new("id"), Order::Asc)
+                .to_string(SqliteQueryBuilder);
+
+            let mut statement = connection.prepare(&sql).unwrap();
+            let ids = statement.query_map([], |row| row.get(0)).unwrap();
+
+            ids.collect::<Result<_, _>>().unwrap()
+        }
+
+        let policy = AbacPolicy::new([
+            Rule::permit(
+                "owner-or-team",
+                "resource.owner == subject.id || resource.team in subject.teams",
+            )
+            .unwrap(),
+            Rule::permit(
+                "small-and-published",
+                "resource.amount <= 100 && resource.status == \"published\"",
+            )
+            .unwrap(),
+            Rule::forbid(
+                "large-unless-draft",
+                "resource.status != \"draft\" && !(resource.amount <= 1000)",
+            )
+            .unwrap(),
+            Rule::forbid(
+                "archived",
+                "resource.archived || resource.team in [null, 9]",
+            )
+            .unwrap(),
+        ]);
+        let user = User {
+            id: 7,
+            teams: vec![1, 2],
+            admin: false,
+        };
+
+        let owners = [Value::Int(7), Value::Int(8), Value::Null];
+        let teams = [Value::Int(1), Value::Int(9), Value::Null];
+        let statuses = [Value::from("draft"), Value::from("published"), Value::Null];
+        let archived = [Value::Bool(true), Value::Bool(false), Value::Null];
+        let amounts = [Value::Int(50), Value::Int(5000), Value::Null];
+
+        let mut documents = Vec::new();
+        for owner in &owners {
+            for team in &teams {
+                for status in &statuses {
+                    for archived in &archived {
+                        for amount in &amounts {
+                            documents.push(Document {
+                                owner: owner.clone(),
+                                team: team.clone(),
+                                status: status.clone(),
+                                archived: archived.clone(),
+                                amount: amount.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute(
+                "CREATE TABLE document (id INTEGER PRIMARY KEY, owner_id INTEGER, \
+                 team_id INTEGER, status TEXT, archived BOOLEAN, amount INTEGER)",
+                [],
+            )
+            .unwrap();
+        for (id, document) in documents.iter().enumerate() {
+            connection
+                .execute(
+                    "INSERT INTO document VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![
+                        id,
+                        sql_value(&document.owner),
+                        sql_value(&document.team),
+                        sql_value(&document.status),
+                        sql_value(&document.archived),
+                        sql_value(&document.amount),
+                    ],
+                )
+                .unwrap();
+        }
+
+        let residual =
+            QueryPolicy::<Document, _>::residual(&policy, &user, &DocumentAction::Read).unwrap();
+        let decisions: Vec<_> = documents
+            .iter()
+            .map(|document| policy.decide(document, &user, &DocumentAction::Read))
+            .collect();
+
+        let permitted: Vec<_> = (0..documents.len())
+            .filter(|&id| decisions[id].is_permit())
+            .collect();
+        let denied: Vec<_> = (0..documents.len())
+            .filter(|&id| decisions[id].is_deny())
+            .collect();
+
+        assert!(!permitted.is_empty() && !denied.is_empty());
+        assert_eq!(select(&connection, &residual.permit), permitted);
+        assert_eq!(select(&connection, &residual.deny), denied);
+    }
+}
@@ -0,0 +1,91 @@
+//! Asynchronous policies, for decisions that need to look things up in a database or a remote
+//! service before they can be made.
+
+use crate::{Decision, Error, Policy, Resource, Subject};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// The asynchronous counterpart of [`Policy`].
+#[async_trait]
+pub trait AsyncPolicy<Res, Subj>: Send + Sync
+where
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+{
+    /// Evaluates the policy, returning a [`Decision`] that explains the outcome.
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision;
+
+    /// Evaluates the policy, treating anything other than an explicit permit as forbidden.
+    async fn authorise(
+        &self,
+        resource: &Res,
+        subject: &Subj,
+        action: &Res::Action,
+    ) -> Result<(), Error> {
+        self.decide(resource, subject, action).await.into_result()
+    }
+}
+
+/// Adapts a synchronous [`Policy`] so it can be used wherever an [`AsyncPolicy`] is expected,
+/// including combined policies such as [`AnyOf`](crate::combinator::AnyOf).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FromSync<P>(pub P);
+
+impl<P> FromSync<P> {
+    pub fn new(policy: P) -> Self {
+        FromSync(policy)
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, P> AsyncPolicy<Res, Subj> for FromSync<P>
+where
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+    P: Policy<Res, Subj> + Send + Sync,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        self.0.decide(resource, subject, action)
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, P> AsyncPolicy<Res, Subj> for &P
+where
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+    P: AsyncPolicy<Res, Subj> + ?Sized,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action).await
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, P> AsyncPolicy<Res, Subj> for Box<P>
+where
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+    P: AsyncPolicy<Res, Subj> + ?Sized,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action).await
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, P> AsyncPolicy<Res, Subj> for Arc<P>
+where
+    Res: Resource + Sync,
+    Res::Action: Sync,
+    Subj: Subject + Sync,
+    P: AsyncPolicy<Res, Subj> + ?Sized,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action).await
+    }
+}
@@ -0,0 +1,159 @@
+use crate::audit::{AuditError, AuditRecord, AuditSink};
+use parking_lot::Mutex;
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes each record as a line of JSON, flushing after every record:
+///
+/// ```json
+/// {"timestamp":"2024-05-01T09:30:00.125Z","request_id":"req-1","subject":"alice","resource_type":"invoice","resource":"7","action":"approve","effect":"Permit","rule":"managers","reason":"Managers approve invoices"}
+/// ```
+pub struct JsonLinesSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W> JsonLinesSink<W>
+where
+    W: Write + Send,
+{
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+impl JsonLinesSink<File> {
+    /// Opens a file to append records to, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(JsonLinesSink::new(file))
+    }
+}
+
+impl<W> AuditSink for JsonLinesSink<W>
+where
+    W: Write + Send,
+{
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        let mut line = serde_json::to_vec(&json!({
+            "timestamp": format_timestamp(record.timestamp),
+            "request_id": record.request_id,
+            "subject": record.subject,
+            "resource_type": record.resource_type,
+            "resource": record.resource,
+            "action": record.action,
+            "effect": format!("{:?}", record.decision.effect()),
+            "rule": record.decision.rule(),
+            "reason": record.decision.reason(),
+        }))?;
+        line.push(b'\n');
+
+        // Written in one call so concurrent records can't interleave
+        let mut writer = self.writer.lock();
+        writer.write_all(&line)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with milliseconds.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Converts days since the epoch to a civil date, from Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decision;
+    use std::time::Duration;
+
+    #[test]
+    fn write_json_lines() {
+        let sink = JsonLinesSink::new(Vec::new());
+        let record = AuditRecord {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_709_210_096_789),
+            request_id: None,
+            subject: "alice".to_string(),
+            resource_type: "invoice".to_string(),
+            resource: "7".to_string(),
+            action: "approve".to_string(),
+            decision: Decision::deny("Only managers approve invoices"),
+        };
+
+        sink.record(&record).unwrap();
+        sink.record(&record).unwrap();
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["timestamp"], "2024-02-29T12:34:56.789Z");
+        assert_eq!(value["request_id"], serde_json::Value::Null);
+        assert_eq!(value["effect"], "Deny");
+        assert_eq!(value["reason"], "Only managers approve invoices");
+    }
+
+    #[test]
+    fn format_edge_dates() {
+        let at = |seconds: u64, millis: u64| {
+            format_timestamp(UNIX_EPOCH + Duration::from_millis(seconds * 1_000 + millis))
+        };
+
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(946_684_799, 999), "1999-12-31T23:59:59.999Z");
+        // 2000 is a leap year despite being a century, as it is divisible by 400
+        assert_eq!(at(951_782_400, 0), "2000-02-29T00:00:00.000Z");
+        assert_eq!(at(951_868_800, 0), "2000-03-01T00:00:00.000Z");
+        assert_eq!(at(2_147_483_648, 0), "2038-01-19T03:14:08.000Z");
+        // 2100 isn't, so February ends on the 28th
+        assert_eq!(at(4_107_499_200, 0), "2100-02-28T12:00:00.000Z");
+        assert_eq!(at(4_107_542_400, 0), "2100-03-01T00:00:00.000Z");
+        assert_eq!(at(13_574_563_200, 0), "2400-02-29T00:00:00.000Z");
+
+        // Times before the epoch can't be represented, so are clamped to it
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01T00:00:00.000Z"
+        );
+    }
+}
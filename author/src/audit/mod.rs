@@ -0,0 +1,358 @@
+//! Auditing of policy decisions, recording who was allowed or refused to do what, when and why.
+//!
+//! Wrap the policies for sensitive resources in an [`AuditedPolicy`], which passes a record of
+//! every decision to an [`AuditSink`]. Sinks are provided for `tracing` (with the `tracing`
+//! feature), for JSON lines files (with the `json` feature) and for keeping records in memory in
+//! tests.
+
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "json")]
+pub use json::JsonLinesSink;
+
+#[cfg(feature = "async")]
+use crate::AsyncPolicy;
+use crate::{Decision, Named, NamedResource, Policy, Subject};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::SystemTime;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Failed to write audit record: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "json")]
+    #[error("Failed to encode audit record: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Identifies a subject or resource in audit records.
+pub trait Auditable {
+    /// An identifier such as a user id or a record's primary key.
+    fn audit_id(&self) -> String;
+}
+
+/// A record of a single decision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub timestamp: SystemTime,
+    /// Identifies the request the decision was made for, if a request id provider is set.
+    pub request_id: Option<String>,
+    pub subject: String,
+    /// The resource's [`RESOURCE_NAME`](NamedResource::RESOURCE_NAME).
+    pub resource_type: String,
+    pub resource: String,
+    pub action: String,
+    pub decision: Decision,
+}
+
+/// Somewhere audit records are sent.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError>;
+}
+
+impl<S> AuditSink for &S
+where
+    S: AuditSink + ?Sized,
+{
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        (**self).record(record)
+    }
+}
+
+impl<S> AuditSink for Box<S>
+where
+    S: AuditSink + ?Sized,
+{
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        (**self).record(record)
+    }
+}
+
+impl<S> AuditSink for Arc<S>
+where
+    S: AuditSink + ?Sized,
+{
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        (**self).record(record)
+    }
+}
+
+type RequestIdProvider = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Wraps a policy, sending a record of each of its decisions to a sink.
+///
+/// Resources are recorded by their [`NamedResource`] name and [`Auditable`] id.
+///
+/// If a record can't be written, a permit is turned into a deny, so that nothing is allowed
+/// without a record of it.
+pub struct AuditedPolicy<P, S> {
+    policy: P,
+    sink: S,
+    request_id: RequestIdProvider,
+}
+
+impl<P, S> AuditedPolicy<P, S>
+where
+    S: AuditSink,
+{
+    pub fn new(policy: P, sink: S) -> Self {
+        AuditedPolicy {
+            policy,
+            sink,
+            request_id: Arc::new(|| None),
+        }
+    }
+
+    /// Sets the function called on each decision to find the id of the current request, for
+    /// example from a task-local or the current `tracing` span.
+    pub fn with_request_id<F>(mut self, request_id: F) -> Self
+    where
+        F: Fn() -> Option<String> + Send + Sync + 'static,
+    {
+        self.request_id = Arc::new(request_id);
+        self
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    fn audit<Res, Subj>(
+        &self,
+        resource: &Res,
+        subject: &Subj,
+        action: &Res::Action,
+        decision: Decision,
+    ) -> Decision
+    where
+        Res: NamedResource<Action: Named> + Auditable,
+        Subj: Auditable,
+    {
+        let record = AuditRecord {
+            timestamp: SystemTime::now(),
+            request_id: (self.request_id)(),
+            subject: subject.audit_id(),
+            resource_type: Res::RESOURCE_NAME.to_string(),
+            resource: resource.audit_id(),
+            action: action.name().to_string(),
+            decision,
+        };
+
+        match self.sink.record(&record) {
+            Ok(()) => record.decision,
+            Err(e) if record.decision.is_permit() => Decision::deny(format!(
+                "Denied because the decision couldn't be audited: {}",
+                e
+            )),
+            Err(_) => record.decision,
+        }
+    }
+}
+
+impl<Res, Subj, P, S> Policy<Res, Subj> for AuditedPolicy<P, S>
+where
+    Res: NamedResource<Action: Named> + Auditable,
+    Subj: Subject + Auditable,
+    P: Policy<Res, Subj>,
+    S: AuditSink,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let decision = self.policy.decide(resource, subject, action);
+        self.audit(resource, subject, action, decision)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<Res, Subj, P, S> AsyncPolicy<Res, Subj> for AuditedPolicy<P, S>
+where
+    Res: NamedResource<Action: Named + Sync> + Auditable + Sync,
+    Subj: Subject + Auditable + Sync,
+    P: AsyncPolicy<Res, Subj>,
+    S: AuditSink,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let decision = self.policy.decide(resource, subject, action).await;
+        self.audit(resource, subject, action, decision)
+    }
+}
+
+/// Keeps records in memory, for checking what was audited in tests. Clones share the same
+/// records.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    records: Arc<Mutex<Vec<AuditRecord>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().clone()
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().clear()
+    }
+}
+
+impl AuditSink for MemorySink {
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        self.records.lock().push(record.clone());
+        Ok(())
+    }
+}
+
+/// Emits each record as a `tracing` event with the target `author::audit`, at `INFO` level for
+/// permits and `WARN` otherwise.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl AuditSink for TracingSink {
+    fn record(&self, record: &AuditRecord) -> Result<(), AuditError> {
+        macro_rules! event {
+            ($level:expr) => {
+                tracing::event!(
+                    target: "author::audit",
+                    $level,
+                    request_id = record.request_id.as_deref(),
+                    subject = %record.subject,
+                    resource_type = %record.resource_type,
+                    resource = %record.resource,
+                    action = %record.action,
+                    effect = %record.decision.effect(),
+                    rule = record.decision.rule(),
+                    reason = %record.decision.reason(),
+                    "Authorisation decision"
+                )
+            };
+        }
+
+        if record.decision.is_permit() {
+            event!(tracing::Level::INFO);
+        } else {
+            event!(tracing::Level::WARN);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resource;
+
+    struct Invoice {
+        id: u32,
+    }
+
+    impl Resource for Invoice {
+        type Action = InvoiceAction;
+    }
+
+    impl NamedResource for Invoice {
+        const RESOURCE_NAME: &'static str = "invoice";
+    }
+
+    impl Auditable for Invoice {
+        fn audit_id(&self) -> String {
+            self.id.to_string()
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum InvoiceAction {
+        Approve,
+    }
+
+    impl Named for InvoiceAction {
+        fn name(&self) -> &str {
+            "approve"
+        }
+    }
+
+    struct User(&'static str);
+
+    impl Subject for User {}
+
+    impl Auditable for User {
+        fn audit_id(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    struct ManagersApprove;
+
+    impl Policy<Invoice, User> for ManagersApprove {
+        fn decide(&self, _invoice: &Invoice, user: &User, _action: &InvoiceAction) -> Decision {
+            match user.0 {
+                "manager" => Decision::permit("Managers approve invoices").with_rule("managers"),
+                _ => Decision::deny("Only managers approve invoices"),
+            }
+        }
+    }
+
+    struct FailingSink;
+
+    impl AuditSink for FailingSink {
+        fn record(&self, _record: &AuditRecord) -> Result<(), AuditError> {
+            Err(std::io::Error::other("disk full").into())
+        }
+    }
+
+    #[test]
+    fn record_every_decision() {
+        let sink = MemorySink::new();
+        let policy = AuditedPolicy::new(ManagersApprove, sink.clone())
+            .with_request_id(|| Some("req-1".to_string()));
+        let invoice = Invoice { id: 7 };
+
+        assert!(policy
+            .decide(&invoice, &User("manager"), &InvoiceAction::Approve)
+            .is_permit());
+        assert!(policy
+            .decide(&invoice, &User("clerk"), &InvoiceAction::Approve)
+            .is_deny());
+
+        let records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].request_id.as_deref(), Some("req-1"));
+        assert_eq!(records[0].subject, "manager");
+        assert_eq!(records[0].resource_type, "invoice");
+        assert_eq!(records[0].resource, "7");
+        assert_eq!(records[0].action, "approve");
+        assert_eq!(records[0].decision.rule(), Some("managers"));
+        assert_eq!(
+            records[1].decision.reason(),
+            "Only managers approve invoices"
+        );
+    }
+
+    #[test]
+    fn deny_when_record_fails() {
+        let policy = AuditedPolicy::new(ManagersApprove, FailingSink);
+        let decision = policy.decide(
+            &Invoice { id: 7 },
+            &User("manager"),
+            &InvoiceAction::Approve,
+        );
+
+        assert!(decision.is_deny());
+        assert_eq!(
+            decision.reason(),
+            "Denied because the decision couldn't be audited: Failed to write audit record: disk full"
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! Caching of policy decisions, for policies that are expensive to evaluate, such as ones that
+//! look up roles in a database, when the same subject checks the same resources repeatedly.
+
+#[cfg(feature = "async")]
+use crate::AsyncPolicy;
+use crate::{Decision, Policy, Resource, Subject};
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Identifies a subject or resource, so that decisions about it can be cached and invalidated.
+pub trait CacheKey {
+    type Key: Hash + Eq + Clone;
+
+    fn cache_key(&self) -> Self::Key;
+}
+
+type Key<Res, Subj> = (
+    <Subj as CacheKey>::Key,
+    <Res as CacheKey>::Key,
+    <Res as Resource>::Action,
+);
+
+struct Entry {
+    decision: Decision,
+    expires: Instant,
+    /// When the entry was last used, for evicting the least recently used entry.
+    used: u64,
+}
+
+struct Entries<Res, Subj>
+where
+    Res: Resource + CacheKey,
+    Subj: CacheKey,
+{
+    entries: HashMap<Key<Res, Subj>, Entry>,
+    by_use: BTreeMap<u64, Key<Res, Subj>>,
+    clock: u64,
+    /// Incremented whenever entries are invalidated, so that decisions made before an
+    /// invalidation aren't cached after it.
+    generation: u64,
+}
+
+impl<Res, Subj> Entries<Res, Subj>
+where
+    Res: Resource<Action: Clone> + CacheKey,
+    Subj: CacheKey,
+{
+    fn get(&mut self, key: &Key<Res, Subj>, now: Instant) -> Option<Decision> {
+        let entry = self.entries.get_mut(key)?;
+
+        if entry.expires <= now {
+            let used = entry.used;
+            self.entries.remove(key);
+            self.by_use.remove(&used);
+            return None;
+        }
+
+        self.clock += 1;
+        self.by_use.remove(&entry.used);
+        self.by_use.insert(self.clock, key.clone());
+        entry.used = self.clock;
+
+        Some(entry.decision.clone())
+    }
+
+    fn insert(
+        &mut self,
+        key: Key<Res, Subj>,
+        decision: Decision,
+        expires: Instant,
+        capacity: usize,
+    ) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.by_use.remove(&entry.used);
+        }
+
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.by_use.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        if capacity == 0 {
+            return;
+        }
+
+        self.clock += 1;
+        self.by_use.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                decision,
+                expires,
+                used: self.clock,
+            },
+        );
+    }
+
+    fn invalidate(&mut self, keep: impl Fn(&Key<Res, Subj>) -> bool) {
+        self.generation += 1;
+        let by_use = &mut self.by_use;
+
+        self.entries.retain(|key, entry| {
+            let keep = keep(key);
+
+            if !keep {
+                by_use.remove(&entry.used);
+            }
+
+            keep
+        });
+    }
+}
+
+struct Cache<Res, Subj>
+where
+    Res: Resource + CacheKey,
+    Subj: CacheKey,
+{
+    entries: Mutex<Entries<Res, Subj>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// The number of cache lookups that found and didn't find a decision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Wraps a policy, remembering its decisions for each subject, resource and action for a limited
+/// time. Once the cache is full the least recently used decision is dropped.
+///
+/// Decisions stay cached until they expire, so anything that changes them, such as a subject's
+/// roles changing, should invalidate the affected entries, either directly or through an
+/// [`invalidator`](CachedPolicy::invalidator) handed to the code making the change.
+/// Decisions that were being made while the cache was invalidated aren't cached, as they may
+/// predate the change.
+pub struct CachedPolicy<P, Res, Subj>
+where
+    Res: Resource + CacheKey,
+    Subj: CacheKey,
+{
+    policy: P,
+    cache: Arc<Cache<Res, Subj>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl<P, Res, Subj> CachedPolicy<P, Res, Subj>
+where
+    Res: Resource<Action: Clone> + CacheKey,
+    Subj: CacheKey,
+{
+    /// Caches up to 10,000 decisions for a minute each.
+    pub fn new(policy: P) -> Self {
+        CachedPolicy {
+            policy,
+            cache: Arc::new(Cache {
+                entries: Mutex::new(Entries {
+                    entries: HashMap::new(),
+                    by_use: BTreeMap::new(),
+                    clock: 0,
+                    generation: 0,
+                }),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+            ttl: Duration::from_secs(60),
+            capacity: 10_000,
+        }
+    }
+
+    /// Sets how long decisions are cached for.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the most decisions that are cached at once.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Returns a handle that can invalidate this policy's cache from elsewhere, such as from the
+    /// code that updates a subject's roles.
+    pub fn invalidator(&self) -> CacheInvalidator<Res, Subj> {
+        CacheInvalidator {
+            cache: self.cache.clone(),
+        }
+    }
+
+    /// Drops every cached decision about the subject.
+    pub fn invalidate_subject(&self, subject: &Subj::Key) {
+        self.invalidator().invalidate_subject(subject)
+    }
+
+    /// Drops every cached decision about the resource.
+    pub fn invalidate_resource(&self, resource: &Res::Key) {
+        self.invalidator().invalidate_resource(resource)
+    }
+
+    pub fn invalidate_all(&self) {
+        self.invalidator().invalidate_all()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.cache.hits.load(Ordering::Relaxed),
+            misses: self.cache.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.entries.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the cached decision, or on a miss the current generation, to be passed to
+    /// [`insert`](Self::insert) along with the decision once it has been made.
+    fn get(&self, key: &Key<Res, Subj>) -> Result<Decision, u64> {
+        let mut entries = self.cache.entries.lock();
+
+        match entries.get(key, Instant::now()) {
+            Some(decision) => {
+                self.cache.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(decision)
+            }
+            None => {
+                self.cache.misses.fetch_add(1, Ordering::Relaxed);
+                Err(entries.generation)
+            }
+        }
+    }
+
+    /// Caches a decision, unless the cache has been invalidated since the generation it was made
+    /// in, as it may have been based on whatever the invalidation was for.
+    fn insert(&self, key: Key<Res, Subj>, decision: Decision, generation: u64) {
+        let mut entries = self.cache.entries.lock();
+
+        if entries.generation == generation {
+            entries.insert(key, decision, Instant::now() + self.ttl, self.capacity);
+        }
+    }
+}
+
+impl<P, Res, Subj> Policy<Res, Subj> for CachedPolicy<P, Res, Subj>
+where
+    P: Policy<Res, Subj>,
+    Res: Resource<Action: Clone> + CacheKey,
+    Subj: Subject + CacheKey,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let key = (subject.cache_key(), resource.cache_key(), action.clone());
+
+        let generation = match self.get(&key) {
+            Ok(decision) => return decision,
+            Err(generation) => generation,
+        };
+
+        let decision = self.policy.decide(resource, subject, action);
+        self.insert(key, decision.clone(), generation);
+
+        decision
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<P, Res, Subj> AsyncPolicy<Res, Subj> for CachedPolicy<P, Res, Subj>
+where
+    P: AsyncPolicy<Res, Subj>,
+    Res: Resource<Action: Clone + Send + Sync> + CacheKey<Key: Send + Sync> + Sync,
+    Subj: Subject + CacheKey<Key: Send + Sync> + Sync,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let key = (subject.cache_key(), resource.cache_key(), action.clone());
+
+        let generation = match self.get(&key) {
+            Ok(decision) => return decision,
+            Err(generation) => generation,
+        };
+
+        let decision = self.policy.decide(resource, subject, action).await;
+        self.insert(key, decision.clone(), generation);
+
+        decision
+    }
+}
+
+/// A handle for invalidating the decisions cached by a [`CachedPolicy`].
+pub struct CacheInvalidator<Res, Subj>
+where
+    Res: Resource + CacheKey,
+    Subj: CacheKey,
+{
+    cache: Arc<Cache<Res, Subj>>,
+}
+
+impl<Res, Subj> Clone for CacheInvalidator<Res, Subj>
+where
+    Res: Resource + CacheKey,
+    Subj: CacheKey,
+{
+    fn clone(&self) -> Self {
+        CacheInvalidator {
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<Res, Subj> CacheInvalidator<Res, Subj>
+where
+    Res: Resource<Action: Clone> + CacheKey,
+    Subj: CacheKey,
+{
+    pub fn invalidate_subject(&self, subject: &Subj::Key) {
+        self.cache
+            .entries
+            .lock()
+            .invalidate(|(key, _, _)| key != subject);
+    }
+
+    pub fn invalidate_resource(&self, resource: &Res::Key) {
+        self.cache
+            .entries
+            .lock()
+            .invalidate(|(_, key, _)| key != resource);
+    }
+
+    pub fn invalidate_all(&self) {
+        self.cache.entries.lock().invalidate(|_| false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, OnceCell};
+    use std::collections::HashSet;
+
+    struct Document {
+        id: u32,
+    }
+
+    impl Resource for Document {
+        type Action = DocumentAction;
+    }
+
+    impl CacheKey for Document {
+        type Key = u32;
+
+        fn cache_key(&self) -> u32 {
+            self.id
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum DocumentAction {
+        Read,
+        Edit,
+    }
+
+    struct User {
+        id: u32,
+    }
+
+    impl Subject for User {}
+
+    impl CacheKey for User {
+        type Key = u32;
+
+        fn cache_key(&self) -> u32 {
+            self.id
+        }
+    }
+
+    /// Lets editors edit, counting how many times it is asked.
+    struct EditorPolicy {
+        editors: HashSet<u32>,
+        calls: Cell<usize>,
+    }
+
+    impl Policy<Document, User> for EditorPolicy {
+        fn decide(&self, _document: &Document, user: &User, action: &DocumentAction) -> Decision {
+            self.calls.set(self.calls.get() + 1);
+
+            match action {
+                DocumentAction::Edit if !self.editors.contains(&user.id) => {
+                    Decision::deny("Only editors can edit")
+                }
+                _ => Decision::permit("Allowed"),
+            }
+        }
+    }
+
+    fn policy() -> CachedPolicy<EditorPolicy, Document, User> {
+        CachedPolicy::new(EditorPolicy {
+            editors: HashSet::from([1]),
+            calls: Cell::new(0),
+        })
+    }
+
+    #[test]
+    fn cache_decisions_until_invalidated() {
+        let policy = policy();
+        let (editor, viewer) = (User { id: 1 }, User { id: 2 });
+        let document = Document { id: 10 };
+
+        for _ in 0..3 {
+            assert!(policy
+                .decide(&document, &editor, &DocumentAction::Edit)
+                .is_permit());
+            assert!(policy
+                .decide(&document, &viewer, &DocumentAction::Edit)
+                .is_deny());
+        }
+
+        assert_eq!(policy.policy().calls.get(), 2);
+        assert_eq!(policy.stats(), CacheStats { hits: 4, misses: 2 });
+
+        policy.invalidate_subject(&2);
+        policy.decide(&document, &viewer, &DocumentAction::Edit);
+        policy.decide(&document, &editor, &DocumentAction::Edit);
+        assert_eq!(policy.policy().calls.get(), 3);
+
+        policy.invalidator().invalidate_resource(&10);
+        assert!(policy.is_empty());
+    }
+
+    #[test]
+    fn bound_by_capacity_and_ttl() {
+        let policy = policy().with_capacity(2);
+        let user = User { id: 1 };
+
+        policy.decide(&Document { id: 1 }, &user, &DocumentAction::Read);
+        policy.decide(&Document { id: 2 }, &user, &DocumentAction::Read);
+        // Using the first document makes the second the least recently used
+        policy.decide(&Document { id: 1 }, &user, &DocumentAction::Read);
+        policy.decide(&Document { id: 3 }, &user, &DocumentAction::Read);
+        assert_eq!(policy.len(), 2);
+
+        policy.decide(&Document { id: 1 }, &user, &DocumentAction::Read);
+        assert_eq!(policy.policy().calls.get(), 3);
+        policy.decide(&Document { id: 2 }, &user, &DocumentAction::Read);
+        assert_eq!(policy.policy().calls.get(), 4);
+
+        let policy = policy.with_ttl(Duration::ZERO);
+        policy.decide(&Document { id: 4 }, &user, &DocumentAction::Read);
+        policy.decide(&Document { id: 4 }, &user, &DocumentAction::Read);
+        assert_eq!(policy.policy().calls.get(), 6);
+    }
+
+    /// Revokes the subject's access part way through deciding, as if another thread did so while
+    /// the decision was being made.
+    struct RevokingPolicy {
+        invalidator: OnceCell<CacheInvalidator<Document, User>>,
+        calls: Cell<usize>,
+    }
+
+    impl Policy<Document, User> for RevokingPolicy {
+        fn decide(&self, _document: &Document, user: &User, _action: &DocumentAction) -> Decision {
+            self.calls.set(self.calls.get() + 1);
+
+            if self.calls.get() == 1 {
+                self.invalidator.get().unwrap().invalidate_subject(&user.id);
+            }
+
+            Decision::permit("Allowed")
+        }
+    }
+
+    #[test]
+    fn skip_decisions_made_during_invalidation() {
+        let policy = CachedPolicy::new(RevokingPolicy {
+            invalidator: OnceCell::new(),
+            calls: Cell::new(0),
+        });
+        let _ = policy.policy().invalidator.set(policy.invalidator());
+        let (user, document) = (User { id: 1 }, Document { id: 10 });
+
+        policy.decide(&document, &user, &DocumentAction::Read);
+        assert!(policy.is_empty());
+
+        // The next decision is made afresh, and cached as nothing invalidates it
+        policy.decide(&document, &user, &DocumentAction::Read);
+        policy.decide(&document, &user, &DocumentAction::Read);
+        assert_eq!(policy.policy().calls.get(), 2);
+        assert_eq!(policy.len(), 1);
+    }
+}
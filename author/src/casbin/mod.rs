@@ -0,0 +1,609 @@
+//! Support for existing Casbin `model.conf` and `policy.csv` files, so that services using Casbin
+//! can move to Author without rewriting their policies.
+//!
+//! The model's matcher is parsed with the [ABAC expression language](crate::abac::expr), which
+//! covers the usual Casbin matcher syntax: `r.` and `p.` tokens, `==` and the other comparisons,
+//! `&&`, `||`, `!` and function calls. Each role definition, such as `g`, becomes a function
+//! checking whether a subject holds a role, directly or through other roles, and `keyMatch` and
+//! `keyMatch2` are available for matching paths.
+//!
+//! Requests are built from the request definition by token name: `sub` is the subject, `obj` the
+//! resource, `act` the name of the action, and any other token is looked up with
+//! [`CasbinResource::casbin_value`].
+
+mod model;
+
+pub use model::{CasbinModel, PolicyEffect, RoleDefinition};
+
+use crate::abac::expr::{resolve_fields, Context, EvalError, Functions, ParseError};
+use crate::abac::{RuleEffect, Value};
+use crate::rbac::{HierarchyError, RoleHierarchy};
+use crate::{Decision, Named, Policy, Resource, Subject};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CasbinError {
+    #[error("Failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Model is missing {0}")]
+    Missing(String),
+    #[error("Invalid model on line {line}: {message}")]
+    Model { line: usize, message: String },
+    #[error("Invalid matcher on line {line}: {error}")]
+    Matcher { line: usize, error: ParseError },
+    #[error("Invalid policy on line {line}: {message}")]
+    Policy { line: usize, message: String },
+    #[error("Invalid roles for '{name}': {error}")]
+    Roles {
+        name: String,
+        error: HierarchyError<String>,
+    },
+}
+
+/// A subject that can be matched by Casbin policies.
+pub trait CasbinSubject: Subject {
+    /// The value of `r.sub`, usually the name the subject is given in policy lines. A map value
+    /// allows matchers to refer to attributes, as in `r.sub.age > 18`.
+    fn casbin_subject(&self) -> Value;
+}
+
+/// A resource that can be matched by Casbin policies.
+pub trait CasbinResource: Resource {
+    /// The value of `r.obj`.
+    fn casbin_object(&self) -> Value;
+
+    /// The value of any other request token, such as `dom` in models with domains.
+    fn casbin_value(&self, _token: &str) -> Option<Value> {
+        None
+    }
+}
+
+/// A `p` line from the policy file.
+#[derive(Debug, Clone)]
+struct PolicyRule {
+    values: Vec<String>,
+    effect: RuleEffect,
+}
+
+impl PolicyRule {
+    fn describe(&self) -> String {
+        format!("p, {}", self.values.join(", "))
+    }
+}
+
+/// Policy enforcing a Casbin model and policy file. A request the model's effect allows is
+/// permitted, one denied by a matching `deny` line is denied, and one no policy line matches is not
+/// applicable. A matcher that fails to evaluate denies the request.
+#[derive(Clone)]
+pub struct CasbinPolicy {
+    model: CasbinModel,
+    rules: Vec<PolicyRule>,
+    functions: Functions,
+}
+
+impl CasbinPolicy {
+    /// Creates a policy from a model and the contents of a policy file, in which each line is a
+    /// policy such as `p, alice, data1, read` or a role assignment such as `g, alice, admin`.
+    pub fn new(model: CasbinModel, policy: &str) -> Result<Self, CasbinError> {
+        let mut rules = Vec::new();
+        let mut links: HashMap<&str, Vec<(usize, Vec<String>)>> = HashMap::new();
+
+        for (index, line) in policy.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut values =
+                parse_csv_line(line).map_err(|message| policy_error(number, message))?;
+            let kind = values.remove(0);
+
+            if kind == "p" {
+                rules.push(model.rule(number, values)?);
+            } else if let Some(role) = model.roles.iter().find(|r| r.name == kind) {
+                links
+                    .entry(role.name.as_str())
+                    .or_default()
+                    .push((number, values));
+            } else {
+                return Err(policy_error(
+                    number,
+                    format!("unknown policy type '{}'", kind),
+                ));
+            }
+        }
+
+        if model.effect == PolicyEffect::Priority {
+            if let Some(index) = model.policy.iter().position(|t| t == "priority") {
+                // Lower values take priority, as in Casbin, and ties keep their order in the file
+                rules.sort_by_key(|rule| rule.values[index].parse::<i64>().unwrap_or(i64::MAX));
+            }
+        }
+
+        let mut functions = Functions::new()
+            .with("keyMatch", |args| match args {
+                [Value::String(key), Value::String(pattern)] => {
+                    Ok(Value::Bool(key_match(key, pattern)))
+                }
+                _ => Err(EvalError::Type("keyMatch expects two strings".to_string())),
+            })
+            .with("keyMatch2", |args| match args {
+                [Value::String(key), Value::String(pattern)] => {
+                    Ok(Value::Bool(key_match2(key, pattern)))
+                }
+                _ => Err(EvalError::Type("keyMatch2 expects two strings".to_string())),
+            });
+
+        for role in &model.roles {
+            let hierarchies = role_hierarchies(role, links.remove(role.name.as_str()))?;
+            functions = functions.with(role.name.clone(), role_function(role, hierarchies));
+        }
+
+        Ok(CasbinPolicy {
+            model,
+            rules,
+            functions,
+        })
+    }
+
+    /// Parses a model and policy from their contents.
+    pub fn parse(model: &str, policy: &str) -> Result<Self, CasbinError> {
+        Self::new(CasbinModel::parse(model)?, policy)
+    }
+
+    /// Loads a model and policy from files.
+    pub fn load(model: impl AsRef<Path>, policy: impl AsRef<Path>) -> Result<Self, CasbinError> {
+        Self::new(CasbinModel::load(model)?, &std::fs::read_to_string(policy)?)
+    }
+
+    /// Registers a function that matchers may call, such as a custom matching function.
+    pub fn with_function<F>(mut self, name: impl Into<String>, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, EvalError> + Send + Sync + 'static,
+    {
+        self.functions = self.functions.with(name, function);
+        self
+    }
+
+    pub fn model(&self) -> &CasbinModel {
+        &self.model
+    }
+
+    /// Decides a request given as values in the order of the request definition, as Casbin's
+    /// `enforce` does.
+    pub fn enforce(&self, request: &[Value]) -> Decision {
+        if request.len() != self.model.request.len() {
+            return Decision::deny(format!(
+                "Expected {} request values but got {}",
+                self.model.request.len(),
+                request.len()
+            ));
+        }
+
+        let mut allowed_by = None;
+
+        for rule in &self.rules {
+            let context = MatchContext {
+                model: &self.model,
+                request,
+                rule,
+            };
+
+            match self.model.matcher.evaluate_bool(&context, &self.functions) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    return Decision::deny(format!("Failed to evaluate matcher: {}", e))
+                        .with_rule(rule.describe())
+                }
+            }
+
+            match (self.model.effect, rule.effect) {
+                (PolicyEffect::Priority, RuleEffect::Permit) => return allowed(rule),
+                (PolicyEffect::AllowOverride, RuleEffect::Permit) => return allowed(rule),
+                (PolicyEffect::AllowOverride, RuleEffect::Forbid) => {}
+                (_, RuleEffect::Forbid) => return denied(rule),
+                (_, RuleEffect::Permit) => {
+                    allowed_by.get_or_insert(rule);
+                }
+            }
+        }
+
+        match (self.model.effect, allowed_by) {
+            (PolicyEffect::DenyOverride, _) => Decision::permit("No policy denies the request"),
+            (_, Some(rule)) => allowed(rule),
+            (_, None) => Decision::not_applicable("No policy allows the request"),
+        }
+    }
+}
+
+impl CasbinModel {
+    fn rule(&self, line: usize, values: Vec<String>) -> Result<PolicyRule, CasbinError> {
+        if values.len() != self.policy.len() {
+            return Err(policy_error(
+                line,
+                format!(
+                    "expected {} values for 'p = {}' but got {}",
+                    self.policy.len(),
+                    self.policy.join(", "),
+                    values.len()
+                ),
+            ));
+        }
+
+        let effect = match self.policy.iter().position(|t| t == "eft") {
+            None => RuleEffect::Permit,
+            Some(index) => match values[index].as_str() {
+                "allow" => RuleEffect::Permit,
+                "deny" => RuleEffect::Forbid,
+                other => {
+                    return Err(policy_error(
+                        line,
+                        format!("effect must be 'allow' or 'deny' but is '{}'", other),
+                    ))
+                }
+            },
+        };
+
+        if let Some(index) = self.policy.iter().position(|t| t == "priority") {
+            if values[index].parse::<i64>().is_err() {
+                return Err(policy_error(
+                    line,
+                    format!("priority must be a number but is '{}'", values[index]),
+                ));
+            }
+        }
+
+        Ok(PolicyRule { values, effect })
+    }
+}
+
+impl<Res, Subj> Policy<Res, Subj> for CasbinPolicy
+where
+    Res: CasbinResource<Action: Named>,
+    Subj: CasbinSubject,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let mut request = Vec::with_capacity(self.model.request.len());
+
+        for token in &self.model.request {
+            let value = match token.as_str() {
+                "sub" => Some(subject.casbin_subject()),
+                "obj" => Some(resource.casbin_object()),
+                "act" => Some(Value::from(action.name())),
+                token => resource.casbin_value(token),
+            };
+
+            match value {
+                Some(value) => request.push(value),
+                None => return Decision::deny(format!("No value for request token '{}'", token)),
+            }
+        }
+
+        self.enforce(&request)
+    }
+}
+
+struct MatchContext<'a> {
+    model: &'a CasbinModel,
+    request: &'a [Value],
+    rule: &'a PolicyRule,
+}
+
+impl Context for MatchContext<'_> {
+    fn resolve(&self, path: &[String]) -> Option<Value> {
+        let [root, token, fields @ ..] = path else {
+            return None;
+        };
+
+        let value = match root.as_str() {
+            "r" => {
+                let index = self.model.request.iter().position(|t| t == token)?;
+                self.request[index].clone()
+            }
+            "p" => {
+                let index = self.model.policy.iter().position(|t| t == token)?;
+                Value::from(&self.rule.values[index])
+            }
+            _ => return None,
+        };
+
+        resolve_fields(&value, fields)
+    }
+}
+
+fn allowed(rule: &PolicyRule) -> Decision {
+    Decision::permit("Allowed by a matching policy").with_rule(rule.describe())
+}
+
+fn denied(rule: &PolicyRule) -> Decision {
+    Decision::deny("Denied by a matching policy").with_rule(rule.describe())
+}
+
+/// Builds the hierarchy of each domain from role links such as `g, alice, admin` or
+/// `g, alice, admin, domain1`. Without domains every link is in the domain `""`.
+fn role_hierarchies(
+    role: &RoleDefinition,
+    links: Option<Vec<(usize, Vec<String>)>>,
+) -> Result<HashMap<String, RoleHierarchy<String>>, CasbinError> {
+    let arity = if role.domains { 3 } else { 2 };
+    let mut builders = HashMap::new();
+
+    for (line, values) in links.into_iter().flatten() {
+        if values.len() != arity {
+            return Err(policy_error(
+                line,
+                format!("expected {} values for '{}'", arity, role.name),
+            ));
+        }
+
+        let domain = values.get(2).cloned().unwrap_or_default();
+        let builder = builders
+            .remove(&domain)
+            .unwrap_or_else(RoleHierarchy::builder)
+            .inherit(values[0].clone(), values[1].clone());
+        builders.insert(domain, builder);
+    }
+
+    builders
+        .into_iter()
+        .map(|(domain, builder)| {
+            builder
+                .build()
+                .map(|hierarchy| (domain, hierarchy))
+                .map_err(|error| CasbinError::Roles {
+                    name: role.name.clone(),
+                    error,
+                })
+        })
+        .collect()
+}
+
+/// The function for a role definition, such as `g(r.sub, p.sub)`, which is true if the first
+/// name is the second or holds it as a role.
+fn role_function(
+    role: &RoleDefinition,
+    hierarchies: HashMap<String, RoleHierarchy<String>>,
+) -> impl Fn(&[Value]) -> Result<Value, EvalError> + Send + Sync + 'static {
+    let name = role.name.clone();
+    let domains = role.domains;
+    let hierarchies = Arc::new(hierarchies);
+
+    move |args| {
+        let (member, role, domain) = match (args, domains) {
+            ([member, role], false) => (member, role, ""),
+            ([member, role, Value::String(domain)], true) => (member, role, domain.as_str()),
+            _ => {
+                return Err(EvalError::Type(format!(
+                    "{} expects {} arguments",
+                    name,
+                    if domains { 3 } else { 2 }
+                )))
+            }
+        };
+
+        let (Value::String(member), Value::String(role)) = (member, role) else {
+            return Err(EvalError::Type(format!(
+                "{} expects names as strings",
+                name
+            )));
+        };
+
+        Ok(Value::Bool(
+            member == role
+                || hierarchies
+                    .get(domain)
+                    .is_some_and(|h| h.inherited_roles(member).contains(role)),
+        ))
+    }
+}
+
+/// Casbin's `keyMatch`, in which a `*` in the pattern matches the rest of the key, as in
+/// `/data/*`.
+fn key_match(key: &str, pattern: &str) -> bool {
+    match pattern.find('*') {
+        Some(index) => key.starts_with(&pattern[..index]),
+        None => key == pattern,
+    }
+}
+
+/// Casbin's `keyMatch2`, in which a `:name` segment of the pattern matches any single segment of
+/// the key and a trailing `*` matches the rest, as in `/users/:id/*`.
+fn key_match2(key: &str, pattern: &str) -> bool {
+    let mut keys = key.split('/');
+    let mut patterns = pattern.split('/').peekable();
+
+    while let Some(pattern) = patterns.next() {
+        if pattern == "*" && patterns.peek().is_none() {
+            return true;
+        }
+
+        match keys.next() {
+            Some(key) if pattern.starts_with(':') && !key.is_empty() => {}
+            Some(key) if key == pattern => {}
+            _ => return false,
+        }
+    }
+
+    keys.next().is_none()
+}
+
+/// Splits a line of comma-separated values, which may be double-quoted to contain commas, with
+/// `""` inside quotes for a literal quote.
+fn parse_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut value = String::new();
+
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => value.push('"'),
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Err("unterminated quoted value".to_string()),
+                }
+            }
+
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err("unexpected text after quoted value".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                value.push(c);
+            }
+
+            value.truncate(value.trim_end().len());
+        }
+
+        values.push(value);
+
+        if chars.next().is_none() {
+            return Ok(values);
+        }
+    }
+}
+
+fn policy_error(line: usize, message: impl Into<String>) -> CasbinError {
+    CasbinError::Policy {
+        line,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RBAC_MODEL: &str = r#"
+[request_definition]
+r = sub, obj, act
+
+[policy_definition]
+p = sub, obj, act, eft
+
+[role_definition]
+g = _, _
+
+[policy_effect]
+e = some(where (p.eft == allow)) && !some(where (p.eft == deny))
+
+[matchers]
+m = g(r.sub, p.sub) && keyMatch2(r.obj, p.obj) && r.act == p.act
+"#;
+
+    const RBAC_POLICY: &str = "
+p, admin, /data/:id, read, allow
+p, admin, /data/:id, write, allow
+p, bob, /data/2, read, deny
+p, alice, /data/1, read, allow
+
+g, bob, admin
+g, admin, staff
+";
+
+    fn request(sub: &str, obj: &str, act: &str) -> [Value; 3] {
+        [sub.into(), obj.into(), act.into()]
+    }
+
+    #[test]
+    fn enforce_model_and_policy() {
+        let policy = CasbinPolicy::parse(RBAC_MODEL, RBAC_POLICY).unwrap();
+
+        assert!(policy
+            .enforce(&request("bob", "/data/1", "write"))
+            .is_permit());
+        assert!(policy
+            .enforce(&request("alice", "/data/1", "read"))
+            .is_permit());
+        assert!(policy
+            .enforce(&request("alice", "/data/1", "write"))
+            .is_not_applicable());
+
+        let decision = policy.enforce(&request("bob", "/data/2", "read"));
+        assert!(decision.is_deny());
+        assert_eq!(decision.rule(), Some("p, bob, /data/2, read, deny"));
+    }
+
+    #[test]
+    fn domains_and_priority() {
+        let model = r#"
+[request_definition]
+r = sub, dom, obj, act
+
+[policy_definition]
+p = priority, sub, dom, obj, act, eft
+
+[role_definition]
+g = _, _, _
+
+[policy_effect]
+e = priority(p.eft) || deny
+
+[matchers]
+m = g(r.sub, p.sub, r.dom) && r.dom == p.dom && keyMatch(r.obj, p.obj) && r.act == p.act
+"#;
+        let policy = CasbinPolicy::parse(
+            model,
+            "p, 10, writer, acme, /docs/*, write, allow\n\
+             p, 1, mallory, acme, /docs/*, write, deny\n\
+             g, alice, writer, acme\n\
+             g, mallory, writer, acme",
+        )
+        .unwrap();
+
+        let request = |sub: &str, dom: &str| -> [Value; 4] {
+            [
+                sub.into(),
+                dom.into(),
+                "/docs/readme".into(),
+                "write".into(),
+            ]
+        };
+
+        assert!(policy.enforce(&request("alice", "acme")).is_permit());
+        assert!(policy
+            .enforce(&request("alice", "globex"))
+            .is_not_applicable());
+        assert!(policy.enforce(&request("mallory", "acme")).is_deny());
+    }
+
+    #[test]
+    fn report_invalid_files() {
+        let model = RBAC_MODEL.replace("r.act == p.act", "r.act == p.action");
+        assert!(matches!(
+            CasbinModel::parse(&model),
+            Err(CasbinError::Model { line: 15, .. })
+        ));
+
+        let model = RBAC_MODEL.replace("r.act == p.act", "r.act = p.act");
+        let Err(CasbinError::Matcher { line: 15, error }) = CasbinModel::parse(&model) else {
+            panic!("expected matcher to fail to parse");
+        };
+        assert_eq!(&model[error.span.start..error.span.end], "=");
+
+        assert!(matches!(
+            CasbinPolicy::parse(RBAC_MODEL, "p, alice, /data/1, read"),
+            Err(CasbinError::Policy { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn split_csv_lines() {
+        assert_eq!(
+            parse_csv_line(r#"p, alice , "/a, b", "say ""hi""""#).unwrap(),
+            vec!["p", "alice", "/a, b", r#"say "hi""#]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::abac::expr::Expr;
+use crate::casbin::CasbinError;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How the effects of matching policy lines are combined into a decision, from the
+/// `[policy_effect]` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyEffect {
+    /// `some(where (p.eft == allow))`: permitted if any allow line matches.
+    AllowOverride,
+    /// `!some(where (p.eft == deny))`: permitted unless a deny line matches.
+    DenyOverride,
+    /// `some(where (p.eft == allow)) && !some(where (p.eft == deny))`: permitted if an allow line
+    /// matches and no deny line does.
+    AllowAndDeny,
+    /// `priority(p.eft) || deny`: the first matching line decides, in order of the `priority`
+    /// token if the policy definition has one and file order otherwise.
+    Priority,
+}
+
+impl PolicyEffect {
+    fn parse(source: &str) -> Option<Self> {
+        let normalised: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+
+        match normalised.as_str() {
+            "some(where(p.eft==allow))" => Some(PolicyEffect::AllowOverride),
+            "!some(where(p.eft==deny))" => Some(PolicyEffect::DenyOverride),
+            "some(where(p.eft==allow))&&!some(where(p.eft==deny))" => {
+                Some(PolicyEffect::AllowAndDeny)
+            }
+            "priority(p.eft)||deny" => Some(PolicyEffect::Priority),
+            _ => None,
+        }
+    }
+}
+
+/// A role definition such as `g = _, _`, or `g = _, _, _` for roles granted within a domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleDefinition {
+    pub name: String,
+    pub domains: bool,
+}
+
+/// A parsed Casbin `model.conf`.
+///
+/// ```text
+/// [request_definition]
+/// r = sub, obj, act
+///
+/// [policy_definition]
+/// p = sub, obj, act
+///
+/// [role_definition]
+/// g = _, _
+///
+/// [policy_effect]
+/// e = some(where (p.eft == allow))
+///
+/// [matchers]
+/// m = g(r.sub, p.sub) && r.obj == p.obj && r.act == p.act
+/// ```
+///
+/// Only a single request definition `r`, policy definition `p`, effect `e` and matcher `m` are
+/// supported, alongside any number of role definitions.
+#[derive(Debug, Clone)]
+pub struct CasbinModel {
+    pub(crate) request: Vec<String>,
+    pub(crate) policy: Vec<String>,
+    pub(crate) roles: Vec<RoleDefinition>,
+    pub(crate) effect: PolicyEffect,
+    pub(crate) matcher: Expr,
+}
+
+impl CasbinModel {
+    pub fn parse(source: &str) -> Result<Self, CasbinError> {
+        let mut sections: BTreeMap<&str, BTreeMap<&str, (usize, usize, &str)>> = BTreeMap::new();
+        let mut section = None;
+        let mut offset = 0;
+
+        for (index, line) in source.split_inclusive('\n').enumerate() {
+            let line_start = offset;
+            offset += line.len();
+
+            let number = index + 1;
+            let content = line.split('#').next().unwrap_or_default();
+            let trimmed = content.trim();
+
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = Some(name.trim());
+                sections.entry(name.trim()).or_default();
+                continue;
+            }
+
+            let Some(section) = section else {
+                return Err(model_error(number, "expected a [section] header"));
+            };
+
+            let Some((key, value)) = content.split_once('=') else {
+                return Err(model_error(number, "expected 'key = value'"));
+            };
+
+            let value_start = line_start + key.len() + 1;
+            let value_start = value_start + (value.len() - value.trim_start().len());
+
+            sections
+                .entry(section)
+                .or_default()
+                .insert(key.trim(), (number, value_start, value.trim()));
+        }
+
+        let single = |section: &str, key: &str| {
+            let entries = sections
+                .get(section)
+                .ok_or_else(|| CasbinError::Missing(format!("[{}] section", section)))?;
+
+            if let Some((extra, (line, _, _))) = entries.iter().find(|(k, _)| **k != key) {
+                return Err(model_error(
+                    *line,
+                    format!("unsupported definition '{}' in [{}]", extra, section),
+                ));
+            }
+
+            entries
+                .get(key)
+                .copied()
+                .ok_or_else(|| CasbinError::Missing(format!("'{}' in [{}]", key, section)))
+        };
+
+        let (_, _, request) = single("request_definition", "r")?;
+        let (_, _, policy) = single("policy_definition", "p")?;
+        let (effect_line, _, effect) = single("policy_effect", "e")?;
+        let (matcher_line, matcher_start, matcher) = single("matchers", "m")?;
+
+        let request = tokens(request);
+        let policy = tokens(policy);
+
+        let effect = PolicyEffect::parse(effect).ok_or_else(|| {
+            model_error(
+                effect_line,
+                format!("unsupported policy effect '{}'", effect),
+            )
+        })?;
+
+        let roles = sections
+            .get("role_definition")
+            .into_iter()
+            .flatten()
+            .map(|(name, (line, _, value))| match tokens(value).len() {
+                2 => Ok(RoleDefinition {
+                    name: name.to_string(),
+                    domains: false,
+                }),
+                3 => Ok(RoleDefinition {
+                    name: name.to_string(),
+                    domains: true,
+                }),
+                _ => Err(model_error(
+                    *line,
+                    "role definitions must be '_, _' or '_, _, _'",
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let matcher = Expr::parse(matcher).map_err(|mut error| {
+            error.span = error.span.offset(matcher_start);
+            CasbinError::Matcher {
+                line: matcher_line,
+                error,
+            }
+        })?;
+
+        let mut unknown = None;
+
+        matcher.visit_paths(&mut |path, _| {
+            let known = match path {
+                [root, token, ..] if root == "r" => request.contains(token),
+                [root, token, ..] if root == "p" => policy.contains(token),
+                _ => false,
+            };
+
+            if !known && unknown.is_none() {
+                unknown = Some(path.join("."));
+            }
+        });
+
+        if let Some(path) = unknown {
+            return Err(model_error(
+                matcher_line,
+                format!("matcher refers to undefined token '{}'", path),
+            ));
+        }
+
+        Ok(CasbinModel {
+            request,
+            policy,
+            roles,
+            effect,
+            matcher,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CasbinError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The tokens of the request definition, such as `["sub", "obj", "act"]`.
+    pub fn request_tokens(&self) -> &[String] {
+        &self.request
+    }
+
+    /// The tokens of the policy definition, such as `["sub", "obj", "act"]`.
+    pub fn policy_tokens(&self) -> &[String] {
+        &self.policy
+    }
+
+    pub fn roles(&self) -> &[RoleDefinition] {
+        &self.roles
+    }
+
+    pub fn effect(&self) -> PolicyEffect {
+        self.effect
+    }
+
+    pub fn matcher(&self) -> &Expr {
+        &self.matcher
+    }
+}
+
+fn tokens(definition: &str) -> Vec<String> {
+    definition
+        .split(',')
+        .map(|token| token.trim().to_string())
+        .collect()
+}
+
+fn model_error(line: usize, message: impl Into<String>) -> CasbinError {
+    CasbinError::Model {
+        line,
+        message: message.into(),
+    }
+}
@@ -0,0 +1,539 @@
+//! Policies that combine the results of other policies.
+//!
+//! Each combinator wraps a [`PolicySet`], which is implemented for tuples of policies of
+//! different types as well as for `Vec`s and arrays of policies of the same type, so that
+//! for example "global admin OR resource owner" can be written as:
+//!
+//! ```ignore
+//! let policy = AnyOf::new((GlobalRbacPolicy::new(), ResourceRbacPolicy::new()));
+//! ```
+//!
+//! Policies are evaluated in order and evaluation stops as soon as the combined result is known.
+
+use crate::{Decision, Effect, Policy, Resource, Subject, SubjectDecider};
+
+/// A collection of policies that can be evaluated in turn by a combinator.
+pub trait PolicySet<Res, Subj>
+where
+    Res: Resource,
+    Subj: Subject,
+{
+    fn policies(&self) -> Vec<&dyn Policy<Res, Subj>>;
+}
+
+impl<Res, Subj, P> PolicySet<Res, Subj> for Vec<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj>,
+{
+    fn policies(&self) -> Vec<&dyn Policy<Res, Subj>> {
+        self.iter().map(|p| p as &dyn Policy<Res, Subj>).collect()
+    }
+}
+
+impl<Res, Subj, P, const N: usize> PolicySet<Res, Subj> for [P; N]
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj>,
+{
+    fn policies(&self) -> Vec<&dyn Policy<Res, Subj>> {
+        self.iter().map(|p| p as &dyn Policy<Res, Subj>).collect()
+    }
+}
+
+macro_rules! tuple_policy_set {
+    ($($name:ident: $idx:tt),+) => {
+        impl<Res, Subj, $($name),+> PolicySet<Res, Subj> for ($($name,)+)
+        where
+            Res: Resource,
+            Subj: Subject,
+            $($name: Policy<Res, Subj>),+
+        {
+            fn policies(&self) -> Vec<&dyn Policy<Res, Subj>> {
+                vec![$(&self.$idx as &dyn Policy<Res, Subj>),+]
+            }
+        }
+    };
+}
+
+tuple_policy_set!(A: 0);
+tuple_policy_set!(A: 0, B: 1);
+tuple_policy_set!(A: 0, B: 1, C: 2);
+tuple_policy_set!(A: 0, B: 1, C: 2, D: 3);
+tuple_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Permits if any of the inner policies permits. A policy that is not applicable counts as a
+/// denial, so if nothing permits the result is always a denial.
+pub struct AnyOf<P>(pub P);
+
+impl<P> AnyOf<P> {
+    pub fn new(policies: P) -> Self {
+        AnyOf(policies)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for AnyOf<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: PolicySet<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        any_of(
+            self.0
+                .policies()
+                .into_iter()
+                .map(|policy| policy.decide(resource, subject, action)),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let deciders = subject_deciders(&self.0, subject);
+
+        Box::new(move |resource, action| {
+            any_of(deciders.iter().map(|decide| decide(resource, action)))
+        })
+    }
+}
+
+/// Permits only if every one of the inner policies permits. A policy that is not applicable
+/// counts as a denial, as does an empty set of policies.
+pub struct AllOf<P>(pub P);
+
+impl<P> AllOf<P> {
+    pub fn new(policies: P) -> Self {
+        AllOf(policies)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for AllOf<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: PolicySet<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        all_of(
+            self.0
+                .policies()
+                .into_iter()
+                .map(|policy| policy.decide(resource, subject, action)),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let deciders = subject_deciders(&self.0, subject);
+
+        Box::new(move |resource, action| {
+            all_of(deciders.iter().map(|decide| decide(resource, action)))
+        })
+    }
+}
+
+/// Inverts the result of the inner policy. A policy that is not applicable stays not applicable,
+/// so negating a policy never grants access that the inner policy had no opinion about.
+pub struct Not<P>(pub P);
+
+impl<P> Not<P> {
+    pub fn new(policy: P) -> Self {
+        Not(policy)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for Not<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        negate(self.0.decide(resource, subject, action))
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let decide = self.0.for_subject(subject);
+
+        Box::new(move |resource, action| negate(decide(resource, action)))
+    }
+}
+
+/// Returns the result of the first inner policy that is applicable to the request, or
+/// not applicable if none of them are.
+pub struct FirstApplicable<P>(pub P);
+
+impl<P> FirstApplicable<P> {
+    pub fn new(policies: P) -> Self {
+        FirstApplicable(policies)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for FirstApplicable<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: PolicySet<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        first_applicable(
+            self.0
+                .policies()
+                .into_iter()
+                .map(|policy| policy.decide(resource, subject, action)),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let deciders = subject_deciders(&self.0, subject);
+
+        Box::new(move |resource, action| {
+            first_applicable(deciders.iter().map(|decide| decide(resource, action)))
+        })
+    }
+}
+
+/// Denies if any inner policy denies, otherwise permits if any inner policy permits. If no inner
+/// policy is applicable then neither is this one.
+pub struct DenyOverrides<P>(pub P);
+
+impl<P> DenyOverrides<P> {
+    pub fn new(policies: P) -> Self {
+        DenyOverrides(policies)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for DenyOverrides<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: PolicySet<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        deny_overrides(
+            self.0
+                .policies()
+                .into_iter()
+                .map(|policy| policy.decide(resource, subject, action)),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let deciders = subject_deciders(&self.0, subject);
+
+        Box::new(move |resource, action| {
+            deny_overrides(deciders.iter().map(|decide| decide(resource, action)))
+        })
+    }
+}
+
+/// Permits if any inner policy permits, otherwise denies if any inner policy denies. If no inner
+/// policy is applicable then neither is this one.
+///
+/// This differs from [`AnyOf`] only in that it preserves [`Effect::NotApplicable`], which matters
+/// when it is itself nested inside [`FirstApplicable`] or [`DenyOverrides`].
+pub struct PermitOverrides<P>(pub P);
+
+impl<P> PermitOverrides<P> {
+    pub fn new(policies: P) -> Self {
+        PermitOverrides(policies)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for PermitOverrides<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: PolicySet<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        permit_overrides(
+            self.0
+                .policies()
+                .into_iter()
+                .map(|policy| policy.decide(resource, subject, action)),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let deciders = subject_deciders(&self.0, subject);
+
+        Box::new(move |resource, action| {
+            permit_overrides(deciders.iter().map(|decide| decide(resource, action)))
+        })
+    }
+}
+
+fn subject_deciders<'a, Res, Subj, P>(
+    policies: &'a P,
+    subject: &'a Subj,
+) -> Vec<SubjectDecider<'a, Res>>
+where
+    Res: Resource,
+    Subj: Subject,
+    Res: 'a,
+    P: PolicySet<Res, Subj>,
+{
+    policies
+        .policies()
+        .into_iter()
+        .map(|policy| policy.for_subject(subject))
+        .collect()
+}
+
+fn any_of(decisions: impl Iterator<Item = Decision>) -> Decision {
+    let mut reasons = Vec::new();
+
+    for decision in decisions {
+        if decision.is_permit() {
+            return decision;
+        }
+
+        reasons.push(decision.reason().to_string());
+    }
+
+    Decision::deny(combined_reason("No policy permitted the request", &reasons))
+}
+
+fn all_of(decisions: impl Iterator<Item = Decision>) -> Decision {
+    let mut decisions = decisions.peekable();
+
+    if decisions.peek().is_none() {
+        return Decision::deny("No policies to satisfy");
+    }
+
+    let mut reasons = Vec::new();
+    let mut rules = Vec::new();
+
+    for decision in decisions {
+        match decision.effect() {
+            Effect::Permit => {
+                reasons.push(decision.reason().to_string());
+                rules.extend(decision.rule().map(str::to_string));
+            }
+            Effect::Deny => return decision,
+            Effect::NotApplicable => return Decision::deny(decision.reason()),
+        }
+    }
+
+    let decision = Decision::permit(combined_reason(
+        "All policies permitted the request",
+        &reasons,
+    ));
+
+    if rules.is_empty() {
+        decision
+    } else {
+        decision.with_rule(rules.join(" AND "))
+    }
+}
+
+fn negate(decision: Decision) -> Decision {
+    let negated = match decision.effect() {
+        Effect::Permit => Decision::deny(format!("Negated: {}", decision.reason())),
+        Effect::Deny => Decision::permit(format!("Negated: {}", decision.reason())),
+        Effect::NotApplicable => return decision,
+    };
+
+    match decision.rule() {
+        Some(rule) => negated.with_rule(format!("NOT {}", rule)),
+        None => negated,
+    }
+}
+
+fn first_applicable(decisions: impl Iterator<Item = Decision>) -> Decision {
+    for decision in decisions {
+        if !decision.is_not_applicable() {
+            return decision;
+        }
+    }
+
+    Decision::not_applicable("No policy was applicable to the request")
+}
+
+fn deny_overrides(decisions: impl Iterator<Item = Decision>) -> Decision {
+    let mut permit = None;
+
+    for decision in decisions {
+        match decision.effect() {
+            Effect::Permit => permit = permit.or(Some(decision)),
+            Effect::Deny => return decision,
+            Effect::NotApplicable => {}
+        }
+    }
+
+    permit.unwrap_or_else(|| Decision::not_applicable("No policy was applicable to the request"))
+}
+
+fn permit_overrides(decisions: impl Iterator<Item = Decision>) -> Decision {
+    let mut deny = None;
+
+    for decision in decisions {
+        match decision.effect() {
+            Effect::Permit => return decision,
+            Effect::Deny => deny = deny.or(Some(decision)),
+            Effect::NotApplicable => {}
+        }
+    }
+
+    deny.unwrap_or_else(|| Decision::not_applicable("No policy was applicable to the request"))
+}
+
+fn combined_reason(summary: &str, reasons: &[String]) -> String {
+    if reasons.is_empty() {
+        summary.to_string()
+    } else {
+        format!("{}: {}", summary, reasons.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Doc;
+
+    impl Resource for Doc {
+        type Action = ();
+    }
+
+    struct User;
+
+    impl Subject for User {}
+
+    struct Permit;
+    struct Deny;
+    struct Abstain;
+
+    impl Policy<Doc, User> for Permit {
+        fn decide(&self, _: &Doc, _: &User, _: &()) -> Decision {
+            Decision::permit("permitted").with_rule("permit")
+        }
+    }
+
+    impl Policy<Doc, User> for Deny {
+        fn decide(&self, _: &Doc, _: &User, _: &()) -> Decision {
+            Decision::deny("denied").with_rule("deny")
+        }
+    }
+
+    impl Policy<Doc, User> for Abstain {
+        fn decide(&self, _: &Doc, _: &User, _: &()) -> Decision {
+            Decision::not_applicable("abstained")
+        }
+    }
+
+    fn check<P: Policy<Doc, User>>(policy: P) -> Effect {
+        policy.decide(&Doc, &User, &()).effect()
+    }
+
+    #[test]
+    fn any_of() {
+        assert_eq!(check(AnyOf((Deny, Permit))), Effect::Permit);
+        assert_eq!(check(AnyOf((Deny, Abstain))), Effect::Deny);
+        assert_eq!(check(AnyOf((Abstain,))), Effect::Deny);
+        assert_eq!(check(AnyOf(Vec::<Permit>::new())), Effect::Deny);
+    }
+
+    #[test]
+    fn all_of() {
+        assert_eq!(check(AllOf((Permit, Permit))), Effect::Permit);
+        assert_eq!(check(AllOf((Permit, Deny))), Effect::Deny);
+        assert_eq!(check(AllOf((Permit, Abstain))), Effect::Deny);
+        assert_eq!(check(AllOf(Vec::<Permit>::new())), Effect::Deny);
+    }
+
+    #[test]
+    fn not() {
+        assert_eq!(check(Not(Permit)), Effect::Deny);
+        assert_eq!(check(Not(Deny)), Effect::Permit);
+        assert_eq!(check(Not(Abstain)), Effect::NotApplicable);
+    }
+
+    #[test]
+    fn first_applicable() {
+        assert_eq!(
+            check(FirstApplicable((Abstain, Deny, Permit))),
+            Effect::Deny
+        );
+        assert_eq!(
+            check(FirstApplicable((Abstain, Permit, Deny))),
+            Effect::Permit
+        );
+        assert_eq!(
+            check(FirstApplicable([Abstain, Abstain])),
+            Effect::NotApplicable
+        );
+    }
+
+    #[test]
+    fn deny_overrides() {
+        assert_eq!(check(DenyOverrides((Permit, Deny))), Effect::Deny);
+        assert_eq!(check(DenyOverrides((Permit, Abstain))), Effect::Permit);
+        assert_eq!(check(DenyOverrides((Abstain,))), Effect::NotApplicable);
+    }
+
+    #[test]
+    fn permit_overrides() {
+        assert_eq!(check(PermitOverrides((Deny, Permit))), Effect::Permit);
+        assert_eq!(check(PermitOverrides((Deny, Abstain))), Effect::Deny);
+        assert_eq!(check(PermitOverrides((Abstain,))), Effect::NotApplicable);
+    }
+
+    #[test]
+    fn nested() {
+        let boxed: Vec<Box<dyn Policy<Doc, User>>> = vec![Box::new(Deny), Box::new(Permit)];
+
+        assert_eq!(check(AllOf((AnyOf(boxed), Not(Deny)))), Effect::Permit);
+    }
+
+    #[test]
+    fn for_subject() {
+        let policy = AllOf((AnyOf((Deny, Permit)), Not(Deny)));
+        let decide = policy.for_subject(&User);
+
+        assert_eq!(decide(&Doc, &()), policy.decide(&Doc, &User, &()));
+        assert_eq!(
+            FirstApplicable((Abstain, Deny)).for_subject(&User)(&Doc, &()).effect(),
+            Effect::Deny
+        );
+    }
+
+    #[test]
+    fn reasons() {
+        let decision = AnyOf((Deny, Abstain)).decide(&Doc, &User, &());
+        assert_eq!(
+            decision.reason(),
+            "No policy permitted the request: denied; abstained"
+        );
+
+        let decision = AllOf((Permit, Not(Deny))).decide(&Doc, &User, &());
+        assert_eq!(decision.rule(), Some("permit AND NOT deny"));
+
+        let error = AllOf((Permit, Deny))
+            .authorise(&Doc, &User, &())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Forbidden: Deny: denied (rule: deny)");
+    }
+}
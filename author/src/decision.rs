@@ -0,0 +1,115 @@
+use crate::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+/// The outcome of evaluating a policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Effect {
+    Permit,
+    Deny,
+    /// The policy has no rules that apply to the request, so expresses no opinion either way.
+    NotApplicable,
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Permit => write!(f, "Permit"),
+            Effect::Deny => write!(f, "Deny"),
+            Effect::NotApplicable => write!(f, "Not applicable"),
+        }
+    }
+}
+
+/// The result of an authorisation check, recording not just whether access was granted but which
+/// rule or role decided it and why, so denials can be explained to users and logged usefully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    effect: Effect,
+    rule: Option<String>,
+    reason: String,
+}
+
+impl Decision {
+    pub fn new(effect: Effect, reason: impl Into<String>) -> Self {
+        Decision {
+            effect,
+            rule: None,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn permit(reason: impl Into<String>) -> Self {
+        Decision::new(Effect::Permit, reason)
+    }
+
+    pub fn deny(reason: impl Into<String>) -> Self {
+        Decision::new(Effect::Deny, reason)
+    }
+
+    pub fn not_applicable(reason: impl Into<String>) -> Self {
+        Decision::new(Effect::NotApplicable, reason)
+    }
+
+    /// Records the rule or role that produced this decision.
+    pub fn with_rule(mut self, rule: impl Into<String>) -> Self {
+        self.rule = Some(rule.into());
+        self
+    }
+
+    pub fn effect(&self) -> Effect {
+        self.effect
+    }
+
+    pub fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn is_permit(&self) -> bool {
+        self.effect == Effect::Permit
+    }
+
+    pub fn is_deny(&self) -> bool {
+        self.effect == Effect::Deny
+    }
+
+    pub fn is_not_applicable(&self) -> bool {
+        self.effect == Effect::NotApplicable
+    }
+
+    /// Converts the decision into a plain result. Anything other than an explicit permit is
+    /// treated as forbidden, with the decision preserved inside the error.
+    pub fn into_result(self) -> Result<(), Error> {
+        match self.effect {
+            Effect::Permit => Ok(()),
+            Effect::Deny | Effect::NotApplicable => Err(Error::Forbidden(self)),
+        }
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.effect, self.reason)?;
+
+        if let Some(rule) = &self.rule {
+            write!(f, " (rule: {})", rule)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats a set of roles in a stable order for use in decision reasons.
+pub(crate) fn format_roles<'a, R, I>(roles: I) -> String
+where
+    R: Debug + 'a,
+    I: IntoIterator<Item = &'a R>,
+{
+    let mut roles: Vec<_> = roles.into_iter().map(|r| format!("{:?}", r)).collect();
+    roles.sort();
+
+    format!("[{}]", roles.join(", "))
+}
@@ -0,0 +1,260 @@
+//! Subjects acting on behalf of other subjects, such as a support agent impersonating a customer
+//! to reproduce an issue, or a manager's delegate approving expenses while they are on leave.
+//!
+//! An [`ActingAs`] subject pairs the actor, who is actually making the request, with the
+//! principal they act on behalf of. An [`ActingAsPolicy`] evaluates an inner policy for both and
+//! combines the two decisions, by default only permitting what both of them are permitted to do.
+
+use crate::audit::Auditable;
+#[cfg(feature = "async")]
+use crate::AsyncPolicy;
+use crate::{Decision, Policy, Resource, Subject};
+
+/// Combines the decision for the actor with the decision for the principal.
+pub type Combine = fn(actor: Decision, principal: Decision) -> Decision;
+
+/// Permits only if both the actor and the principal are permitted.
+pub fn intersection(actor: Decision, principal: Decision) -> Decision {
+    match (actor.is_permit(), principal.is_permit()) {
+        (true, true) => principal,
+        (false, _) => Decision::new(
+            actor.effect(),
+            format!("Actor is not permitted: {}", actor.reason()),
+        ),
+        (true, false) => Decision::new(
+            principal.effect(),
+            format!(
+                "Subject acted on behalf of is not permitted: {}",
+                principal.reason()
+            ),
+        ),
+    }
+}
+
+/// Uses only the principal's decision, for impersonation where the actor should see exactly what
+/// the principal would.
+pub fn principal_only(_actor: Decision, principal: Decision) -> Decision {
+    principal
+}
+
+/// A subject acting on behalf of another.
+#[derive(Debug, Clone)]
+pub struct ActingAs<Actor, Principal> {
+    actor: Actor,
+    principal: Principal,
+    combine: Combine,
+}
+
+impl<Actor, Principal> ActingAs<Actor, Principal> {
+    /// The actor acting on behalf of the principal, permitted to do only what both of them are.
+    pub fn new(actor: Actor, principal: Principal) -> Self {
+        ActingAs {
+            actor,
+            principal,
+            combine: intersection,
+        }
+    }
+
+    /// The actor impersonating the principal, permitted to do whatever the principal is.
+    pub fn impersonating(actor: Actor, principal: Principal) -> Self {
+        ActingAs::new(actor, principal).with_combine(principal_only)
+    }
+
+    /// Sets how the decisions for the actor and principal are combined.
+    pub fn with_combine(mut self, combine: Combine) -> Self {
+        self.combine = combine;
+        self
+    }
+
+    /// The subject actually making the request.
+    pub fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    /// The subject the request is made on behalf of.
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+}
+
+impl<Actor, Principal> Subject for ActingAs<Actor, Principal> {}
+
+/// Records both subjects, so that audit logs show who really made each request.
+impl<Actor, Principal> Auditable for ActingAs<Actor, Principal>
+where
+    Actor: Auditable,
+    Principal: Auditable,
+{
+    fn audit_id(&self) -> String {
+        format!(
+            "{} acting as {}",
+            self.actor.audit_id(),
+            self.principal.audit_id()
+        )
+    }
+}
+
+/// Wraps a policy so that it can decide for [`ActingAs`] subjects, by evaluating it for both the
+/// actor and the principal and combining the results.
+pub struct ActingAsPolicy<P>(pub P);
+
+impl<P> ActingAsPolicy<P> {
+    pub fn new(policy: P) -> Self {
+        ActingAsPolicy(policy)
+    }
+}
+
+impl<Res, Actor, Principal, P> Policy<Res, ActingAs<Actor, Principal>> for ActingAsPolicy<P>
+where
+    Res: Resource,
+    Actor: Subject,
+    Principal: Subject,
+    P: Policy<Res, Actor> + Policy<Res, Principal>,
+{
+    fn decide(
+        &self,
+        resource: &Res,
+        subject: &ActingAs<Actor, Principal>,
+        action: &Res::Action,
+    ) -> Decision {
+        let actor = self.0.decide(resource, &subject.actor, action);
+        let principal = self.0.decide(resource, &subject.principal, action);
+
+        (subject.combine)(actor, principal)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<Res, Actor, Principal, P> AsyncPolicy<Res, ActingAs<Actor, Principal>> for ActingAsPolicy<P>
+where
+    Res: Resource<Action: Sync> + Sync,
+    Actor: Subject + Sync,
+    Principal: Subject + Sync,
+    P: AsyncPolicy<Res, Actor> + AsyncPolicy<Res, Principal>,
+{
+    async fn decide(
+        &self,
+        resource: &Res,
+        subject: &ActingAs<Actor, Principal>,
+        action: &Res::Action,
+    ) -> Decision {
+        let actor =
+            AsyncPolicy::<Res, Actor>::decide(&self.0, resource, &subject.actor, action).await;
+        let principal =
+            AsyncPolicy::<Res, Principal>::decide(&self.0, resource, &subject.principal, action)
+                .await;
+
+        (subject.combine)(actor, principal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditedPolicy, MemorySink};
+    use crate::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+    use std::collections::HashSet;
+
+    struct Expense;
+
+    impl Resource for Expense {
+        type Action = ExpenseAction;
+    }
+
+    impl crate::NamedResource for Expense {
+        const RESOURCE_NAME: &'static str = "expense";
+    }
+
+    impl Auditable for Expense {
+        fn audit_id(&self) -> String {
+            "expense".to_string()
+        }
+    }
+
+    impl RbacResource<Role> for Expense {
+        fn allowed_roles(&self, action: &ExpenseAction) -> HashSet<Role> {
+            match action {
+                ExpenseAction::View => HashSet::from([Role::Staff, Role::Manager]),
+                ExpenseAction::Approve => HashSet::from([Role::Manager]),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum ExpenseAction {
+        View,
+        Approve,
+    }
+
+    impl crate::Named for ExpenseAction {
+        fn name(&self) -> &str {
+            match self {
+                ExpenseAction::View => "view",
+                ExpenseAction::Approve => "approve",
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Staff,
+        Manager,
+        Support,
+    }
+
+    struct User(&'static str, Role);
+
+    impl Subject for User {}
+
+    impl GlobalRbacSubject for User {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            HashSet::from([self.1.clone()])
+        }
+    }
+
+    impl Auditable for User {
+        fn audit_id(&self) -> String {
+            self.0.to_string()
+        }
+    }
+
+    #[test]
+    fn delegate_with_intersection() {
+        let policy = ActingAsPolicy::new(GlobalRbacPolicy::new());
+        let delegate = ActingAs::new(
+            User("deputy", Role::Manager),
+            User("manager", Role::Manager),
+        );
+        assert!(policy
+            .decide(&Expense, &delegate, &ExpenseAction::Approve)
+            .is_permit());
+
+        let decision = policy.decide(
+            &Expense,
+            &ActingAs::new(User("clerk", Role::Staff), User("manager", Role::Manager)),
+            &ExpenseAction::Approve,
+        );
+        assert!(decision.is_deny());
+        assert!(decision.reason().starts_with("Actor is not permitted: "));
+    }
+
+    #[test]
+    fn impersonate_and_audit_actor() {
+        let sink = MemorySink::new();
+        let policy = AuditedPolicy::new(ActingAsPolicy::new(GlobalRbacPolicy::new()), sink.clone());
+        let support =
+            ActingAs::impersonating(User("agent", Role::Support), User("customer", Role::Staff));
+
+        assert!(policy
+            .decide(&Expense, &support, &ExpenseAction::View)
+            .is_permit());
+        assert!(policy
+            .decide(&Expense, &support, &ExpenseAction::Approve)
+            .is_deny());
+
+        assert_eq!(sink.records()[0].subject, "agent acting as customer");
+    }
+}
@@ -0,0 +1,384 @@
+//! Access to individual fields of resources, such as letting support agents read a customer but
+//! not their payment details.
+//!
+//! Each field of a resource can be treated as a resource in its own right, a [`Field`], with its
+//! own [`FieldAction`]s, so policies can decide who may read or write it. Resources declare which
+//! of their fields are sensitive by implementing [`FieldResource`], and with the `redact` feature
+//! a [`Redactor`] serializes resources with the sensitive fields a subject can't read removed or
+//! masked.
+
+use crate::{Named, Resource};
+
+/// A field of a resource, which may be given its own access rules.
+#[derive(Debug)]
+pub struct Field<'a, Res> {
+    resource: &'a Res,
+    name: &'a str,
+}
+
+impl<'a, Res> Field<'a, Res> {
+    pub fn new(resource: &'a Res, name: &'a str) -> Self {
+        Field { resource, name }
+    }
+
+    pub fn resource(&self) -> &'a Res {
+        self.resource
+    }
+
+    /// The name of the field, as a dotted path for nested fields such as `payment.card_number`.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+}
+
+impl<Res> Resource for Field<'_, Res> {
+    type Action = FieldAction;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldAction {
+    Read,
+    Write,
+}
+
+impl Named for FieldAction {
+    fn name(&self) -> &str {
+        match self {
+            FieldAction::Read => "read",
+            FieldAction::Write => "write",
+        }
+    }
+}
+
+/// A resource with fields that need their own access rules.
+pub trait FieldResource: Resource {
+    /// The fields that are only visible to subjects allowed to read them, as named when the
+    /// resource is serialized. Nested fields are written as dotted paths, which apply to every
+    /// element of any array they pass through.
+    fn sensitive_fields(&self) -> Vec<&'static str>;
+}
+
+#[cfg(feature = "redact")]
+pub use redact::{RedactError, Redaction, Redactor};
+
+#[cfg(feature = "redact")]
+mod redact {
+    use crate::field::{Field, FieldAction, FieldResource};
+    use crate::{Policy, Subject};
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::marker::PhantomData;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    pub enum RedactError {
+        #[error("Failed to serialize resource: {0}")]
+        Serialize(#[from] serde_json::Error),
+        #[error("Sensitive field '{0}' not found in serialized resource")]
+        UnknownField(String),
+    }
+
+    /// What is done with fields a subject isn't allowed to read.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Redaction {
+        /// Leaves the field out.
+        Remove,
+        /// Replaces the field's value.
+        Mask(Value),
+    }
+
+    /// Serializes resources, hiding the sensitive fields a subject isn't allowed to read.
+    pub struct Redactor<'p, P, Subj> {
+        policy: &'p P,
+        redaction: Redaction,
+        _subject: PhantomData<fn(&Subj)>,
+    }
+
+    impl<'p, P, Subj> Redactor<'p, P, Subj>
+    where
+        Subj: Subject,
+    {
+        /// Creates a redactor that removes fields the policy doesn't permit the subject to read.
+        pub fn new(policy: &'p P) -> Self {
+            Redactor {
+                policy,
+                redaction: Redaction::Remove,
+                _subject: PhantomData,
+            }
+        }
+
+        /// Replaces fields the subject can't read with a value, such as `"[redacted]"`, instead
+        /// of removing them.
+        pub fn mask_with(mut self, value: impl Into<Value>) -> Self {
+            self.redaction = Redaction::Mask(value.into());
+            self
+        }
+
+        /// Serializes the resource, hiding the sensitive fields the subject can't read. Fails if
+        /// a sensitive field can't be found, rather than risk serving it.
+        pub fn redact<Res>(&self, resource: &Res, subject: &Subj) -> Result<Value, RedactError>
+        where
+            Res: FieldResource + Serialize,
+            P: for<'a> Policy<Field<'a, Res>, Subj>,
+        {
+            let mut value = serde_json::to_value(resource)?;
+
+            for name in resource.sensitive_fields() {
+                let field = Field::new(resource, name);
+
+                if !self
+                    .policy
+                    .decide(&field, subject, &FieldAction::Read)
+                    .is_permit()
+                {
+                    let path: Vec<_> = name.split('.').collect();
+                    hide(&mut value, &path, &self.redaction)
+                        .map_err(|()| RedactError::UnknownField(name.to_string()))?;
+                }
+            }
+
+            Ok(value)
+        }
+    }
+
+    /// Hides the field at the path, applying the rest of the path to every element of any array
+    /// reached along the way. Nulls are left alone as there is nothing to hide, but any other
+    /// value the path can't be followed through is an error, so a mistyped or outdated path can't
+    /// leave a field visible.
+    fn hide(value: &mut Value, path: &[&str], redaction: &Redaction) -> Result<(), ()> {
+        match value {
+            Value::Null => Ok(()),
+            Value::Array(elements) => elements
+                .iter_mut()
+                .try_for_each(|element| hide(element, path, redaction)),
+            Value::Object(object) => match path {
+                [] => Err(()),
+                [name] => {
+                    let field = object.get_mut(*name).ok_or(())?;
+
+                    match redaction {
+                        Redaction::Remove => {
+                            object.remove(*name);
+                        }
+                        Redaction::Mask(mask) => *field = mask.clone(),
+                    }
+
+                    Ok(())
+                }
+                [parent, rest @ ..] => hide(object.get_mut(*parent).ok_or(())?, rest, redaction),
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "redact"))]
+mod tests {
+    use super::*;
+    use crate::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+    use crate::{Policy, Subject};
+    use serde::Serialize;
+    use serde_json::json;
+    use std::collections::HashSet;
+
+    #[derive(Serialize)]
+    struct Customer {
+        name: String,
+        email: String,
+        payment: Payment,
+    }
+
+    /// Serialized as an array of customers.
+    #[derive(Serialize)]
+    #[serde(transparent)]
+    struct Customers(Vec<Customer>);
+
+    impl Resource for Customers {
+        type Action = ();
+    }
+
+    impl FieldResource for Customers {
+        fn sensitive_fields(&self) -> Vec<&'static str> {
+            vec!["payment.card_number"]
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Wallet {
+        cards: Vec<Payment>,
+        missing_card: Option<Payment>,
+    }
+
+    impl Resource for Wallet {
+        type Action = ();
+    }
+
+    impl FieldResource for Wallet {
+        fn sensitive_fields(&self) -> Vec<&'static str> {
+            vec!["cards.card_number", "missing_card.card_number"]
+        }
+    }
+
+    struct Misnamed;
+
+    impl Resource for Misnamed {
+        type Action = ();
+    }
+
+    impl Serialize for Misnamed {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            customer().serialize(serializer)
+        }
+    }
+
+    impl FieldResource for Misnamed {
+        fn sensitive_fields(&self) -> Vec<&'static str> {
+            vec!["payment.cvv"]
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Payment {
+        card_number: String,
+        expiry: String,
+    }
+
+    impl Resource for Customer {
+        type Action = ();
+    }
+
+    impl FieldResource for Customer {
+        fn sensitive_fields(&self) -> Vec<&'static str> {
+            vec!["email", "payment.card_number"]
+        }
+    }
+
+    impl<Res> RbacResource<Role> for Field<'_, Res> {
+        fn allowed_roles(&self, action: &FieldAction) -> HashSet<Role> {
+            match (self.name(), action) {
+                ("email", FieldAction::Read) => HashSet::from([Role::Support, Role::Billing]),
+                _ => HashSet::from([Role::Billing]),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Support,
+        Billing,
+    }
+
+    struct Agent(Role);
+
+    impl Subject for Agent {}
+
+    impl GlobalRbacSubject for Agent {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            HashSet::from([self.0.clone()])
+        }
+    }
+
+    fn customer() -> Customer {
+        Customer {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            payment: Payment {
+                card_number: "4111111111111111".to_string(),
+                expiry: "12/30".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn redact_unreadable_fields() {
+        let policy = GlobalRbacPolicy::new();
+        let customer = customer();
+
+        let redacted = Redactor::new(&policy)
+            .redact(&customer, &Agent(Role::Support))
+            .unwrap();
+        assert_eq!(
+            redacted,
+            json!({
+                "name": "Ada",
+                "email": "ada@example.com",
+                "payment": { "expiry": "12/30" },
+            })
+        );
+
+        let masked = Redactor::new(&policy)
+            .mask_with("[redacted]")
+            .redact(&customer, &Agent(Role::Support))
+            .unwrap();
+        assert_eq!(masked["payment"]["card_number"], "[redacted]");
+
+        let unredacted = Redactor::new(&policy)
+            .redact(&customer, &Agent(Role::Billing))
+            .unwrap();
+        assert_eq!(unredacted["payment"]["card_number"], "4111111111111111");
+    }
+
+    #[test]
+    fn redact_fields_in_arrays() {
+        let policy = GlobalRbacPolicy::new();
+        let support = Agent(Role::Support);
+
+        let customers = Redactor::new(&policy)
+            .redact(&Customers(vec![customer(), customer()]), &support)
+            .unwrap();
+        for customer in customers.as_array().unwrap() {
+            assert_eq!(customer["payment"], json!({ "expiry": "12/30" }));
+        }
+
+        let wallet = Wallet {
+            cards: vec![customer().payment, customer().payment],
+            missing_card: None,
+        };
+        let redacted = Redactor::new(&policy)
+            .mask_with("[redacted]")
+            .redact(&wallet, &support)
+            .unwrap();
+        assert_eq!(
+            redacted,
+            json!({
+                "cards": [
+                    { "card_number": "[redacted]", "expiry": "12/30" },
+                    { "card_number": "[redacted]", "expiry": "12/30" },
+                ],
+                "missing_card": null,
+            })
+        );
+    }
+
+    #[test]
+    fn fail_on_unknown_fields() {
+        let policy = GlobalRbacPolicy::new();
+
+        let error = Redactor::new(&policy)
+            .redact(&Misnamed, &Agent(Role::Support))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Sensitive field 'payment.cvv' not found in serialized resource"
+        );
+    }
+
+    #[test]
+    fn authorise_field_writes() {
+        let policy = GlobalRbacPolicy::new();
+        let customer = customer();
+        let email = Field::new(&customer, "email");
+
+        assert!(policy
+            .authorise(&email, &Agent(Role::Support), &FieldAction::Read)
+            .is_ok());
+        assert!(policy
+            .authorise(&email, &Agent(Role::Support), &FieldAction::Write)
+            .is_err());
+        assert!(policy
+            .authorise(&email, &Agent(Role::Billing), &FieldAction::Write)
+            .is_ok());
+    }
+}
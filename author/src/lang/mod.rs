@@ -0,0 +1,605 @@
+//! A small declarative policy language in the style of Cedar, so that who may do what can be
+//! reviewed and changed as text rather than as Rust `match` arms:
+//!
+//! ```text
+//! @id("support-read")
+//! permit (
+//!     principal in Role::"support",
+//!     action in [Action::"read", Action::"list"],
+//!     resource is customer
+//! ) when { resource.region == principal.region };
+//!
+//! forbid (principal, action == Action::"delete", resource)
+//!     unless { context.mfa };
+//! ```
+//!
+//! Each policy has a scope restricting the principal, action and resource it applies to, followed
+//! by any number of `when` and `unless` conditions, written in the
+//! [ABAC expression language](crate::abac::expr) with the roots `principal`, `resource` and
+//! `context`. Resource types are named by their [`NamedResource::RESOURCE_NAME`] and actions by
+//! their [`Named`] name.
+//!
+//! If any forbid policy matches, the request is denied. Otherwise it is permitted if any permit
+//! policy matches, and the set is not applicable if none do. A condition that fails to evaluate
+//! denies the request. Policies can be checked against a [`Schema`] of the known resources,
+//! actions, principals and attributes before they are used.
+
+mod parser;
+mod schema;
+
+pub use schema::{Schema, Type, ValidationError};
+
+use crate::abac::expr::{resolve_fields, Context, EvalError, Expr, Functions, ParseError, Span};
+use crate::abac::{AbacResource, AbacSubject, Environment, RuleEffect, Value};
+use crate::{Decision, Named, NamedResource, Policy};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LangError {
+    #[error("Failed to parse policies: {0}")]
+    Parse(#[from] ParseError),
+    #[error("Invalid policies: {}", format_validation_errors(.0))]
+    Invalid(Vec<ValidationError>),
+}
+
+fn format_validation_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The type and id of an entity, written `Role::"admin"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityUid {
+    pub entity_type: String,
+    pub id: String,
+}
+
+impl EntityUid {
+    pub fn new(entity_type: impl Into<String>, id: impl Into<String>) -> Self {
+        EntityUid {
+            entity_type: entity_type.into(),
+            id: id.into(),
+        }
+    }
+}
+
+impl Display for EntityUid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{:?}", self.entity_type, self.id)
+    }
+}
+
+/// A principal that policies can refer to.
+pub trait Principal: AbacSubject {
+    /// The principal's own uid, as in `principal == User::"alice"`. Its type is matched by
+    /// `principal is User`.
+    fn uid(&self) -> EntityUid;
+
+    /// The entities the principal belongs to, such as its roles, as in
+    /// `principal in Role::"admin"`.
+    fn parents(&self) -> Vec<EntityUid> {
+        Vec::new()
+    }
+}
+
+/// An entity uid or type name along with where it was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrincipalScope {
+    Any,
+    Eq(Spanned<EntityUid>),
+    In(Spanned<EntityUid>),
+    Is(Spanned<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionScope {
+    Any,
+    Eq(Spanned<EntityUid>),
+    In(Vec<Spanned<EntityUid>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceScope {
+    Any,
+    Is(Spanned<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionKind {
+    When,
+    Unless,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub kind: ConditionKind,
+    pub expr: Expr,
+}
+
+/// A single `permit` or `forbid` policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    /// The policy's `@id` annotation, or `policy` followed by its position in the set.
+    pub id: String,
+    pub effect: RuleEffect,
+    pub principal: PrincipalScope,
+    pub action: ActionScope,
+    pub resource: ResourceScope,
+    pub conditions: Vec<Condition>,
+    pub span: Span,
+}
+
+impl Statement {
+    fn in_scope<Res, Subj>(&self, subject: &Subj, action: &str) -> bool
+    where
+        Res: NamedResource,
+        Subj: Principal,
+    {
+        let principal = match &self.principal {
+            PrincipalScope::Any => true,
+            PrincipalScope::Eq(uid) => subject.uid() == uid.value,
+            PrincipalScope::In(uid) => {
+                subject.uid() == uid.value || subject.parents().contains(&uid.value)
+            }
+            PrincipalScope::Is(name) => subject.uid().entity_type == name.value,
+        };
+
+        let action_matches = |uid: &Spanned<EntityUid>| uid.value.id == action;
+        let action = match &self.action {
+            ActionScope::Any => true,
+            ActionScope::Eq(uid) => action_matches(uid),
+            ActionScope::In(uids) => uids.iter().any(action_matches),
+        };
+
+        let resource = match &self.resource {
+            ResourceScope::Any => true,
+            ResourceScope::Is(name) => name.value == Res::RESOURCE_NAME,
+        };
+
+        principal && action && resource
+    }
+}
+
+type ContextProvider = Arc<dyn Fn() -> Environment + Send + Sync>;
+
+/// The policies parsed from a document written in the policy language.
+#[derive(Clone)]
+pub struct PolicyDocument {
+    statements: Vec<Statement>,
+    functions: Functions,
+    context: ContextProvider,
+}
+
+impl PolicyDocument {
+    /// Parses policies without checking them against a schema.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        Ok(PolicyDocument {
+            statements: parser::parse(source)?,
+            functions: Functions::new(),
+            context: Arc::new(Environment::new),
+        })
+    }
+
+    /// Parses policies and checks them against the schema.
+    pub fn parse_with_schema(source: &str, schema: &Schema) -> Result<Self, LangError> {
+        let policies = Self::parse(source)?;
+        schema.validate(&policies).map_err(LangError::Invalid)?;
+
+        Ok(policies)
+    }
+
+    /// Sets the function called on each decision to provide the `context` attributes.
+    pub fn with_context<F>(mut self, context: F) -> Self
+    where
+        F: Fn() -> Environment + Send + Sync + 'static,
+    {
+        self.context = Arc::new(context);
+        self
+    }
+
+    /// Replaces the functions that conditions may call.
+    pub fn with_functions(mut self, functions: Functions) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+
+    pub fn functions(&self) -> &Functions {
+        &self.functions
+    }
+}
+
+struct RequestContext<'a, Res, Subj> {
+    resource: &'a Res,
+    subject: &'a Subj,
+    context: Environment,
+}
+
+impl<Res, Subj> Context for RequestContext<'_, Res, Subj>
+where
+    Res: AbacResource,
+    Subj: AbacSubject,
+{
+    fn resolve(&self, path: &[String]) -> Option<Value> {
+        let [root, name, fields @ ..] = path else {
+            return None;
+        };
+
+        let value = match root.as_str() {
+            "principal" => self.subject.attribute(name)?,
+            "resource" => self.resource.attribute(name)?,
+            "context" => self.context.get(name)?.clone(),
+            _ => return None,
+        };
+
+        resolve_fields(&value, fields)
+    }
+}
+
+impl<Res, Subj> Policy<Res, Subj> for PolicyDocument
+where
+    Res: AbacResource + NamedResource<Action: Named>,
+    Subj: Principal,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let context = RequestContext {
+            resource,
+            subject,
+            context: (self.context)(),
+        };
+
+        let mut permitted_by = None;
+
+        for statement in self
+            .statements
+            .iter()
+            .filter(|s| s.in_scope::<Res, Subj>(subject, action.name()))
+        {
+            let satisfied = statement.conditions.iter().try_fold(true, |satisfied, c| {
+                Ok::<_, EvalError>(
+                    satisfied
+                        && c.expr.evaluate_bool(&context, &self.functions)?
+                            == (c.kind == ConditionKind::When),
+                )
+            });
+
+            match satisfied {
+                Ok(true) if statement.effect == RuleEffect::Forbid => {
+                    return Decision::deny("Forbidden by policy").with_rule(statement.id.clone());
+                }
+                Ok(true) => {
+                    permitted_by.get_or_insert(statement);
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    return Decision::deny(format!("Failed to evaluate condition: {}", e))
+                        .with_rule(statement.id.clone());
+                }
+            }
+        }
+
+        match permitted_by {
+            Some(statement) => {
+                Decision::permit("Permitted by policy").with_rule(statement.id.clone())
+            }
+            None => Decision::not_applicable(format!(
+                "No policy permits action '{}' on {}",
+                action.name(),
+                Res::RESOURCE_NAME
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Enumerable, Resource, Subject};
+
+    struct Customer {
+        region: &'static str,
+    }
+
+    impl Resource for Customer {
+        type Action = CustomerAction;
+    }
+
+    impl NamedResource for Customer {
+        const RESOURCE_NAME: &'static str = "customer";
+    }
+
+    impl AbacResource for Customer {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "region" => Some(self.region.into()),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum CustomerAction {
+        Read,
+        Delete,
+    }
+
+    impl Named for CustomerAction {
+        fn name(&self) -> &str {
+            match self {
+                CustomerAction::Read => "read",
+                CustomerAction::Delete => "delete",
+            }
+        }
+    }
+
+    impl Enumerable for CustomerAction {
+        fn all() -> Vec<Self> {
+            vec![CustomerAction::Read, CustomerAction::Delete]
+        }
+    }
+
+    struct User {
+        name: &'static str,
+        region: &'static str,
+        roles: Vec<&'static str>,
+    }
+
+    impl Subject for User {}
+
+    impl AbacSubject for User {
+        fn attribute(&self, name: &str) -> Option<Value> {
+            match name {
+                "region" => Some(self.region.into()),
+                _ => None,
+            }
+        }
+    }
+
+    impl Principal for User {
+        fn uid(&self) -> EntityUid {
+            EntityUid::new("User", self.name)
+        }
+
+        fn parents(&self) -> Vec<EntityUid> {
+            self.roles
+                .iter()
+                .map(|r| EntityUid::new("Role", *r))
+                .collect()
+        }
+    }
+
+    const POLICIES: &str = r#"
+        // Support staff can read customers in their own region
+        @id("support-read")
+        permit (
+            principal in Role::"support",
+            action == Action::"read",
+            resource is customer
+        ) when { resource.region == principal.region };
+
+        @id("admin")
+        permit (principal in Role::"admin", action, resource);
+
+        @id("mfa-delete")
+        forbid (principal, action in [Action::"delete"], resource)
+            unless { context.mfa };
+    "#;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .resource::<Customer>([("region", Type::String)])
+            .principal("User", [("region", Type::String)])
+            .role(EntityUid::new("Role", "support"))
+            .role(EntityUid::new("Role", "admin"))
+            .context([("mfa", Type::Bool)])
+    }
+
+    #[test]
+    fn evaluate_policies() {
+        let policies = PolicyDocument::parse_with_schema(POLICIES, &schema())
+            .unwrap()
+            .with_context(|| Environment::from([("mfa".to_string(), false.into())]));
+
+        let support = User {
+            name: "sam",
+            region: "eu",
+            roles: vec!["support"],
+        };
+        let admin = User {
+            name: "alex",
+            region: "us",
+            roles: vec!["admin"],
+        };
+        let eu = Customer { region: "eu" };
+        let us = Customer { region: "us" };
+
+        let decision = policies.decide(&eu, &support, &CustomerAction::Read);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("support-read"));
+
+        assert!(policies
+            .decide(&us, &support, &CustomerAction::Read)
+            .is_not_applicable());
+        assert!(policies
+            .decide(&us, &admin, &CustomerAction::Read)
+            .is_permit());
+
+        let decision = policies.decide(&us, &admin, &CustomerAction::Delete);
+        assert!(decision.is_deny());
+        assert_eq!(decision.rule(), Some("mfa-delete"));
+    }
+
+    #[test]
+    fn report_errors_with_spans() {
+        let source = "permit (principal, action, resource)\n  when { resource.region = 1 };";
+        let Err(error) = PolicyDocument::parse(source) else {
+            panic!("expected policies to fail to parse");
+        };
+        assert_eq!(error.span.line_column(source), (2, 26));
+
+        let source = "permit (principal in Role::\"support\", action == Action::\"write\", \
+                      resource is customer)\n  when { resource.region > 3 && principal.team };";
+        let Err(LangError::Invalid(errors)) = PolicyDocument::parse_with_schema(source, &schema())
+        else {
+            panic!("expected policies to be invalid");
+        };
+
+        let messages: Vec<_> = errors
+            .iter()
+            .map(|e| (&source[e.span.start..e.span.end], e.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "Action::\"write\"",
+                    "unknown action 'write' for resource type 'customer'"
+                ),
+                ("resource.region > 3", "can't compare string with int"),
+                (
+                    "principal.team",
+                    "unknown attribute 'team' on principal type 'User'"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluate_unless_conditions() {
+        let source = r#"
+            permit (principal, action, resource);
+            forbid (principal, action == Action::"delete", resource) unless { context.mfa };
+        "#;
+        let user = User {
+            name: "sam",
+            region: "eu",
+            roles: vec![],
+        };
+        let customer = Customer { region: "eu" };
+
+        let without_mfa = PolicyDocument::parse(source)
+            .unwrap()
+            .with_context(|| Environment::from([("mfa".to_string(), false.into())]));
+        let decision = without_mfa.decide(&customer, &user, &CustomerAction::Delete);
+        assert!(decision.is_deny());
+        assert_eq!(decision.rule(), Some("policy1"));
+
+        let with_mfa = PolicyDocument::parse(source)
+            .unwrap()
+            .with_context(|| Environment::from([("mfa".to_string(), true.into())]));
+        let decision = with_mfa.decide(&customer, &user, &CustomerAction::Delete);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("policy0"));
+    }
+
+    #[test]
+    fn evaluate_principal_scopes() {
+        let source = r#"
+            @id("alex") permit (principal == User::"alex", action == Action::"delete", resource);
+            @id("users") permit (principal is User, action == Action::"read", resource);
+            @id("teams") permit (principal is Billing::Team, action, resource);
+        "#;
+        let policies = PolicyDocument::parse(source).unwrap();
+
+        let alex = User {
+            name: "alex",
+            region: "us",
+            roles: vec![],
+        };
+        let sam = User {
+            name: "sam",
+            region: "eu",
+            roles: vec![],
+        };
+        let customer = Customer { region: "eu" };
+
+        let decision = policies.decide(&customer, &alex, &CustomerAction::Delete);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("alex"));
+        assert!(policies
+            .decide(&customer, &sam, &CustomerAction::Delete)
+            .is_not_applicable());
+
+        let decision = policies.decide(&customer, &sam, &CustomerAction::Read);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("users"));
+    }
+
+    #[test]
+    fn parse_comments_and_namespaced_types() {
+        let source = r#"
+            // Finance can read invoices
+            permit (
+                principal in Billing::Team::"finance", // the whole team
+                action,
+                resource is Billing::Invoice
+            );
+            // forbid (principal, action, resource);
+        "#;
+        let policies = PolicyDocument::parse(source).unwrap();
+
+        let [statement] = policies.statements() else {
+            panic!("expected a single statement");
+        };
+        let PrincipalScope::In(team) = &statement.principal else {
+            panic!("expected an 'in' principal scope");
+        };
+        assert_eq!(team.value, EntityUid::new("Billing::Team", "finance"));
+        assert_eq!(
+            &source[team.span.start..team.span.end],
+            "Billing::Team::\"finance\""
+        );
+
+        let ResourceScope::Is(invoice) = &statement.resource else {
+            panic!("expected an 'is' resource scope");
+        };
+        assert_eq!(invoice.value, "Billing::Invoice");
+        assert_eq!(
+            &source[invoice.span.start..invoice.span.end],
+            "Billing::Invoice"
+        );
+    }
+
+    #[test]
+    fn report_syntax_errors() {
+        let errors = [
+            (
+                "permit (principal == User::\"alex, action, resource);",
+                "Unterminated string",
+                "\"alex, action, resource);",
+            ),
+            (
+                "@owner(\"sam\")\npermit (principal, action, resource);",
+                "Unknown annotation '@owner'",
+                "@owner",
+            ),
+            (
+                "permit (principal, action, resource) when { context.mfa;",
+                "Unclosed '{' in condition",
+                "{ context.mfa;",
+            ),
+        ];
+
+        for (source, message, spanned) in errors {
+            let Err(error) = PolicyDocument::parse(source) else {
+                panic!("expected {:?} to fail to parse", source);
+            };
+            assert_eq!(error.message, message);
+            assert_eq!(&source[error.span.start..error.span.end], spanned);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::abac::expr::{Expr, ParseError, Span};
+use crate::abac::RuleEffect;
+use crate::lang::{
+    ActionScope, Condition, ConditionKind, EntityUid, PrincipalScope, ResourceScope, Spanned,
+    Statement,
+};
+
+pub fn parse(source: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut parser = Parser {
+        source,
+        position: 0,
+    };
+    let mut statements = Vec::new();
+
+    while !parser.at_end() {
+        let statement = parser.statement(statements.len())?;
+        statements.push(statement);
+    }
+
+    Ok(statements)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    /// Skips whitespace and `//` comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_trivia();
+        self.position == self.source.len()
+    }
+
+    fn error(&self, message: impl Into<String>, span: Span) -> ParseError {
+        ParseError {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Describes what comes next, for error messages.
+    fn found(&mut self) -> (String, Span) {
+        self.skip_trivia();
+
+        let start = self.position;
+        let length = self.ident_length();
+
+        if length > 0 {
+            let ident = &self.rest()[..length];
+            (format!("'{}'", ident), Span::new(start, start + length))
+        } else if let Some(c) = self.rest().chars().next() {
+            (format!("'{}'", c), Span::new(start, start + c.len_utf8()))
+        } else {
+            ("end of policies".to_string(), Span::new(start, start))
+        }
+    }
+
+    fn expected(&mut self, expected: &str) -> ParseError {
+        let (found, span) = self.found();
+        self.error(format!("Expected {} but found {}", expected, found), span)
+    }
+
+    fn ident_length(&self) -> usize {
+        let rest = self.rest();
+
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return 0;
+        }
+
+        rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len())
+    }
+
+    fn ident(&mut self) -> Option<Spanned<String>> {
+        self.skip_trivia();
+
+        let length = self.ident_length();
+
+        if length == 0 {
+            return None;
+        }
+
+        let start = self.position;
+        self.position += length;
+
+        Some(Spanned {
+            value: self.source[start..self.position].to_string(),
+            span: Span::new(start, self.position),
+        })
+    }
+
+    fn expect_ident(&mut self, expected: &str) -> Result<Spanned<String>, ParseError> {
+        self.ident().ok_or_else(|| self.expected(expected))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> Option<Span> {
+        self.skip_trivia();
+
+        if self.ident_length() == keyword.len() && self.rest().starts_with(keyword) {
+            let start = self.position;
+            self.position += keyword.len();
+            Some(Span::new(start, self.position))
+        } else {
+            None
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Span, ParseError> {
+        self.eat_keyword(keyword)
+            .ok_or_else(|| self.expected(&format!("'{}'", keyword)))
+    }
+
+    fn eat(&mut self, punctuation: &str) -> Option<Span> {
+        self.skip_trivia();
+
+        if self.rest().starts_with(punctuation) {
+            let start = self.position;
+            self.position += punctuation.len();
+            Some(Span::new(start, self.position))
+        } else {
+            None
+        }
+    }
+
+    fn expect(&mut self, punctuation: &str) -> Result<Span, ParseError> {
+        self.eat(punctuation)
+            .ok_or_else(|| self.expected(&format!("'{}'", punctuation)))
+    }
+
+    fn string(&mut self) -> Result<Spanned<String>, ParseError> {
+        let start = self.expect("\"")?.start;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+
+        loop {
+            match chars.next() {
+                Some((i, '"')) => {
+                    self.position += i + 1;
+                    break;
+                }
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, c)) => value.push(c),
+                    None => return Err(self.unterminated(start)),
+                },
+                Some((_, c)) => value.push(c),
+                None => return Err(self.unterminated(start)),
+            }
+        }
+
+        Ok(Spanned {
+            value,
+            span: Span::new(start, self.position),
+        })
+    }
+
+    fn unterminated(&self, start: usize) -> ParseError {
+        self.error("Unterminated string", Span::new(start, self.source.len()))
+    }
+
+    /// Parses an entity uid such as `Role::"admin"`, whose type may itself be namespaced, as in
+    /// `Billing::Team::"finance"`.
+    fn entity_uid(&mut self) -> Result<Spanned<EntityUid>, ParseError> {
+        let first = self.expect_ident("an entity such as Role::\"admin\"")?;
+        let mut entity_type = first.value;
+
+        loop {
+            self.expect("::")?;
+            self.skip_trivia();
+
+            if self.rest().starts_with('"') {
+                let id = self.string()?;
+
+                return Ok(Spanned {
+                    value: EntityUid::new(entity_type, id.value),
+                    span: first.span.to(id.span),
+                });
+            }
+
+            let next = self.expect_ident("an entity id")?;
+            entity_type = format!("{}::{}", entity_type, next.value);
+        }
+    }
+
+    /// Parses an entity type name, which may be namespaced.
+    fn type_name(&mut self) -> Result<Spanned<String>, ParseError> {
+        let mut name = self.expect_ident("a type name")?;
+
+        while self.rest().starts_with("::") {
+            self.position += 2;
+            let next = self.expect_ident("a type name")?;
+            name.value = format!("{}::{}", name.value, next.value);
+            name.span = name.span.to(next.span);
+        }
+
+        Ok(name)
+    }
+
+    fn statement(&mut self, index: usize) -> Result<Statement, ParseError> {
+        let mut id = None;
+
+        while let Some(at) = self.eat("@") {
+            let annotation = self.expect_ident("an annotation name")?;
+            self.expect("(")?;
+            let value = self.string()?;
+            self.expect(")")?;
+
+            if annotation.value != "id" {
+                return Err(self.error(
+                    format!("Unknown annotation '@{}'", annotation.value),
+                    at.to(annotation.span),
+                ));
+            }
+
+            id = Some(value.value);
+        }
+
+        let effect = self.expect_ident("'permit' or 'forbid'")?;
+        let start = effect.span;
+        let effect = match effect.value.as_str() {
+            "permit" => RuleEffect::Permit,
+            "forbid" => RuleEffect::Forbid,
+            other => {
+                return Err(self.error(
+                    format!("Expected 'permit' or 'forbid' but found '{}'", other),
+                    effect.span,
+                ))
+            }
+        };
+
+        self.expect("(")?;
+        let principal = self.principal_scope()?;
+        self.expect(",")?;
+        let action = self.action_scope()?;
+        self.expect(",")?;
+        let resource = self.resource_scope()?;
+        self.expect(")")?;
+
+        let mut conditions = Vec::new();
+
+        loop {
+            let kind = if self.eat_keyword("when").is_some() {
+                ConditionKind::When
+            } else if self.eat_keyword("unless").is_some() {
+                ConditionKind::Unless
+            } else {
+                break;
+            };
+
+            conditions.push(Condition {
+                kind,
+                expr: self.condition()?,
+            });
+        }
+
+        let end = self.expect(";")?;
+
+        Ok(Statement {
+            id: id.unwrap_or_else(|| format!("policy{}", index)),
+            effect,
+            principal,
+            action,
+            resource,
+            conditions,
+            span: start.to(end),
+        })
+    }
+
+    fn principal_scope(&mut self) -> Result<PrincipalScope, ParseError> {
+        self.expect_keyword("principal")?;
+
+        if self.eat("==").is_some() {
+            Ok(PrincipalScope::Eq(self.entity_uid()?))
+        } else if self.eat_keyword("in").is_some() {
+            Ok(PrincipalScope::In(self.entity_uid()?))
+        } else if self.eat_keyword("is").is_some() {
+            Ok(PrincipalScope::Is(self.type_name()?))
+        } else {
+            Ok(PrincipalScope::Any)
+        }
+    }
+
+    fn action_scope(&mut self) -> Result<ActionScope, ParseError> {
+        self.expect_keyword("action")?;
+
+        if self.eat("==").is_some() {
+            Ok(ActionScope::Eq(self.action_uid()?))
+        } else if self.eat_keyword("in").is_some() {
+            if self.eat("[").is_none() {
+                return Ok(ActionScope::In(vec![self.action_uid()?]));
+            }
+
+            let mut actions = Vec::new();
+
+            while self.eat("]").is_none() {
+                actions.push(self.action_uid()?);
+
+                if self.eat(",").is_none() {
+                    self.expect("]")?;
+                    break;
+                }
+            }
+
+            Ok(ActionScope::In(actions))
+        } else {
+            Ok(ActionScope::Any)
+        }
+    }
+
+    fn action_uid(&mut self) -> Result<Spanned<EntityUid>, ParseError> {
+        let uid = self.entity_uid()?;
+
+        if uid.value.entity_type != "Action" {
+            return Err(self.error(
+                format!(
+                    "Expected an action such as Action::\"read\" but found {}",
+                    uid.value
+                ),
+                uid.span,
+            ));
+        }
+
+        Ok(uid)
+    }
+
+    fn resource_scope(&mut self) -> Result<ResourceScope, ParseError> {
+        self.expect_keyword("resource")?;
+
+        if self.eat_keyword("is").is_some() {
+            Ok(ResourceScope::Is(self.type_name()?))
+        } else {
+            Ok(ResourceScope::Any)
+        }
+    }
+
+    /// Parses a `{ ... }` condition body as an expression, keeping spans relative to the whole
+    /// source.
+    fn condition(&mut self) -> Result<Expr, ParseError> {
+        let open = self.expect("{")?;
+        let start = open.end;
+        let mut quote = None;
+        let mut escaped = false;
+        let mut end = None;
+
+        for (i, c) in self.rest().char_indices() {
+            match (quote, c) {
+                (Some(_), _) if escaped => escaped = false,
+                (Some(_), '\\') => escaped = true,
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '}') => {
+                    end = Some(start + i);
+                    break;
+                }
+                (None, _) => {}
+            }
+        }
+
+        let Some(end) = end else {
+            return Err(self.error(
+                "Unclosed '{' in condition",
+                Span::new(open.start, self.source.len()),
+            ));
+        };
+
+        self.position = end + 1;
+
+        Expr::parse(&self.source[start..end])
+            .map(|expr| expr.offset(start))
+            .map_err(|mut error| {
+                error.span = error.span.offset(start);
+                error
+            })
+    }
+}
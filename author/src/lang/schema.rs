@@ -0,0 +1,448 @@
+use crate::abac::expr::{CompareOp, Expr, ExprKind, Span};
+use crate::abac::Value;
+use crate::lang::{
+    ActionScope, EntityUid, PolicyDocument, PrincipalScope, ResourceScope, Statement,
+};
+use crate::{Enumerable, Named, NamedResource};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+/// The type of an attribute or expression, for checking conditions before they are evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int,
+    Float,
+    String,
+    List,
+    Map,
+    /// A value whose type isn't known, such as a field of a map, which is accepted anywhere.
+    Any,
+}
+
+impl Type {
+    fn of(value: &Value) -> Type {
+        match value {
+            Value::Null => Type::Any,
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::String(_) => Type::String,
+            Value::List(_) => Type::List,
+            Value::Map(_) => Type::Map,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    fn accepts(self, other: Type) -> bool {
+        self == other
+            || self == Type::Any
+            || other == Type::Any
+            || (self.is_numeric() && other.is_numeric())
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Type::Bool => "bool",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::String => "string",
+            Type::List => "list",
+            Type::Map => "map",
+            Type::Any => "any",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A problem found when checking policies against a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at {span}")]
+pub struct ValidationError {
+    pub message: String,
+    pub span: Span,
+}
+
+type Attributes = BTreeMap<String, Type>;
+
+#[derive(Debug, Clone, Default)]
+struct ResourceType {
+    actions: BTreeSet<String>,
+    attributes: Attributes,
+}
+
+/// The resource types, actions, principal types, roles and attributes that policies may refer to.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    resources: BTreeMap<String, ResourceType>,
+    principals: BTreeMap<String, Attributes>,
+    roles: BTreeSet<EntityUid>,
+    context: Attributes,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a resource type along with its actions and attributes.
+    pub fn resource<Res>(
+        mut self,
+        attributes: impl IntoIterator<Item = (&'static str, Type)>,
+    ) -> Self
+    where
+        Res: NamedResource,
+        Res::Action: Enumerable + Named,
+    {
+        self.resources.insert(
+            Res::RESOURCE_NAME.to_string(),
+            ResourceType {
+                actions: Res::Action::all()
+                    .iter()
+                    .map(|a| a.name().to_string())
+                    .collect(),
+                attributes: attributes
+                    .into_iter()
+                    .map(|(name, ty)| (name.to_string(), ty))
+                    .collect(),
+            },
+        );
+        self
+    }
+
+    /// Registers a principal type along with its attributes.
+    pub fn principal(
+        mut self,
+        entity_type: impl Into<String>,
+        attributes: impl IntoIterator<Item = (&'static str, Type)>,
+    ) -> Self {
+        self.principals.insert(
+            entity_type.into(),
+            attributes
+                .into_iter()
+                .map(|(name, ty)| (name.to_string(), ty))
+                .collect(),
+        );
+        self
+    }
+
+    /// Registers an entity that principals can be in, such as `Role::"admin"`.
+    pub fn role(mut self, role: EntityUid) -> Self {
+        self.roles.insert(role);
+        self
+    }
+
+    /// Registers every value of a role type as an entity of the given type.
+    pub fn roles<Role>(mut self, entity_type: &str) -> Self
+    where
+        Role: Enumerable + Named,
+    {
+        self.roles.extend(
+            Role::all()
+                .iter()
+                .map(|r| EntityUid::new(entity_type, r.name())),
+        );
+        self
+    }
+
+    /// Registers the attributes of the `context`.
+    pub fn context(mut self, attributes: impl IntoIterator<Item = (&'static str, Type)>) -> Self {
+        self.context.extend(
+            attributes
+                .into_iter()
+                .map(|(name, ty)| (name.to_string(), ty)),
+        );
+        self
+    }
+
+    /// Checks that the policies only refer to known types, actions, roles and attributes, and that
+    /// their conditions are well typed, returning every problem found.
+    pub fn validate(&self, policies: &PolicyDocument) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        for statement in policies.statements() {
+            Checker {
+                schema: self,
+                policies,
+                errors: &mut errors,
+            }
+            .statement(statement);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+struct Checker<'a> {
+    schema: &'a Schema,
+    policies: &'a PolicyDocument,
+    errors: &'a mut Vec<ValidationError>,
+}
+
+/// The principal and resource types a statement may apply to.
+struct Scope<'a> {
+    principals: Vec<(&'a str, &'a Attributes)>,
+    resources: Vec<(&'a str, &'a ResourceType)>,
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, message: impl Into<String>, span: Span) {
+        self.errors.push(ValidationError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        let schema = self.schema;
+
+        let principal_type = match &statement.principal {
+            PrincipalScope::Any => None,
+            PrincipalScope::Eq(uid) => Some((uid.value.entity_type.as_str(), uid.span)),
+            PrincipalScope::Is(name) => Some((name.value.as_str(), name.span)),
+            PrincipalScope::In(uid) => {
+                if !schema.roles.contains(&uid.value) {
+                    self.error(format!("unknown role {}", uid.value), uid.span);
+                }
+
+                None
+            }
+        };
+
+        let principals = match principal_type {
+            None => schema
+                .principals
+                .iter()
+                .map(|(name, attributes)| (name.as_str(), attributes))
+                .collect(),
+            Some((name, span)) => match schema.principals.get_key_value(name) {
+                Some((name, attributes)) => vec![(name.as_str(), attributes)],
+                None => {
+                    self.error(format!("unknown principal type '{}'", name), span);
+                    Vec::new()
+                }
+            },
+        };
+
+        let resources: Vec<_> = match &statement.resource {
+            ResourceScope::Any => schema
+                .resources
+                .iter()
+                .map(|(name, resource)| (name.as_str(), resource))
+                .collect(),
+            ResourceScope::Is(name) => match schema.resources.get_key_value(&name.value) {
+                Some((name, resource)) => vec![(name.as_str(), resource)],
+                None => {
+                    self.error(format!("unknown resource type '{}'", name.value), name.span);
+                    Vec::new()
+                }
+            },
+        };
+
+        let actions = match &statement.action {
+            ActionScope::Any => Vec::new(),
+            ActionScope::Eq(uid) => vec![uid],
+            ActionScope::In(uids) => uids.iter().collect(),
+        };
+
+        for action in actions {
+            let name = &action.value.id;
+            let applies = |resource: &(&str, &ResourceType)| resource.1.actions.contains(name);
+
+            match &statement.resource {
+                ResourceScope::Is(_) if !resources.is_empty() && !resources.iter().any(applies) => {
+                    self.error(
+                        format!(
+                            "unknown action '{}' for resource type '{}'",
+                            name, resources[0].0
+                        ),
+                        action.span,
+                    );
+                }
+                ResourceScope::Any
+                    if !schema.resources.values().any(|r| r.actions.contains(name)) =>
+                {
+                    self.error(format!("unknown action '{}'", name), action.span);
+                }
+                _ => {}
+            }
+        }
+
+        let scope = Scope {
+            principals,
+            resources,
+        };
+
+        for condition in &statement.conditions {
+            let ty = self.expr(&condition.expr, &scope);
+            self.expect_bool(&condition.expr, ty);
+        }
+    }
+
+    fn expect_bool(&mut self, expr: &Expr, ty: Type) {
+        if !Type::Bool.accepts(ty) {
+            self.error(format!("expected bool but '{}' is {}", expr, ty), expr.span);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, scope: &Scope) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(value) => Type::of(value),
+            ExprKind::Path(path) => self.path(path, expr.span, scope),
+            ExprKind::List(items) => {
+                for item in items {
+                    self.expr(item, scope);
+                }
+
+                Type::List
+            }
+            ExprKind::Call(name, args) => {
+                for arg in args {
+                    self.expr(arg, scope);
+                }
+
+                if !self.policies.functions().contains(name) {
+                    self.error(format!("unknown function '{}'", name), expr.span);
+                }
+
+                match name.as_str() {
+                    "contains" | "starts_with" | "ends_with" => Type::Bool,
+                    "len" => Type::Int,
+                    "lower" | "upper" => Type::String,
+                    _ => Type::Any,
+                }
+            }
+            ExprKind::Not(inner) => {
+                let ty = self.expr(inner, scope);
+                self.expect_bool(inner, ty);
+
+                Type::Bool
+            }
+            ExprKind::And(left, right) | ExprKind::Or(left, right) => {
+                for operand in [left, right] {
+                    let ty = self.expr(operand, scope);
+                    self.expect_bool(operand, ty);
+                }
+
+                Type::Bool
+            }
+            ExprKind::Compare(left, op, right) => {
+                let left_type = self.expr(left, scope);
+                let right_type = self.expr(right, scope);
+
+                let valid = match op {
+                    CompareOp::Eq | CompareOp::Ne => left_type.accepts(right_type),
+                    CompareOp::Lt | CompareOp::Le | CompareOp::Gt | CompareOp::Ge => {
+                        left_type.accepts(right_type)
+                            && [Type::Int, Type::Float, Type::String, Type::Any]
+                                .contains(&left_type)
+                    }
+                    CompareOp::In => {
+                        [Type::List, Type::Map, Type::String, Type::Any].contains(&right_type)
+                    }
+                };
+
+                if !valid {
+                    let message = match op {
+                        CompareOp::In => {
+                            format!("can't check whether {} is in {}", left_type, right_type)
+                        }
+                        _ => format!("can't compare {} with {}", left_type, right_type),
+                    };
+
+                    self.error(message, expr.span);
+                }
+
+                Type::Bool
+            }
+        }
+    }
+
+    fn path(&mut self, path: &[String], span: Span, scope: &Scope) -> Type {
+        let [root, name, fields @ ..] = path else {
+            self.error(
+                format!(
+                    "unknown attribute '{}', expected principal, resource or context",
+                    path.join(".")
+                ),
+                span,
+            );
+            return Type::Any;
+        };
+
+        let types: Vec<_> = match root.as_str() {
+            "principal" => scope
+                .principals
+                .iter()
+                .map(|(entity_type, attributes)| ("principal", *entity_type, *attributes))
+                .collect(),
+            "resource" => scope
+                .resources
+                .iter()
+                .map(|(entity_type, resource)| ("resource", *entity_type, &resource.attributes))
+                .collect(),
+            "context" => {
+                let Some(ty) = self.schema.context.get(name) else {
+                    self.error(format!("unknown context attribute '{}'", name), span);
+                    return Type::Any;
+                };
+
+                return field_type(*ty, fields);
+            }
+            _ => {
+                self.error(
+                    format!(
+                        "unknown attribute '{}', expected principal, resource or context",
+                        path.join(".")
+                    ),
+                    span,
+                );
+                return Type::Any;
+            }
+        };
+
+        // The attribute must exist on every type the policy may apply to
+        let mut found = None;
+
+        for (kind, entity_type, attributes) in types {
+            match attributes.get(name) {
+                Some(ty) => {
+                    found.get_or_insert(*ty);
+                }
+                None => {
+                    self.error(
+                        format!(
+                            "unknown attribute '{}' on {} type '{}'",
+                            name, kind, entity_type
+                        ),
+                        span,
+                    );
+                    return Type::Any;
+                }
+            }
+        }
+
+        found.map_or(Type::Any, |ty| field_type(ty, fields))
+    }
+}
+
+/// Fields of a map are unchecked, so only a path that ends at the attribute itself has a known
+/// type.
+fn field_type(ty: Type, fields: &[String]) -> Type {
+    if fields.is_empty() {
+        ty
+    } else {
+        Type::Any
+    }
+}
@@ -1,18 +1,161 @@
+// Lets code generated by author-derive refer to `::author` from within this crate.
+extern crate self as author;
+
 use std::hash::Hash;
+use std::sync::Arc;
 use thiserror::Error;
 
+pub mod abac;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod audit;
+pub mod cache;
+pub mod casbin;
+pub mod combinator;
+mod decision;
+pub mod delegation;
+pub mod field;
+pub mod lang;
+pub mod owner;
+pub mod query;
 pub mod rbac;
+pub mod rebac;
+pub mod store;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncPolicy;
+#[cfg(feature = "derive")]
+pub use author_derive::{Enumerable, Named, Resource, Subject};
+pub use decision::{Decision, Effect};
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("Forbidden")]
-    Forbidden,
+    #[error("Forbidden: {0}")]
+    Forbidden(Decision),
 }
 
+/// A resource that can only be reached by authorising access to it. Repositories can hand these
+/// out instead of bare resources so that forgetting the policy check is a compile error rather than
+/// a security hole.
 pub struct ProtectedResource<R> {
     resource: R,
 }
 
+impl<R> ProtectedResource<R>
+where
+    R: Resource,
+{
+    pub fn new(resource: R) -> Self {
+        ProtectedResource { resource }
+    }
+
+    /// Evaluates the policy against the resource without giving access to it.
+    pub fn decide<Subj, P>(&self, policy: &P, subject: &Subj, action: &R::Action) -> Decision
+    where
+        Subj: Subject,
+        P: Policy<R, Subj> + ?Sized,
+    {
+        policy.decide(&self.resource, subject, action)
+    }
+
+    /// Returns the resource if the policy permits the subject to perform the action on it.
+    pub fn authorise<Subj, P>(
+        self,
+        policy: &P,
+        subject: &Subj,
+        action: &R::Action,
+    ) -> Result<R, Error>
+    where
+        Subj: Subject,
+        P: Policy<R, Subj> + ?Sized,
+    {
+        policy.authorise(&self.resource, subject, action)?;
+
+        Ok(self.resource)
+    }
+
+    /// Like [`authorise`](Self::authorise), but borrows the resource so it can be authorised
+    /// again for other actions.
+    pub fn authorise_ref<Subj, P>(
+        &self,
+        policy: &P,
+        subject: &Subj,
+        action: &R::Action,
+    ) -> Result<&R, Error>
+    where
+        Subj: Subject,
+        P: Policy<R, Subj> + ?Sized,
+    {
+        policy.authorise(&self.resource, subject, action)?;
+
+        Ok(&self.resource)
+    }
+
+    /// Authorises the action and applies `f` to the resource, for example to pick out just the
+    /// fields a handler needs.
+    pub fn authorise_map<Subj, P, T, F>(
+        self,
+        policy: &P,
+        subject: &Subj,
+        action: &R::Action,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        Subj: Subject,
+        P: Policy<R, Subj> + ?Sized,
+        F: FnOnce(R) -> T,
+    {
+        self.authorise(policy, subject, action).map(f)
+    }
+
+    /// Returns the resource if the policy permits the action, or `None` otherwise. Useful for
+    /// filtering a list of resources down to those the subject may see:
+    ///
+    /// ```ignore
+    /// let visible: Vec<_> = documents
+    ///     .into_iter()
+    ///     .filter_map(|d| d.filter(&policy, &user, &DocumentAction::Read))
+    ///     .collect();
+    /// ```
+    pub fn filter<Subj, P>(self, policy: &P, subject: &Subj, action: &R::Action) -> Option<R>
+    where
+        Subj: Subject,
+        P: Policy<R, Subj> + ?Sized,
+    {
+        self.authorise(policy, subject, action).ok()
+    }
+
+    /// The asynchronous counterpart of [`authorise`](Self::authorise).
+    #[cfg(feature = "async")]
+    pub async fn authorise_async<Subj, P>(
+        self,
+        policy: &P,
+        subject: &Subj,
+        action: &R::Action,
+    ) -> Result<R, Error>
+    where
+        R: Sync,
+        R::Action: Sync,
+        Subj: Subject + Sync,
+        P: AsyncPolicy<R, Subj> + ?Sized,
+    {
+        policy.authorise(&self.resource, subject, action).await?;
+
+        Ok(self.resource)
+    }
+}
+
+impl<R> From<R> for ProtectedResource<R>
+where
+    R: Resource,
+{
+    fn from(resource: R) -> Self {
+        ProtectedResource::new(resource)
+    }
+}
+
 // pub trait Object {
 //     type Identifier: Hash + Eq;
 //
@@ -27,12 +170,320 @@ pub trait Resource {
 
 pub trait Subject {}
 
+/// Gives a value, such as an action or role, a stable name so it can be referred to from outside
+/// Rust code, for example in configuration files.
+pub trait Named {
+    fn name(&self) -> &str;
+}
+
+/// A resource type that can be referred to by name from outside Rust code.
+pub trait NamedResource: Resource {
+    const RESOURCE_NAME: &'static str;
+}
+
+/// A type with a fixed set of values, typically a fieldless enum of actions or roles, that can
+/// all be listed.
+pub trait Enumerable: Sized {
+    fn all() -> Vec<Self>;
+}
+
 pub trait Policy<Res, Subj>
 where
     Res: Resource,
     Subj: Subject,
 {
+    /// Evaluates the policy, returning a [`Decision`] that explains the outcome.
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision;
+
+    /// Evaluates the policy, treating anything other than an explicit permit as forbidden.
     fn authorise(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Result<(), Error> {
-        Err(Error::Forbidden)
+        self.decide(resource, subject, action).into_result()
+    }
+
+    /// Prepares to make decisions for a single subject, so that work that only depends on the
+    /// subject, such as working out which roles they hold, is done once rather than for every
+    /// resource. Policies with such work should override this; by default it just calls
+    /// [`decide`](Self::decide).
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        Box::new(move |resource, action| self.decide(resource, subject, action))
+    }
+
+    /// Evaluates the policy for many resource and action pairs on behalf of one subject.
+    fn decide_many<'r, I>(&self, subject: &Subj, requests: I) -> Vec<Decision>
+    where
+        Self: Sized,
+        Res: 'r,
+        I: IntoIterator<Item = (&'r Res, &'r Res::Action)>,
+    {
+        let decide = self.for_subject(subject);
+
+        requests
+            .into_iter()
+            .map(|(resource, action)| decide(resource, action))
+            .collect()
+    }
+
+    /// Authorises many resource and action pairs on behalf of one subject, returning a result for
+    /// each in the same order.
+    fn authorise_many<'r, I>(&self, subject: &Subj, requests: I) -> Vec<Result<(), Error>>
+    where
+        Self: Sized,
+        Res: 'r,
+        I: IntoIterator<Item = (&'r Res, &'r Res::Action)>,
+    {
+        self.decide_many(subject, requests)
+            .into_iter()
+            .map(Decision::into_result)
+            .collect()
+    }
+
+    /// Decides every action the subject could perform on the resource, explaining each decision,
+    /// for example to work out which buttons to show in a UI.
+    fn explain_actions(&self, resource: &Res, subject: &Subj) -> Vec<(Res::Action, Decision)>
+    where
+        Res::Action: Enumerable,
+    {
+        let decide = self.for_subject(subject);
+
+        Res::Action::all()
+            .into_iter()
+            .map(|action| {
+                let decision = decide(resource, &action);
+                (action, decision)
+            })
+            .collect()
+    }
+
+    /// Lists the actions the subject is permitted to perform on the resource.
+    fn permitted_actions(&self, resource: &Res, subject: &Subj) -> Vec<Res::Action>
+    where
+        Res::Action: Enumerable,
+    {
+        self.explain_actions(resource, subject)
+            .into_iter()
+            .filter(|(_, decision)| decision.is_permit())
+            .map(|(action, _)| action)
+            .collect()
+    }
+}
+
+/// Makes decisions for a particular subject, as returned by [`Policy::for_subject`].
+pub type SubjectDecider<'a, Res> = Box<dyn Fn(&Res, &<Res as Resource>::Action) -> Decision + 'a>;
+
+/// Adds [`filter_authorised`](Self::filter_authorised) to iterators over references to
+/// resources.
+pub trait AuthorisedIterator: Iterator + Sized {
+    /// Filters the iterator down to the resources the subject may perform the action on,
+    /// preparing the policy for the subject only once.
+    fn filter_authorised<'a, 'r, Res, Subj, P>(
+        self,
+        policy: &'a P,
+        subject: &'a Subj,
+        action: &'a Res::Action,
+    ) -> FilterAuthorised<'a, Self, Res>
+    where
+        Self: Iterator<Item = &'r Res>,
+        Res: Resource + 'a + 'r,
+        Subj: Subject,
+        P: Policy<Res, Subj> + ?Sized,
+    {
+        FilterAuthorised {
+            inner: self,
+            decide: policy.for_subject(subject),
+            action,
+        }
+    }
+}
+
+impl<I> AuthorisedIterator for I where I: Iterator {}
+
+/// Iterator returned by [`AuthorisedIterator::filter_authorised`].
+pub struct FilterAuthorised<'a, I, Res>
+where
+    Res: Resource,
+{
+    inner: I,
+    decide: SubjectDecider<'a, Res>,
+    action: &'a Res::Action,
+}
+
+impl<'r, I, Res> Iterator for FilterAuthorised<'_, I, Res>
+where
+    I: Iterator<Item = &'r Res>,
+    Res: Resource + 'r,
+{
+    type Item = &'r Res;
+
+    fn next(&mut self) -> Option<&'r Res> {
+        self.inner
+            .by_ref()
+            .find(|resource| (self.decide)(resource, self.action).is_permit())
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for &P
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj> + ?Sized,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        (**self).for_subject(subject)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for Box<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj> + ?Sized,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        (**self).for_subject(subject)
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for Arc<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj> + ?Sized,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        (**self).decide(resource, subject, action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        (**self).for_subject(subject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Document {
+        public: bool,
+        title: &'static str,
+    }
+
+    impl Resource for Document {
+        type Action = DocumentAction;
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum DocumentAction {
+        Read,
+        Delete,
+    }
+
+    struct User;
+
+    impl Subject for User {}
+
+    struct PublicReadPolicy;
+
+    impl Policy<Document, User> for PublicReadPolicy {
+        fn decide(&self, document: &Document, _user: &User, action: &DocumentAction) -> Decision {
+            match action {
+                DocumentAction::Read if document.public => Decision::permit("Document is public"),
+                _ => Decision::deny("Only public documents can be read"),
+            }
+        }
+    }
+
+    fn documents() -> Vec<ProtectedResource<Document>> {
+        vec![
+            ProtectedResource::new(Document {
+                public: true,
+                title: "Public",
+            }),
+            ProtectedResource::new(Document {
+                public: false,
+                title: "Private",
+            }),
+        ]
+    }
+
+    #[test]
+    fn only_yield_authorised_resources() {
+        let mut protected = documents().into_iter();
+        let public = protected.next().unwrap();
+        let private = protected.next().unwrap();
+
+        assert!(public
+            .authorise_ref(&PublicReadPolicy, &User, &DocumentAction::Delete)
+            .is_err());
+        assert_eq!(
+            public
+                .authorise_map(&PublicReadPolicy, &User, &DocumentAction::Read, |d| d.title)
+                .unwrap(),
+            "Public"
+        );
+
+        let Err(Error::Forbidden(decision)) =
+            private.authorise(&PublicReadPolicy, &User, &DocumentAction::Read)
+        else {
+            panic!("expected private document to be forbidden");
+        };
+        assert_eq!(decision.reason(), "Only public documents can be read");
+
+        let visible: Vec<_> = documents()
+            .into_iter()
+            .filter_map(|d| d.filter(&PublicReadPolicy, &User, &DocumentAction::Read))
+            .map(|d| d.title)
+            .collect();
+        assert_eq!(visible, vec!["Public"]);
+    }
+
+    #[test]
+    fn batches() {
+        let documents = [
+            Document {
+                public: true,
+                title: "Public",
+            },
+            Document {
+                public: false,
+                title: "Private",
+            },
+        ];
+
+        let results = PublicReadPolicy.authorise_many(
+            &User,
+            [
+                (&documents[0], &DocumentAction::Read),
+                (&documents[0], &DocumentAction::Delete),
+                (&documents[1], &DocumentAction::Read),
+            ],
+        );
+        assert!(matches!(results.as_slice(), [Ok(()), Err(_), Err(_)]));
+
+        let visible: Vec<_> = documents
+            .iter()
+            .filter_authorised(&PublicReadPolicy, &User, &DocumentAction::Read)
+            .map(|d| d.title)
+            .collect();
+        assert_eq!(visible, vec!["Public"]);
     }
 }
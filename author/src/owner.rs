@@ -0,0 +1,280 @@
+//! Access for the owners of resources, such as letting the user who created a document edit it,
+//! without having to model ownership as a resource role.
+//!
+//! An [`OwnerPolicy`] is not applicable to anyone other than the owner, so it is typically
+//! combined with other policies:
+//!
+//! ```ignore
+//! let policy = AnyOf::new((
+//!     OwnerPolicy::new([DocumentAction::Read, DocumentAction::Write]),
+//!     GlobalRbacPolicy::new(),
+//! ));
+//! ```
+//!
+//! Resources that store their owner in a column of their table can implement [`QueryOwned`] so
+//! that the policy can be translated into a query, selecting the rows where the column holds the
+//! subject's identifier.
+
+use crate::abac::Value;
+use crate::query::{Predicate, QueryError, QueryPolicy, QueryResource, Residual};
+use crate::{Decision, Policy, Resource, Subject, SubjectDecider};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// A subject with an identifier that resources can record as their owner.
+pub trait IdentifiedSubject: Subject {
+    type Id: Eq;
+
+    fn subject_id(&self) -> Self::Id;
+}
+
+/// A resource that may be owned by a subject.
+pub trait Owned<Id>: Resource {
+    /// The identifier of the owner, if the resource has one.
+    fn owner_id(&self) -> Option<Id>;
+}
+
+/// An owned resource whose owner's identifier is stored in a column of its table.
+pub trait QueryOwned<Id>: Owned<Id> + QueryResource {
+    fn owner_column() -> String;
+}
+
+/// Policy that permits the owner of a resource to perform a configured set of actions on it, and
+/// is not applicable to anything else.
+pub struct OwnerPolicy<Action> {
+    // `None` if owners may perform every action
+    actions: Option<HashSet<Action>>,
+}
+
+impl<Action> OwnerPolicy<Action>
+where
+    Action: Hash + Eq,
+{
+    /// Creates a policy permitting owners to perform the given actions.
+    pub fn new(actions: impl IntoIterator<Item = Action>) -> Self {
+        OwnerPolicy {
+            actions: Some(actions.into_iter().collect()),
+        }
+    }
+
+    /// Creates a policy permitting owners to perform any action.
+    pub fn all_actions() -> Self {
+        OwnerPolicy { actions: None }
+    }
+
+    fn owners_may(&self, action: &Action) -> bool {
+        self.actions
+            .as_ref()
+            .is_none_or(|actions| actions.contains(action))
+    }
+
+    fn decide_for_owner<Id>(&self, owner: Option<Id>, subject_id: &Id, action: &Action) -> Decision
+    where
+        Id: Eq,
+    {
+        if !self.owners_may(action) {
+            return Decision::not_applicable("Owners are not allowed to perform this action");
+        }
+
+        match owner {
+            Some(owner) if owner == *subject_id => {
+                Decision::permit("Subject owns the resource").with_rule("owner")
+            }
+            Some(_) => Decision::not_applicable("Subject does not own the resource"),
+            None => Decision::not_applicable("Resource has no owner"),
+        }
+    }
+}
+
+impl<Res, Subj> Policy<Res, Subj> for OwnerPolicy<Res::Action>
+where
+    Subj: IdentifiedSubject,
+    Res: Owned<Subj::Id>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        self.decide_for_owner(resource.owner_id(), &subject.subject_id(), action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let subject_id = subject.subject_id();
+
+        Box::new(move |resource, action| {
+            self.decide_for_owner(resource.owner_id(), &subject_id, action)
+        })
+    }
+}
+
+impl<Res, Subj> QueryPolicy<Res, Subj> for OwnerPolicy<Res::Action>
+where
+    Subj: IdentifiedSubject<Id: Into<Value>>,
+    Res: QueryOwned<Subj::Id>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        if !self.owners_may(action) {
+            return Ok(Residual::not_applicable());
+        }
+
+        Ok(Residual::new(
+            Predicate::eq(Res::owner_column(), subject.subject_id()),
+            Predicate::False,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinator::AnyOf;
+    use crate::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+
+    struct Document {
+        created_by: Option<u32>,
+    }
+
+    impl Resource for Document {
+        type Action = DocumentAction;
+    }
+
+    impl Owned<u32> for Document {
+        fn owner_id(&self) -> Option<u32> {
+            self.created_by
+        }
+    }
+
+    impl QueryResource for Document {}
+
+    impl QueryOwned<u32> for Document {
+        fn owner_column() -> String {
+            "created_by".to_string()
+        }
+    }
+
+    impl RbacResource<Role> for Document {
+        fn allowed_roles(&self, _action: &DocumentAction) -> HashSet<Role> {
+            HashSet::from([Role::Admin])
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    enum DocumentAction {
+        Edit,
+        Delete,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    enum Role {
+        Admin,
+    }
+
+    struct User {
+        id: u32,
+        admin: bool,
+    }
+
+    impl Subject for User {}
+
+    impl IdentifiedSubject for User {
+        type Id = u32;
+
+        fn subject_id(&self) -> u32 {
+            self.id
+        }
+    }
+
+    impl GlobalRbacSubject for User {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            if self.admin {
+                HashSet::from([Role::Admin])
+            } else {
+                HashSet::new()
+            }
+        }
+    }
+
+    #[test]
+    fn permit_configured_actions_for_owners() {
+        let policy = OwnerPolicy::new([DocumentAction::Edit]);
+        let document = Document {
+            created_by: Some(1),
+        };
+        let (owner, other) = (
+            User {
+                id: 1,
+                admin: false,
+            },
+            User {
+                id: 2,
+                admin: false,
+            },
+        );
+
+        let decision = policy.decide(&document, &owner, &DocumentAction::Edit);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("owner"));
+
+        assert!(policy
+            .decide(&document, &owner, &DocumentAction::Delete)
+            .is_not_applicable());
+        assert!(policy
+            .decide(&document, &other, &DocumentAction::Edit)
+            .is_not_applicable());
+        assert!(policy
+            .decide(
+                &Document { created_by: None },
+                &owner,
+                &DocumentAction::Edit
+            )
+            .is_not_applicable());
+    }
+
+    #[test]
+    fn combine_with_global_roles() {
+        let policy = AnyOf::new((OwnerPolicy::all_actions(), GlobalRbacPolicy::new()));
+        let document = Document {
+            created_by: Some(1),
+        };
+        let owner = User {
+            id: 1,
+            admin: false,
+        };
+        let admin = User { id: 2, admin: true };
+        let other = User {
+            id: 3,
+            admin: false,
+        };
+
+        assert!(policy
+            .decide(&document, &owner, &DocumentAction::Delete)
+            .is_permit());
+        assert!(policy
+            .decide(&document, &admin, &DocumentAction::Delete)
+            .is_permit());
+        assert!(policy
+            .decide(&document, &other, &DocumentAction::Delete)
+            .is_deny());
+    }
+
+    #[test]
+    fn query_owned_resources() {
+        let policy = OwnerPolicy::new([DocumentAction::Edit]);
+        let owner = User {
+            id: 1,
+            admin: false,
+        };
+
+        let residual =
+            QueryPolicy::<Document, _>::residual(&policy, &owner, &DocumentAction::Edit).unwrap();
+        assert_eq!(residual.permit.to_string(), "created_by = 1");
+        assert_eq!(residual.deny, Predicate::False);
+
+        assert_eq!(
+            QueryPolicy::<Document, _>::residual(&policy, &owner, &DocumentAction::Delete).unwrap(),
+            Residual::not_applicable()
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! Translating policies into database query predicates.
+//!
+//! Rather than loading every row and then filtering out the ones a subject may not see, a policy
+//! can be partially evaluated for a subject and action, leaving a [`Predicate`] over the columns
+//! of the resource's table, such as `owner_id = 7 OR team_id IN (1, 2)`. The predicate can then be
+//! rendered into the `WHERE` clause of a query, for example with sea-query when the `sea-query`
+//! feature is enabled.
+
+use crate::abac::expr::EvalError;
+use crate::abac::Value;
+use crate::combinator::{AllOf, AnyOf, DenyOverrides, FirstApplicable, Not, PermitOverrides};
+use crate::{Decision, Effect, Resource, Subject};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+#[cfg(feature = "sea-query")]
+mod sea_query;
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("Can't translate policy into a query: {0}")]
+    Unsupported(String),
+    #[error("No column for resource attribute '{0}'")]
+    UnknownColumn(String),
+    #[error(transparent)]
+    Eval(#[from] EvalError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    /// The operator to use when the operands are swapped, so `5 < x` becomes `x > 5`.
+    pub fn flip(self) -> Self {
+        match self {
+            Operator::Eq => Operator::Eq,
+            Operator::Ne => Operator::Ne,
+            Operator::Lt => Operator::Gt,
+            Operator::Le => Operator::Ge,
+            Operator::Gt => Operator::Lt,
+            Operator::Ge => Operator::Le,
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Operator::Eq => "=",
+            Operator::Ne => "<>",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+/// A backend-neutral condition on the columns of a resource's table. A comparison doesn't hold
+/// where the column is null, so its negation does, unlike SQL's `NOT` on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    True,
+    False,
+    /// Compares a column with a value. Comparing with [`Value::Null`] checks whether the column
+    /// is or isn't null.
+    Compare(String, Operator, Value),
+    In(String, Vec<Value>),
+    Not(Box<Predicate>),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+}
+
+impl Predicate {
+    pub fn compare(column: impl Into<String>, op: Operator, value: impl Into<Value>) -> Self {
+        Predicate::Compare(column.into(), op, value.into())
+    }
+
+    pub fn eq(column: impl Into<String>, value: impl Into<Value>) -> Self {
+        Predicate::compare(column, Operator::Eq, value)
+    }
+
+    pub fn is_in<I, V>(column: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        let values: Vec<_> = values.into_iter().map(Into::into).collect();
+
+        if values.is_empty() {
+            Predicate::False
+        } else {
+            Predicate::In(column.into(), values)
+        }
+    }
+
+    /// Combines predicates with `AND`, simplifying away constant predicates.
+    pub fn all(predicates: impl IntoIterator<Item = Predicate>) -> Self {
+        let mut all = Vec::new();
+
+        for predicate in predicates {
+            match predicate {
+                Predicate::True => {}
+                Predicate::False => return Predicate::False,
+                Predicate::And(inner) => all.extend(inner),
+                predicate => all.push(predicate),
+            }
+        }
+
+        match all.len() {
+            0 => Predicate::True,
+            1 => all.remove(0),
+            _ => Predicate::And(all),
+        }
+    }
+
+    /// Combines predicates with `OR`, simplifying away constant predicates.
+    pub fn any(predicates: impl IntoIterator<Item = Predicate>) -> Self {
+        let mut any = Vec::new();
+
+        for predicate in predicates {
+            match predicate {
+                Predicate::False => {}
+                Predicate::True => return Predicate::True,
+                Predicate::Or(inner) => any.extend(inner),
+                predicate => any.push(predicate),
+            }
+        }
+
+        match any.len() {
+            0 => Predicate::False,
+            1 => any.remove(0),
+            _ => Predicate::Or(any),
+        }
+    }
+
+    pub fn and(self, other: Predicate) -> Self {
+        Predicate::all([self, other])
+    }
+
+    pub fn or(self, other: Predicate) -> Self {
+        Predicate::any([self, other])
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        match self {
+            Predicate::True => Predicate::False,
+            Predicate::False => Predicate::True,
+            Predicate::Not(inner) => *inner,
+            predicate => Predicate::Not(Box::new(predicate)),
+        }
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn fmt_value(value: &Value, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match value {
+                Value::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+                Value::Null => write!(f, "NULL"),
+                Value::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+                value => write!(f, "{}", value),
+            }
+        }
+
+        fn fmt_operand(predicate: &Predicate, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match predicate {
+                Predicate::And(_) | Predicate::Or(_) => write!(f, "({})", predicate),
+                predicate => write!(f, "{}", predicate),
+            }
+        }
+
+        fn fmt_joined(
+            predicates: &[Predicate],
+            separator: &str,
+            f: &mut Formatter<'_>,
+        ) -> std::fmt::Result {
+            for (i, predicate) in predicates.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", separator)?;
+                }
+                fmt_operand(predicate, f)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Predicate::True => write!(f, "TRUE"),
+            Predicate::False => write!(f, "FALSE"),
+            Predicate::Compare(column, Operator::Eq, Value::Null) => {
+                write!(f, "{} IS NULL", column)
+            }
+            Predicate::Compare(column, Operator::Ne, Value::Null) => {
+                write!(f, "{} IS NOT NULL", column)
+            }
+            Predicate::Compare(column, op, value) => {
+                write!(f, "{} {} ", column, op)?;
+                fmt_value(value, f)
+            }
+            Predicate::In(column, values) => {
+                write!(f, "{} IN (", column)?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_value(value, f)?;
+                }
+                write!(f, ")")
+            }
+            Predicate::Not(inner) => {
+                write!(f, "NOT ")?;
+                match **inner {
+                    Predicate::Compare(..) | Predicate::In(..) => write!(f, "({})", inner),
+                    _ => fmt_operand(inner, f),
+                }
+            }
+            Predicate::And(predicates) => fmt_joined(predicates, "AND", f),
+            Predicate::Or(predicates) => fmt_joined(predicates, "OR", f),
+        }
+    }
+}
+
+/// The result of partially evaluating a policy: the conditions under which it would permit and
+/// deny. Where neither holds the policy is not applicable. The two are never both true.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Residual {
+    pub permit: Predicate,
+    pub deny: Predicate,
+}
+
+impl Residual {
+    pub fn new(permit: Predicate, deny: Predicate) -> Self {
+        Residual { permit, deny }
+    }
+
+    /// A residual for a policy that never applies.
+    pub fn not_applicable() -> Self {
+        Residual::new(Predicate::False, Predicate::False)
+    }
+
+    /// A residual that permits wherever `permit` holds and denies everywhere else.
+    pub fn permit_or_deny(permit: Predicate) -> Self {
+        let deny = permit.clone().not();
+
+        Residual::new(permit, deny)
+    }
+
+    /// A residual for a decision that doesn't depend on the resource, such as one made only on
+    /// the subject's global roles, which has the same effect on every resource.
+    pub fn from_decision(decision: &Decision) -> Self {
+        match decision.effect() {
+            Effect::Permit => Residual::new(Predicate::True, Predicate::False),
+            Effect::Deny => Residual::new(Predicate::False, Predicate::True),
+            Effect::NotApplicable => Residual::not_applicable(),
+        }
+    }
+
+    /// The condition under which the policy has an opinion either way.
+    pub fn applicable(&self) -> Predicate {
+        self.permit.clone().or(self.deny.clone())
+    }
+}
+
+/// Maps resource attributes, as used in policies, to columns in the resource's table. By default
+/// each top-level attribute is stored in a column of the same name.
+pub trait QueryResource: Resource {
+    fn column(attribute: &str) -> Option<String> {
+        (!attribute.contains('.')).then(|| attribute.to_string())
+    }
+}
+
+/// A policy that can be translated into a query predicate selecting every resource the subject
+/// may perform the action on.
+pub trait QueryPolicy<Res, Subj>
+where
+    Res: QueryResource,
+    Subj: Subject,
+{
+    /// Partially evaluates the policy for the subject and action, leaving conditions on the
+    /// resource.
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError>;
+
+    /// Returns a predicate that holds for exactly the resources the policy permits the subject to
+    /// perform the action on.
+    fn query(&self, subject: &Subj, action: &Res::Action) -> Result<Predicate, QueryError> {
+        Ok(self.residual(subject, action)?.permit)
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for &P
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicy<Res, Subj> + ?Sized,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        (**self).residual(subject, action)
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for Box<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicy<Res, Subj> + ?Sized,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        (**self).residual(subject, action)
+    }
+}
+
+/// The query counterpart of [`PolicySet`](crate::combinator::PolicySet), allowing combined
+/// policies to be translated into queries.
+pub trait QueryPolicySet<Res, Subj>
+where
+    Res: QueryResource,
+    Subj: Subject,
+{
+    fn query_policies(&self) -> Vec<&dyn QueryPolicy<Res, Subj>>;
+
+    fn residuals(&self, subject: &Subj, action: &Res::Action) -> Result<Vec<Residual>, QueryError> {
+        self.query_policies()
+            .into_iter()
+            .map(|policy| policy.residual(subject, action))
+            .collect()
+    }
+}
+
+impl<Res, Subj, P> QueryPolicySet<Res, Subj> for Vec<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicy<Res, Subj>,
+{
+    fn query_policies(&self) -> Vec<&dyn QueryPolicy<Res, Subj>> {
+        self.iter()
+            .map(|p| p as &dyn QueryPolicy<Res, Subj>)
+            .collect()
+    }
+}
+
+impl<Res, Subj, P, const N: usize> QueryPolicySet<Res, Subj> for [P; N]
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicy<Res, Subj>,
+{
+    fn query_policies(&self) -> Vec<&dyn QueryPolicy<Res, Subj>> {
+        self.iter()
+            .map(|p| p as &dyn QueryPolicy<Res, Subj>)
+            .collect()
+    }
+}
+
+macro_rules! tuple_query_policy_set {
+    ($($name:ident: $idx:tt),+) => {
+        impl<Res, Subj, $($name),+> QueryPolicySet<Res, Subj> for ($($name,)+)
+        where
+            Res: QueryResource,
+            Subj: Subject,
+            $($name: QueryPolicy<Res, Subj>),+
+        {
+            fn query_policies(&self) -> Vec<&dyn QueryPolicy<Res, Subj>> {
+                vec![$(&self.$idx as &dyn QueryPolicy<Res, Subj>),+]
+            }
+        }
+    };
+}
+
+tuple_query_policy_set!(A: 0);
+tuple_query_policy_set!(A: 0, B: 1);
+tuple_query_policy_set!(A: 0, B: 1, C: 2);
+tuple_query_policy_set!(A: 0, B: 1, C: 2, D: 3);
+tuple_query_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple_query_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple_query_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple_query_policy_set!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for AnyOf<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicySet<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residuals = self.0.residuals(subject, action)?;
+
+        Ok(Residual::permit_or_deny(Predicate::any(
+            residuals.into_iter().map(|r| r.permit),
+        )))
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for AllOf<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicySet<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residuals = self.0.residuals(subject, action)?;
+
+        if residuals.is_empty() {
+            return Ok(Residual::permit_or_deny(Predicate::False));
+        }
+
+        Ok(Residual::permit_or_deny(Predicate::all(
+            residuals.into_iter().map(|r| r.permit),
+        )))
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for Not<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicy<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residual = self.0.residual(subject, action)?;
+
+        Ok(Residual::new(residual.deny, residual.permit))
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for FirstApplicable<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicySet<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residuals = self.0.residuals(subject, action)?;
+
+        // Working backwards, each policy decides where it is applicable and defers to the rest
+        // everywhere else
+        Ok(residuals
+            .into_iter()
+            .rev()
+            .fold(Residual::not_applicable(), |rest, residual| {
+                let not_applicable = residual.applicable().not();
+
+                Residual::new(
+                    residual.permit.or(not_applicable.clone().and(rest.permit)),
+                    residual.deny.or(not_applicable.and(rest.deny)),
+                )
+            }))
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for DenyOverrides<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicySet<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residuals = self.0.residuals(subject, action)?;
+
+        let deny = Predicate::any(residuals.iter().map(|r| r.deny.clone()));
+        let permit =
+            Predicate::any(residuals.into_iter().map(|r| r.permit)).and(deny.clone().not());
+
+        Ok(Residual::new(permit, deny))
+    }
+}
+
+impl<Res, Subj, P> QueryPolicy<Res, Subj> for PermitOverrides<P>
+where
+    Res: QueryResource,
+    Subj: Subject,
+    P: QueryPolicySet<Res, Subj>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let residuals = self.0.residuals(subject, action)?;
+
+        let permit = Predicate::any(residuals.iter().map(|r| r.permit.clone()));
+        let deny = Predicate::any(residuals.into_iter().map(|r| r.deny)).and(permit.clone().not());
+
+        Ok(Residual::new(permit, deny))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify() {
+        let owner = Predicate::eq("owner_id", 7);
+
+        assert_eq!(owner.clone().and(Predicate::True), owner);
+        assert_eq!(owner.clone().or(Predicate::True), Predicate::True);
+        assert_eq!(owner.clone().not().not(), owner);
+        assert_eq!(
+            Predicate::is_in("team_id", Vec::<i64>::new()),
+            Predicate::False
+        );
+        assert_eq!(
+            Predicate::all([owner.clone(), owner.clone().and(owner.clone())]),
+            Predicate::And(vec![owner.clone(), owner.clone(), owner])
+        );
+    }
+
+    struct Doc;
+
+    impl Resource for Doc {
+        type Action = ();
+    }
+
+    impl QueryResource for Doc {}
+
+    struct User;
+
+    impl Subject for User {}
+
+    struct Fixed(Residual);
+
+    impl QueryPolicy<Doc, User> for Fixed {
+        fn residual(&self, _: &User, _: &()) -> Result<Residual, QueryError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn combine_residuals() {
+        let owner = Predicate::eq("owner_id", 7);
+        let locked = Predicate::eq("locked", true);
+
+        let owners = Fixed(Residual::new(owner.clone(), Predicate::False));
+        let lock = Fixed(Residual::new(Predicate::False, locked.clone()));
+
+        let residual = DenyOverrides((&owners, &lock))
+            .residual(&User, &())
+            .unwrap();
+        assert_eq!(residual.permit, owner.clone().and(locked.clone().not()));
+        assert_eq!(residual.deny, locked.clone());
+
+        let residual = FirstApplicable((&owners, &lock))
+            .residual(&User, &())
+            .unwrap();
+        assert_eq!(residual.permit, owner.clone());
+        assert_eq!(residual.deny, owner.clone().not().and(locked.clone()));
+
+        let residual = Not(&lock).residual(&User, &()).unwrap();
+        assert_eq!(residual.permit, locked.clone());
+        assert_eq!(residual.deny, Predicate::False);
+
+        let query = AnyOf((&owners, Not(&lock))).query(&User, &()).unwrap();
+        assert_eq!(query, owner.or(locked));
+    }
+
+    #[test]
+    fn display() {
+        let predicate = Predicate::eq("owner_id", 7)
+            .or(Predicate::is_in("team_id", [1, 2]))
+            .and(Predicate::compare("status", Operator::Ne, "it's archived"))
+            .and(Predicate::eq("deleted_at", Value::Null).not());
+
+        assert_eq!(
+            predicate.to_string(),
+            "(owner_id = 7 OR team_id IN (1, 2)) AND status <> 'it''s archived' AND NOT (deleted_at IS NULL)"
+        );
+    }
+}
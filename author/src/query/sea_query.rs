@@ -0,0 +1,233 @@
+use crate::abac::Value;
+use crate::query::{Operator, Predicate};
+use ::sea_query::{Alias, Condition, Expr, IntoCondition, SimpleExpr};
+
+impl Predicate {
+    /// Renders the predicate as a sea-query expression, for use with
+    /// [`and_where`](::sea_query::SelectStatement::and_where) or SeaORM's
+    /// [`filter`](https://docs.rs/sea-orm/latest/sea_orm/query/trait.QueryFilter.html#method.filter).
+    ///
+    /// SQL comparisons with `NULL` are neither true nor false, so `NOT (column = 1)` wouldn't
+    /// select rows where the column is null, whereas the predicate holds for them. Negations are
+    /// pushed down to the comparisons instead, so that it is rendered as
+    /// `(column <> 1 OR column IS NULL)`.
+    pub fn to_sea_query(&self) -> SimpleExpr {
+        render(self, false)
+    }
+}
+
+fn render(predicate: &Predicate, negated: bool) -> SimpleExpr {
+    render_nullable(predicate, negated, &[])
+}
+
+/// Renders a predicate where the columns in `not_null` are already known not to be null, so
+/// negated comparisons on them needn't also check for null.
+fn render_nullable(predicate: &Predicate, negated: bool, not_null: &[&str]) -> SimpleExpr {
+    match predicate {
+        Predicate::True => Expr::value(!negated),
+        Predicate::False => Expr::value(negated),
+        Predicate::Compare(column, op, Value::Null) => {
+            let column = Expr::col(Alias::new(column));
+
+            if (*op == Operator::Ne) != negated {
+                column.is_not_null()
+            } else {
+                column.is_null()
+            }
+        }
+        Predicate::Compare(name, op, value) => {
+            let column = Expr::col(Alias::new(name));
+            let value = sea_query_value(value);
+
+            if negated {
+                let compared = match op {
+                    Operator::Eq => column.clone().ne(value),
+                    Operator::Ne => column.clone().eq(value),
+                    Operator::Lt => column.clone().gte(value),
+                    Operator::Le => column.clone().gt(value),
+                    Operator::Gt => column.clone().lte(value),
+                    Operator::Ge => column.clone().lt(value),
+                };
+
+                or_null(compared, column, name, not_null)
+            } else {
+                match op {
+                    Operator::Eq => column.eq(value),
+                    Operator::Ne => column.ne(value),
+                    Operator::Lt => column.lt(value),
+                    Operator::Le => column.lte(value),
+                    Operator::Gt => column.gt(value),
+                    Operator::Ge => column.gte(value),
+                }
+            }
+        }
+        Predicate::In(name, values) => {
+            let column = Expr::col(Alias::new(name));
+            let values = values.iter().map(sea_query_value);
+
+            if negated {
+                or_null(column.clone().is_not_in(values), column, name, not_null)
+            } else {
+                column.is_in(values)
+            }
+        }
+        Predicate::Not(inner) => render_nullable(inner, !negated, not_null),
+        Predicate::And(predicates) if negated => any(predicates, negated),
+        Predicate::And(predicates) => all(predicates, negated),
+        Predicate::Or(predicates) if negated => all(predicates, negated),
+        Predicate::Or(predicates) => any(predicates, negated),
+    }
+}
+
+fn all(predicates: &[Predicate], negated: bool) -> SimpleExpr {
+    // Columns another part of the conjunction requires not to be null
+    let not_null: Vec<_> = predicates
+        .iter()
+        .filter_map(|p| match p {
+            Predicate::Compare(column, op, Value::Null) if (*op == Operator::Ne) != negated => {
+                Some(column.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+
+    predicates
+        .iter()
+        .fold(Condition::all(), |condition, p| {
+            condition.add(render_nullable(p, negated, &not_null))
+        })
+        .into()
+}
+
+fn any(predicates: &[Predicate], negated: bool) -> SimpleExpr {
+    predicates
+        .iter()
+        .fold(Condition::any(), |condition, p| {
+            condition.add(render(p, negated))
+        })
+        .into()
+}
+
+/// Extends a comparison to also hold where the column is null, unless it is known not to be.
+fn or_null(compared: SimpleExpr, column: Expr, name: &str, not_null: &[&str]) -> SimpleExpr {
+    if not_null.contains(&name) {
+        compared
+    } else {
+        Condition::any().add(compared).add(column.is_null()).into()
+    }
+}
+
+impl IntoCondition for Predicate {
+    fn into_condition(self) -> Condition {
+        self.to_sea_query().into_condition()
+    }
+}
+
+/// Converts a scalar value. Lists and maps are never compared with columns, since partial
+/// evaluation rejects them, but are rendered as JSON-like strings just in case.
+fn sea_query_value(value: &Value) -> ::sea_query::Value {
+    match value {
+        Value::Null => ::sea_query::Value::String(None),
+        Value::Bool(b) => (*b).into(),
+        Value::Int(i) => (*i).into(),
+        Value::Float(x) => (*x).into(),
+        Value::String(s) => s.clone().into(),
+        value => value.to_string().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinator::DenyOverrides;
+    use crate::query::{QueryError, QueryPolicy, QueryResource, Residual};
+    use crate::{Resource, Subject};
+    use ::sea_query::{Asterisk, Query, SqliteQueryBuilder};
+
+    fn render_where(predicate: Predicate) -> String {
+        Query::select()
+            .column(Asterisk)
+            .from(Alias::new("document"))
+            .cond_where(predicate)
+            .to_string(SqliteQueryBuilder)
+    }
+
+    #[test]
+    fn render() {
+        let predicate = Predicate::eq("owner_id", 7)
+            .or(Predicate::is_in("team_id", [1, 2]))
+            .and(Predicate::eq("archived", true).not())
+            .and(Predicate::eq("deleted_at", Value::Null));
+
+        assert_eq!(
+            render_where(predicate),
+            r#"SELECT * FROM "document" WHERE ("owner_id" = 7 OR "team_id" IN (1, 2)) AND ("archived" <> TRUE OR "archived" IS NULL) AND "deleted_at" IS NULL"#
+        );
+    }
+
+    struct Document;
+
+    impl Resource for Document {
+        type Action = ();
+    }
+
+    impl QueryResource for Document {}
+
+    struct User;
+
+    impl Subject for User {}
+
+    struct Fixed(Residual);
+
+    impl QueryPolicy<Document, User> for Fixed {
+        fn residual(&self, _: &User, _: &()) -> Result<Residual, QueryError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn render_negations_including_null_columns() {
+        // Documents whose `locked` column is null aren't locked, so the owner may still see them
+        let owners = Fixed(Residual::new(
+            Predicate::eq("owner_id", 7),
+            Predicate::False,
+        ));
+        let lock = Fixed(Residual::new(
+            Predicate::False,
+            Predicate::eq("locked", true),
+        ));
+
+        let query = DenyOverrides((owners, lock)).query(&User, &()).unwrap();
+
+        assert_eq!(
+            render_where(query),
+            r#"SELECT * FROM "document" WHERE "owner_id" = 7 AND ("locked" <> TRUE OR "locked" IS NULL)"#
+        );
+
+        let predicate = Predicate::compare("size", Operator::Gt, 10)
+            .or(Predicate::is_in("team_id", [1, 2]))
+            .not();
+
+        assert_eq!(
+            render_where(predicate),
+            r#"SELECT * FROM "document" WHERE ("size" <= 10 OR "size" IS NULL) AND ("team_id" NOT IN (1, 2) OR "team_id" IS NULL)"#
+        );
+
+        let predicate = Predicate::eq("deleted_at", Value::Null).not();
+
+        assert_eq!(
+            render_where(predicate),
+            r#"SELECT * FROM "document" WHERE "deleted_at" IS NOT NULL"#
+        );
+
+        // Where the column must not be null anyway, the negation needn't check for it
+        let predicate = Predicate::eq("archived", true)
+            .or(Predicate::eq("archived", Value::Null))
+            .not();
+
+        assert_eq!(
+            render_where(predicate),
+            r#"SELECT * FROM "document" WHERE "archived" <> TRUE AND "archived" IS NOT NULL"#
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use crate::asynchronous::AsyncPolicy;
+use crate::rbac::{
+    decide_on_roles, FlatRoles, GlobalRbacSubject, Hierarchy, RbacResource, RbacResourceWithRole,
+    RbacSubject,
+};
+use crate::{Decision, Resource, Subject};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// The asynchronous counterpart of [`GlobalRbacSubject`], for subjects whose roles have to be
+/// loaded. Every [`GlobalRbacSubject`] is also an `AsyncGlobalRbacSubject`.
+#[async_trait]
+pub trait AsyncGlobalRbacSubject: Subject + Sync {
+    type GlobalRole: Hash + Eq + Send;
+    /// The error returned when the roles can't be loaded, such as a database error.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    async fn global_roles(&self) -> Result<HashSet<Self::GlobalRole>, Self::Error>;
+}
+
+#[async_trait]
+impl<T> AsyncGlobalRbacSubject for T
+where
+    T: GlobalRbacSubject + Sync,
+    T::GlobalRole: Send,
+{
+    type GlobalRole = T::GlobalRole;
+    type Error = Infallible;
+
+    async fn global_roles(&self) -> Result<HashSet<Self::GlobalRole>, Infallible> {
+        Ok(GlobalRbacSubject::global_roles(self))
+    }
+}
+
+/// The asynchronous counterpart of [`RbacSubject`], for subjects whose roles on a resource have
+/// to be loaded, such as project memberships stored in a database. Every [`RbacSubject`] is also
+/// an `AsyncRbacSubject`.
+#[async_trait]
+pub trait AsyncRbacSubject<Res>: Subject + Sync
+where
+    Res: RbacResourceWithRole + Sync,
+{
+    /// The error returned when the roles can't be loaded, such as a database error.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    async fn resource_roles(&self, resource: &Res) -> Result<HashSet<Res::Role>, Self::Error>;
+}
+
+#[async_trait]
+impl<T, Res> AsyncRbacSubject<Res> for T
+where
+    T: RbacSubject<Res> + Sync,
+    Res: RbacResourceWithRole + Sync,
+    Res::Role: Send,
+{
+    type Error = Infallible;
+
+    async fn resource_roles(&self, resource: &Res) -> Result<HashSet<Res::Role>, Infallible> {
+        Ok(RbacSubject::resource_roles(self, resource))
+    }
+}
+
+/// The asynchronous counterpart of [`RbacResource`]. Every [`RbacResource`] is also an
+/// `AsyncRbacResource`.
+#[async_trait]
+pub trait AsyncRbacResource<Role>: Resource + Sync
+where
+    Self::Action: Sync,
+{
+    /// The error returned when the allowed roles can't be loaded.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    async fn allowed_roles(&self, action: &Self::Action) -> Result<HashSet<Role>, Self::Error>;
+}
+
+#[async_trait]
+impl<T, Role> AsyncRbacResource<Role> for T
+where
+    T: RbacResource<Role> + Sync,
+    T::Action: Sync,
+    Role: Send,
+{
+    type Error = Infallible;
+
+    async fn allowed_roles(&self, action: &Self::Action) -> Result<HashSet<Role>, Infallible> {
+        Ok(RbacResource::allowed_roles(self, action))
+    }
+}
+
+/// The asynchronous counterpart of [`GlobalRbacPolicy`](crate::rbac::GlobalRbacPolicy). If the
+/// subject's roles or the allowed roles can't be loaded the action is denied.
+#[derive(Default)]
+pub struct AsyncGlobalRbacPolicy<H = FlatRoles> {
+    hierarchy: H,
+}
+
+impl AsyncGlobalRbacPolicy {
+    pub fn new() -> Self {
+        AsyncGlobalRbacPolicy {
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<H> AsyncGlobalRbacPolicy<H> {
+    pub fn with_hierarchy(hierarchy: H) -> Self {
+        AsyncGlobalRbacPolicy { hierarchy }
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, H> AsyncPolicy<Res, Subj> for AsyncGlobalRbacPolicy<H>
+where
+    Subj: AsyncGlobalRbacSubject,
+    Subj::GlobalRole: Debug,
+    Res: AsyncRbacResource<Subj::GlobalRole>,
+    Res::Action: Sync,
+    H: Hierarchy<Subj::GlobalRole> + Send + Sync,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let subject_global_roles = match subject.global_roles().await {
+            Ok(roles) => self.hierarchy.expand(roles),
+            Err(e) => return Decision::deny(format!("Failed to load subject's roles: {}", e)),
+        };
+
+        let allowed_global_roles = match resource.allowed_roles(action).await {
+            Ok(roles) => roles,
+            Err(e) => return Decision::deny(format!("Failed to load allowed roles: {}", e)),
+        };
+
+        decide_on_roles("global", &subject_global_roles, &allowed_global_roles)
+    }
+}
+
+/// The asynchronous counterpart of [`ResourceRbacPolicy`](crate::rbac::ResourceRbacPolicy). If
+/// the subject's roles or the allowed roles can't be loaded the action is denied.
+#[derive(Default)]
+pub struct AsyncResourceRbacPolicy<H = FlatRoles> {
+    hierarchy: H,
+}
+
+impl AsyncResourceRbacPolicy {
+    pub fn new() -> Self {
+        AsyncResourceRbacPolicy {
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<H> AsyncResourceRbacPolicy<H> {
+    pub fn with_hierarchy(hierarchy: H) -> Self {
+        AsyncResourceRbacPolicy { hierarchy }
+    }
+}
+
+#[async_trait]
+impl<Res, Subj, H> AsyncPolicy<Res, Subj> for AsyncResourceRbacPolicy<H>
+where
+    Subj: AsyncRbacSubject<Res>,
+    Res: RbacResourceWithRole + AsyncRbacResource<<Res as RbacResourceWithRole>::Role>,
+    Res::Action: Sync,
+    Res::Role: Debug + Send,
+    H: Hierarchy<Res::Role> + Send + Sync,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let subject_resource_roles = match subject.resource_roles(resource).await {
+            Ok(roles) => self.hierarchy.expand(roles),
+            Err(e) => return Decision::deny(format!("Failed to load subject's roles: {}", e)),
+        };
+
+        let allowed_resource_roles =
+            match AsyncRbacResource::<Res::Role>::allowed_roles(resource, action).await {
+                Ok(roles) => roles,
+                Err(e) => return Decision::deny(format!("Failed to load allowed roles: {}", e)),
+            };
+
+        decide_on_roles("resource", &subject_resource_roles, &allowed_resource_roles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asynchronous::FromSync;
+    use crate::rbac::GlobalRbacPolicy;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use thiserror::Error;
+
+    struct Project {
+        id: u32,
+    }
+
+    impl Resource for Project {
+        type Action = ProjectAction;
+    }
+
+    impl RbacResourceWithRole for Project {
+        type Role = ProjectRole;
+    }
+
+    impl RbacResource<ProjectRole> for Project {
+        fn allowed_roles(&self, action: &ProjectAction) -> HashSet<ProjectRole> {
+            match action {
+                ProjectAction::View => HashSet::from([ProjectRole::Member, ProjectRole::Owner]),
+                ProjectAction::Archive => HashSet::from([ProjectRole::Owner]),
+            }
+        }
+    }
+
+    impl RbacResource<GlobalRole> for Project {
+        fn allowed_roles(&self, _action: &ProjectAction) -> HashSet<GlobalRole> {
+            HashSet::from([GlobalRole::Admin])
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum ProjectAction {
+        View,
+        Archive,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    enum ProjectRole {
+        Member,
+        Owner,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    enum GlobalRole {
+        Admin,
+    }
+
+    /// Stands in for a membership table that has to be queried.
+    struct Memberships(HashMap<(u32, &'static str), ProjectRole>);
+
+    struct User<'a> {
+        name: &'static str,
+        memberships: &'a Memberships,
+    }
+
+    impl Subject for User<'_> {}
+
+    #[derive(Debug, Error)]
+    #[error("database unavailable")]
+    struct DatabaseUnavailable;
+
+    #[async_trait]
+    impl AsyncRbacSubject<Project> for User<'_> {
+        type Error = DatabaseUnavailable;
+
+        async fn resource_roles(
+            &self,
+            project: &Project,
+        ) -> Result<HashSet<ProjectRole>, DatabaseUnavailable> {
+            if self.name == "unreachable" {
+                return Err(DatabaseUnavailable);
+            }
+
+            Ok(self
+                .memberships
+                .0
+                .get(&(project.id, self.name))
+                .cloned()
+                .into_iter()
+                .collect())
+        }
+    }
+
+    struct Admin;
+
+    impl Subject for Admin {}
+
+    impl GlobalRbacSubject for Admin {
+        type GlobalRole = GlobalRole;
+
+        fn global_roles(&self) -> HashSet<GlobalRole> {
+            HashSet::from([GlobalRole::Admin])
+        }
+    }
+
+    #[test]
+    fn load_resource_roles() {
+        let memberships = Memberships(HashMap::from([
+            ((1, "alice"), ProjectRole::Owner),
+            ((1, "bob"), ProjectRole::Member),
+        ]));
+        let alice = User {
+            name: "alice",
+            memberships: &memberships,
+        };
+        let bob = User {
+            name: "bob",
+            memberships: &memberships,
+        };
+        let unreachable = User {
+            name: "unreachable",
+            memberships: &memberships,
+        };
+
+        let policy = AsyncResourceRbacPolicy::new();
+        let project = Project { id: 1 };
+
+        block_on(async {
+            assert!(policy
+                .authorise(&project, &alice, &ProjectAction::Archive)
+                .await
+                .is_ok());
+            assert!(policy
+                .decide(&project, &bob, &ProjectAction::View)
+                .await
+                .is_permit());
+            assert!(policy
+                .decide(&project, &bob, &ProjectAction::Archive)
+                .await
+                .is_deny());
+            assert!(policy
+                .decide(&Project { id: 2 }, &alice, &ProjectAction::View)
+                .await
+                .is_deny());
+
+            let decision = policy
+                .decide(&project, &unreachable, &ProjectAction::View)
+                .await;
+            assert!(decision.is_deny());
+            assert_eq!(
+                decision.reason(),
+                "Failed to load subject's roles: database unavailable"
+            );
+        });
+    }
+
+    #[test]
+    fn adapt_sync_policies_and_subjects() {
+        let project = Project { id: 1 };
+
+        block_on(async {
+            // A sync subject used with an async policy
+            assert!(AsyncGlobalRbacPolicy::new()
+                .decide(&project, &Admin, &ProjectAction::Archive)
+                .await
+                .is_permit());
+
+            // A sync policy used as an async one
+            assert!(FromSync(GlobalRbacPolicy::new())
+                .decide(&project, &Admin, &ProjectAction::Archive)
+                .await
+                .is_permit());
+        });
+    }
+}
@@ -1,5 +1,551 @@
-#[cfg(test)]
+//! Role-based access control driven by configuration rather than code, so that who may do what
+//! can be changed without recompiling. The configuration maps resource names and action names to
+//! the names of the global roles allowed to perform them:
+//!
+//! ```yaml
+//! resources:
+//!   customer:
+//!     actions:
+//!       read:
+//!         allowed roles: [admin, support]
+//!       write:
+//!         allowed roles: [admin]
+//!   product:
+//! roles:
+//!   admin:
+//!     inherits: [support]
+//! ```
+//!
+//! The optional `roles` section declares a role hierarchy, in which a role is also granted every
+//! role it inherits. Resources, actions and roles may also be written as lists, where each item
+//! names an entry and either nests its fields under the name or lists them alongside it:
+//!
+//! ```yaml
+//! resources:
+//!   - customer:
+//!       actions:
+//!         - read:
+//!           allowed roles: [admin]
+//!   - product:
+//! ```
+//!
+//! The same structure can be loaded from TOML or JSON, where `allowed_roles` may be used in place
+//! of `allowed roles`. Each format sits behind the `yaml`, `toml` or `json` feature, none of which
+//! is enabled by default, so enable the formats you load:
+//!
+//! ```toml
+//! author = { version = "0.1", features = ["yaml"] }
+//! ```
+
+use crate::query::{QueryError, QueryPolicy, QueryResource, Residual};
+use crate::rbac::{GlobalRbacSubject, Hierarchy, HierarchyError, RoleHierarchy};
+use crate::{Decision, Enumerable, Named, NamedResource, Policy, SubjectDecider};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "yaml")]
+    #[error("Invalid YAML config: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[cfg(feature = "toml")]
+    #[error("Invalid TOML config: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[cfg(feature = "json")]
+    #[error("Invalid JSON config: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported config format: {0:?}")]
+    UnsupportedFormat(Option<String>),
+    #[error("Invalid config: {}", format_validation_errors(.0))]
+    Invalid(Vec<ValidationError>),
+    #[error("Invalid role hierarchy: {0}")]
+    Hierarchy(#[from] HierarchyError<String>),
+}
+
+/// A problem found when checking a configuration against an [`RbacSchema`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("unknown resource '{0}'")]
+    UnknownResource(String),
+    #[error("unknown action '{action}' on resource '{resource}'")]
+    UnknownAction { resource: String, action: String },
+    #[error("unknown role '{role}' allowed to '{action}' resource '{resource}'")]
+    UnknownRole {
+        resource: String,
+        action: String,
+        role: String,
+    },
+    #[error("unknown role '{0}' in role hierarchy")]
+    UnknownHierarchyRole(String),
+}
+
+fn format_validation_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RbacPolicyConfig {
+    #[serde(default, deserialize_with = "deserialize_entries")]
+    pub resources: BTreeMap<String, ResourceConfig>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_entries",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub roles: BTreeMap<String, RoleConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceConfig {
+    #[serde(default, deserialize_with = "deserialize_entries")]
+    pub actions: BTreeMap<String, ActionConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionConfig {
+    #[serde(default, rename = "allowed roles", alias = "allowed_roles")]
+    pub allowed_roles: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleConfig {
+    #[serde(default)]
+    pub inherits: BTreeSet<String>,
+}
+
+impl RbacPolicyConfig {
+    /// Builds the role hierarchy declared in the `roles` section.
+    pub fn role_hierarchy(&self) -> Result<RoleHierarchy<String>, HierarchyError<String>> {
+        let mut builder = RoleHierarchy::builder();
+
+        for (role, role_config) in &self.roles {
+            for inherited in &role_config.inherits {
+                builder = builder.inherit(role.clone(), inherited.clone());
+            }
+        }
+
+        builder.build()
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(config: &str) -> Result<Self, ConfigError> {
+        Ok(serde_yaml::from_str(config)?)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(config: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(config)?)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(config: &str) -> Result<Self, ConfigError> {
+        Ok(serde_json::from_str(config)?)
+    }
+
+    /// Loads the config from a file, choosing the format based on the file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&std::fs::read_to_string(path)?),
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml(&std::fs::read_to_string(path)?),
+            #[cfg(feature = "json")]
+            Some("json") => Self::from_json(&std::fs::read_to_string(path)?),
+            other => Err(ConfigError::UnsupportedFormat(other.map(str::to_string))),
+        }
+    }
+}
+
+/// Entries may be given either as a map or as a list, and an entry with no body is treated as
+/// empty.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entries<T> {
+    Map(BTreeMap<String, Option<T>>),
+    List(Vec<ListEntry<T>>),
+}
+
+/// An item of a list of entries, which is either a single-entry map from the name to the body, or
+/// a map holding the name with no value alongside the fields of the body.
+#[derive(Deserialize)]
+struct ListEntry<T> {
+    #[serde(flatten)]
+    fields: T,
+    #[serde(flatten)]
+    names: BTreeMap<String, Option<T>>,
+}
+
+impl<T> ListEntry<T>
+where
+    T: Default + PartialEq,
+{
+    fn into_entry<E>(self) -> Result<(String, T), E>
+    where
+        E: serde::de::Error,
+    {
+        let mut names = self.names.into_iter();
+
+        let (Some((name, body)), None) = (names.next(), names.next()) else {
+            return Err(E::custom("list entries must name exactly one entry"));
+        };
+
+        match body {
+            None => Ok((name, self.fields)),
+            Some(body) if self.fields == T::default() => Ok((name, body)),
+            Some(_) => Err(E::custom(format!(
+                "entry '{}' has fields both nested under and alongside its name",
+                name
+            ))),
+        }
+    }
+}
+
+fn deserialize_entries<'de, D, T>(deserializer: D) -> Result<BTreeMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default + PartialEq,
+{
+    let entries = match Option::<Entries<T>>::deserialize(deserializer)? {
+        None => return Ok(BTreeMap::new()),
+        Some(Entries::Map(map)) => map
+            .into_iter()
+            .map(|(name, body)| (name, body.unwrap_or_default()))
+            .collect(),
+        Some(Entries::List(list)) => list
+            .into_iter()
+            .map(ListEntry::into_entry)
+            .collect::<Result<Vec<_>, D::Error>>()?,
+    };
+
+    let mut result = BTreeMap::new();
+
+    for (name, value) in entries {
+        if result.contains_key(&name) {
+            return Err(D::Error::custom(format!("duplicate entry '{}'", name)));
+        }
+
+        result.insert(name, value);
+    }
+
+    Ok(result)
+}
+
+/// The resources, actions and roles known to the application, used to catch mistakes such as
+/// misspelled role names when a configuration is loaded rather than silently denying access.
+#[derive(Debug, Clone, Default)]
+pub struct RbacSchema {
+    resources: BTreeMap<String, BTreeSet<String>>,
+    roles: BTreeSet<String>,
+}
+
+impl RbacSchema {
+    pub fn new() -> Self {
+        RbacSchema::default()
+    }
+
+    /// Registers a resource type along with all of its actions.
+    pub fn resource<Res>(mut self) -> Self
+    where
+        Res: NamedResource,
+        Res::Action: Named + Enumerable,
+    {
+        let actions = Res::Action::all()
+            .iter()
+            .map(|a| a.name().to_string())
+            .collect();
+
+        self.resources
+            .insert(Res::RESOURCE_NAME.to_string(), actions);
+        self
+    }
+
+    /// Registers every value of a role type.
+    pub fn roles<Role>(mut self) -> Self
+    where
+        Role: Named + Enumerable,
+    {
+        self.roles
+            .extend(Role::all().iter().map(|r| r.name().to_string()));
+        self
+    }
+
+    /// Registers a single role by name.
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.roles.insert(role.into());
+        self
+    }
+
+    /// Checks that the config only refers to known resources, actions and roles, reporting every
+    /// problem found rather than just the first.
+    pub fn validate(&self, config: &RbacPolicyConfig) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        for (resource, resource_config) in &config.resources {
+            let Some(known_actions) = self.resources.get(resource) else {
+                errors.push(ValidationError::UnknownResource(resource.clone()));
+                continue;
+            };
+
+            for (action, action_config) in &resource_config.actions {
+                if !known_actions.contains(action) {
+                    errors.push(ValidationError::UnknownAction {
+                        resource: resource.clone(),
+                        action: action.clone(),
+                    });
+                }
+
+                for role in &action_config.allowed_roles {
+                    if !self.roles.contains(role) {
+                        errors.push(ValidationError::UnknownRole {
+                            resource: resource.clone(),
+                            action: action.clone(),
+                            role: role.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (role, role_config) in &config.roles {
+            for role in std::iter::once(role).chain(&role_config.inherits) {
+                if !self.roles.contains(role) {
+                    errors.push(ValidationError::UnknownHierarchyRole(role.clone()));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+/// Policy that permits an action if the subject holds one of the global roles that the config
+/// allows to perform it, either directly or through the configured role hierarchy. Resources and
+/// actions missing from the config are not applicable.
+#[derive(Debug)]
+pub struct ConfigRbacPolicy {
+    config: RbacPolicyConfig,
+    hierarchy: RoleHierarchy<String>,
+}
+
+impl ConfigRbacPolicy {
+    /// Creates a policy from the given config, after checking it against the schema.
+    pub fn new(config: RbacPolicyConfig, schema: &RbacSchema) -> Result<Self, ConfigError> {
+        schema.validate(&config)?;
+        let hierarchy = config.role_hierarchy()?;
+
+        Ok(ConfigRbacPolicy { config, hierarchy })
+    }
+
+    pub fn config(&self) -> &RbacPolicyConfig {
+        &self.config
+    }
+}
+
+impl<Res, Subj> Policy<Res, Subj> for ConfigRbacPolicy
+where
+    Res: NamedResource,
+    Res::Action: Named,
+    Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Named,
+{
+    fn decide(&self, _resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        self.decide_for::<Res, Subj>(subject, action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let subject_roles = self.subject_roles(subject);
+
+        Box::new(move |_resource, action| self.decide_on_roles::<Res>(&subject_roles, action))
+    }
+}
+
+/// The configuration only depends on the type of resource, so the same decision applies to every
+/// row of its table.
+impl<Res, Subj> QueryPolicy<Res, Subj> for ConfigRbacPolicy
+where
+    Res: NamedResource + QueryResource,
+    Res::Action: Named,
+    Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Named,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        Ok(Residual::from_decision(
+            &self.decide_for::<Res, Subj>(subject, action),
+        ))
+    }
+}
+
+impl ConfigRbacPolicy {
+    fn decide_for<Res, Subj>(&self, subject: &Subj, action: &Res::Action) -> Decision
+    where
+        Res: NamedResource,
+        Res::Action: Named,
+        Subj: GlobalRbacSubject,
+        Subj::GlobalRole: Named,
+    {
+        self.decide_on_roles::<Res>(&self.subject_roles(subject), action)
+    }
+
+    /// The names of the roles the subject holds, including those inherited through the hierarchy.
+    fn subject_roles<Subj>(&self, subject: &Subj) -> HashSet<String>
+    where
+        Subj: GlobalRbacSubject,
+        Subj::GlobalRole: Named,
+    {
+        self.hierarchy.expand(
+            subject
+                .global_roles()
+                .iter()
+                .map(|r| r.name().to_string())
+                .collect(),
+        )
+    }
+
+    fn decide_on_roles<Res>(
+        &self,
+        subject_roles: &HashSet<String>,
+        action: &Res::Action,
+    ) -> Decision
+    where
+        Res: NamedResource,
+        Res::Action: Named,
+    {
+        let resource_name = Res::RESOURCE_NAME;
+        let action_name = action.name();
+
+        let Some(resource_config) = self.config.resources.get(resource_name) else {
+            return Decision::not_applicable(format!(
+                "No rules configured for resource '{}'",
+                resource_name
+            ));
+        };
+
+        let Some(action_config) = resource_config.actions.get(action_name) else {
+            return Decision::not_applicable(format!(
+                "No rules configured for action '{}' on resource '{}'",
+                action_name, resource_name
+            ));
+        };
+
+        let rule = format!("{}:{}", resource_name, action_name);
+
+        let matching_roles: BTreeSet<_> = subject_roles
+            .iter()
+            .map(String::as_str)
+            .filter(|r| action_config.allowed_roles.contains(*r))
+            .collect();
+
+        if matching_roles.is_empty() {
+            return Decision::deny(format!(
+                "Subject holds none of the roles allowed to '{}' resource '{}'; allowed roles are [{}]",
+                action_name,
+                resource_name,
+                join(action_config.allowed_roles.iter().map(String::as_str)),
+            ))
+            .with_rule(rule);
+        }
+
+        Decision::permit(format!(
+            "Subject holds roles [{}] which are allowed to '{}' resource '{}'",
+            join(matching_roles),
+            action_name,
+            resource_name,
+        ))
+        .with_rule(rule)
+    }
+}
+
+fn join<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    names.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(all(test, feature = "yaml"))]
 mod tests {
+    use super::*;
+    use crate::query::Predicate;
+    use crate::{AuthorisedIterator, Resource, Subject};
+    use std::cell::Cell;
+    use std::collections::HashSet;
+
+    struct Customer;
+
+    impl Resource for Customer {
+        type Action = CustomerAction;
+    }
+
+    impl NamedResource for Customer {
+        const RESOURCE_NAME: &'static str = "customer";
+    }
+
+    impl QueryResource for Customer {}
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum CustomerAction {
+        Read,
+        Write,
+    }
+
+    impl Named for CustomerAction {
+        fn name(&self) -> &str {
+            match self {
+                CustomerAction::Read => "read",
+                CustomerAction::Write => "write",
+            }
+        }
+    }
+
+    impl Enumerable for CustomerAction {
+        fn all() -> Vec<Self> {
+            vec![CustomerAction::Read, CustomerAction::Write]
+        }
+    }
+
+    struct User(&'static str);
+
+    impl Subject for User {}
+
+    impl GlobalRbacSubject for User {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            HashSet::from([Role(self.0)])
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct Role(&'static str);
+
+    impl Named for Role {
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn schema() -> RbacSchema {
+        RbacSchema::new()
+            .resource::<Customer>()
+            .role("admin")
+            .role("support")
+    }
+
     #[test]
     fn load_from_yaml() {
         let config_yaml = r#"
@@ -10,5 +556,240 @@ mod tests {
                       allowed roles: [admin]
               - user:
         "#;
+
+        let config = RbacPolicyConfig::from_yaml(config_yaml).unwrap();
+
+        assert_eq!(
+            config.resources["customer"].actions["read"].allowed_roles,
+            BTreeSet::from(["admin".to_string()])
+        );
+        assert!(config.resources["user"].actions.is_empty());
+    }
+
+    #[test]
+    fn load_nested_list_entries_from_yaml() {
+        let config_yaml = r#"
+            resources:
+              - customer:
+                  actions:
+                    - read:
+                        allowed roles: [admin]
+        "#;
+
+        let config = RbacPolicyConfig::from_yaml(config_yaml).unwrap();
+
+        assert_eq!(
+            config.resources["customer"].actions["read"].allowed_roles,
+            BTreeSet::from(["admin".to_string()])
+        );
+
+        let mixed_yaml = r#"
+            resources:
+              - customer:
+                  actions:
+                    - read:
+                        allowed roles: [admin]
+                      allowed roles: [support]
+        "#;
+
+        assert!(RbacPolicyConfig::from_yaml(mixed_yaml).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn load_from_toml() {
+        let config_toml = r#"
+            [resources.customer.actions.read]
+            allowed_roles = ["admin", "support"]
+        "#;
+
+        let config = RbacPolicyConfig::from_toml(config_toml).unwrap();
+
+        assert_eq!(
+            config.resources["customer"].actions["read"].allowed_roles,
+            BTreeSet::from(["admin".to_string(), "support".to_string()])
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn load_from_json() {
+        let config_json = r#"
+            { "resources": { "customer": { "actions": { "write": { "allowed roles": ["admin"] } } } } }
+        "#;
+
+        let config = RbacPolicyConfig::from_json(config_json).unwrap();
+
+        assert_eq!(
+            config.resources["customer"].actions["write"].allowed_roles,
+            BTreeSet::from(["admin".to_string()])
+        );
+    }
+
+    #[test]
+    fn validate_against_schema() {
+        let config = RbacPolicyConfig::from_yaml(
+            r#"
+            resources:
+              customer:
+                actions:
+                  read:
+                    allowed roles: [admin, amdin]
+                  delete:
+              product:
+            "#,
+        )
+        .unwrap();
+
+        let Err(ConfigError::Invalid(errors)) = schema().validate(&config) else {
+            panic!("expected validation to fail");
+        };
+
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::UnknownAction {
+                    resource: "customer".to_string(),
+                    action: "delete".to_string(),
+                },
+                ValidationError::UnknownRole {
+                    resource: "customer".to_string(),
+                    action: "read".to_string(),
+                    role: "amdin".to_string(),
+                },
+                ValidationError::UnknownResource("product".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn authorise_from_config() {
+        let config = RbacPolicyConfig::from_yaml(
+            r#"
+            resources:
+              customer:
+                actions:
+                  read:
+                    allowed roles: [admin, support]
+            "#,
+        )
+        .unwrap();
+
+        let policy = ConfigRbacPolicy::new(config, &schema()).unwrap();
+
+        let decision = policy.decide(&Customer, &User("support"), &CustomerAction::Read);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("customer:read"));
+
+        let decision = policy.decide(&Customer, &User("guest"), &CustomerAction::Read);
+        assert!(decision.is_deny());
+
+        let decision = policy.decide(&Customer, &User("admin"), &CustomerAction::Write);
+        assert!(decision.is_not_applicable());
+
+        // Every customer is readable by support, and none by guests
+        let residual =
+            QueryPolicy::<Customer, _>::residual(&policy, &User("support"), &CustomerAction::Read)
+                .unwrap();
+        assert_eq!(residual, Residual::new(Predicate::True, Predicate::False));
+
+        let residual =
+            QueryPolicy::<Customer, _>::residual(&policy, &User("guest"), &CustomerAction::Read)
+                .unwrap();
+        assert_eq!(residual, Residual::new(Predicate::False, Predicate::True));
+    }
+
+    #[test]
+    fn inherit_roles_from_config() {
+        let config = RbacPolicyConfig::from_yaml(
+            r#"
+            resources:
+              customer:
+                actions:
+                  read:
+                    allowed roles: [support]
+            roles:
+              admin:
+                inherits: [support]
+            "#,
+        )
+        .unwrap();
+
+        let policy = ConfigRbacPolicy::new(config, &schema()).unwrap();
+
+        let decision = policy.decide(&Customer, &User("admin"), &CustomerAction::Read);
+        assert!(decision.is_permit());
+
+        let config = RbacPolicyConfig::from_yaml(
+            r#"
+            roles:
+              admin:
+                inherits: [support]
+              support:
+                inherits: [admin]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            ConfigRbacPolicy::new(config, &schema()),
+            Err(ConfigError::Hierarchy(HierarchyError::Cycle(_)))
+        ));
+    }
+
+    struct Admin {
+        role_lookups: Cell<usize>,
+    }
+
+    impl Subject for Admin {}
+
+    impl GlobalRbacSubject for Admin {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            self.role_lookups.set(self.role_lookups.get() + 1);
+            HashSet::from([Role("admin")])
+        }
+    }
+
+    #[test]
+    fn load_roles_once_per_batch() {
+        let config = RbacPolicyConfig::from_yaml(
+            r#"
+            resources:
+              customer:
+                actions:
+                  read:
+                    allowed roles: [support]
+            roles:
+              admin:
+                inherits: [support]
+            "#,
+        )
+        .unwrap();
+
+        let policy = ConfigRbacPolicy::new(config, &schema()).unwrap();
+        let admin = Admin {
+            role_lookups: Cell::new(0),
+        };
+        let customers = [Customer, Customer, Customer];
+
+        let decisions = policy.decide_many(
+            &admin,
+            customers
+                .iter()
+                .flat_map(|c| [(c, &CustomerAction::Read), (c, &CustomerAction::Write)]),
+        );
+        assert_eq!(decisions.len(), 6);
+        assert!(decisions[0].is_permit());
+        assert!(decisions[1].is_not_applicable());
+        assert_eq!(admin.role_lookups.get(), 1);
+
+        let readable = customers
+            .iter()
+            .filter_authorised(&policy, &admin, &CustomerAction::Read)
+            .count();
+        assert_eq!(readable, 3);
+        assert_eq!(admin.role_lookups.get(), 2);
     }
 }
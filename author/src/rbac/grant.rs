@@ -0,0 +1,316 @@
+use crate::decision::format_roles;
+use crate::rbac::{decide_on_roles, FlatRoles, Hierarchy, RbacResource};
+use crate::{Decision, Policy, Subject};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// The source of the current time used to decide which grants are active.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+impl<C> Clock for &C
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+impl<C> Clock for Arc<C>
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// The system's clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    pub fn new(now: SystemTime) -> Self {
+        ManualClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock()
+    }
+}
+
+type GrantCondition = Arc<dyn Fn(SystemTime) -> bool + Send + Sync>;
+
+/// A role granted to a subject, which may only be active for a period of time or while a
+/// condition holds.
+#[derive(Clone)]
+pub struct RoleGrant<Role> {
+    role: Role,
+    valid_from: Option<SystemTime>,
+    valid_until: Option<SystemTime>,
+    condition: Option<GrantCondition>,
+}
+
+impl<Role> RoleGrant<Role> {
+    /// Grants a role with no time limits.
+    pub fn new(role: Role) -> Self {
+        RoleGrant {
+            role,
+            valid_from: None,
+            valid_until: None,
+            condition: None,
+        }
+    }
+
+    /// The grant isn't active before this time.
+    pub fn valid_from(mut self, time: SystemTime) -> Self {
+        self.valid_from = Some(time);
+        self
+    }
+
+    /// The grant expires at this time.
+    pub fn valid_until(mut self, time: SystemTime) -> Self {
+        self.valid_until = Some(time);
+        self
+    }
+
+    /// The grant is only active at the times this returns true, such as during an on-call shift.
+    pub fn active_when<F>(mut self, condition: F) -> Self
+    where
+        F: Fn(SystemTime) -> bool + Send + Sync + 'static,
+    {
+        self.condition = Some(Arc::new(condition));
+        self
+    }
+
+    pub fn role(&self) -> &Role {
+        &self.role
+    }
+
+    pub fn is_active_at(&self, time: SystemTime) -> bool {
+        self.valid_from.is_none_or(|from| time >= from)
+            && self.valid_until.is_none_or(|until| time < until)
+            && self
+                .condition
+                .as_ref()
+                .is_none_or(|condition| condition(time))
+    }
+}
+
+impl<Role> Debug for RoleGrant<Role>
+where
+    Role: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoleGrant")
+            .field("role", &self.role)
+            .field("valid_from", &self.valid_from)
+            .field("valid_until", &self.valid_until)
+            .field("conditional", &self.condition.is_some())
+            .finish()
+    }
+}
+
+/// A subject whose roles are granted for limited periods of time.
+pub trait GrantedRbacSubject: Subject {
+    type GrantedRole: Hash + Eq;
+
+    fn role_grants(&self) -> Vec<RoleGrant<Self::GrantedRole>> {
+        Vec::new()
+    }
+}
+
+/// Policy that grants access based on the roles a subject has been granted, ignoring any grants
+/// that aren't active according to the policy's clock.
+#[derive(Default)]
+pub struct GrantedRbacPolicy<C = SystemClock, H = FlatRoles> {
+    clock: C,
+    hierarchy: H,
+}
+
+impl GrantedRbacPolicy {
+    pub fn new() -> Self {
+        GrantedRbacPolicy {
+            clock: SystemClock,
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<C, H> GrantedRbacPolicy<C, H> {
+    /// Uses a different clock, such as a [`ManualClock`] in tests.
+    pub fn with_clock<C2>(self, clock: C2) -> GrantedRbacPolicy<C2, H> {
+        GrantedRbacPolicy {
+            clock,
+            hierarchy: self.hierarchy,
+        }
+    }
+
+    /// Also grants every role inherited from the active roles a subject holds.
+    pub fn with_hierarchy<H2>(self, hierarchy: H2) -> GrantedRbacPolicy<C, H2> {
+        GrantedRbacPolicy {
+            clock: self.clock,
+            hierarchy,
+        }
+    }
+}
+
+impl<Res, Subj, C, H> Policy<Res, Subj> for GrantedRbacPolicy<C, H>
+where
+    Subj: GrantedRbacSubject,
+    Subj::GrantedRole: Debug,
+    Res: RbacResource<Subj::GrantedRole>,
+    C: Clock,
+    H: Hierarchy<Subj::GrantedRole>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let now = self.clock.now();
+        let (active, inactive): (Vec<_>, Vec<_>) = subject
+            .role_grants()
+            .into_iter()
+            .partition(|grant| grant.is_active_at(now));
+
+        let active_roles = self
+            .hierarchy
+            .expand(active.into_iter().map(|grant| grant.role).collect());
+        let allowed_roles = RbacResource::allowed_roles(resource, action);
+
+        let decision = decide_on_roles("active", &active_roles, &allowed_roles);
+
+        if decision.is_permit() {
+            return decision;
+        }
+
+        // Explain when access would have been allowed if a grant were active
+        let inactive_roles = self
+            .hierarchy
+            .expand(inactive.into_iter().map(|grant| grant.role).collect());
+        let inactive_matches: HashSet<_> = allowed_roles.intersection(&inactive_roles).collect();
+
+        if inactive_matches.is_empty() {
+            decision
+        } else {
+            Decision::deny(format!(
+                "Subject's grants of roles {} which are allowed to perform this action are not \
+                 active",
+                format_roles(inactive_matches),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resource;
+
+    struct Page;
+
+    impl Resource for Page {
+        type Action = ();
+    }
+
+    impl RbacResource<Role> for Page {
+        fn allowed_roles(&self, _action: &()) -> HashSet<Role> {
+            HashSet::from([Role::Editor, Role::OnCall])
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Editor,
+        OnCall,
+    }
+
+    struct User(Vec<RoleGrant<Role>>);
+
+    impl Subject for User {}
+
+    impl GrantedRbacSubject for User {
+        type GrantedRole = Role;
+
+        fn role_grants(&self) -> Vec<RoleGrant<Role>> {
+            self.0.clone()
+        }
+    }
+
+    fn days(days: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(days * 86_400)
+    }
+
+    #[test]
+    fn ignore_expired_grants() {
+        let clock = Arc::new(ManualClock::new(days(10)));
+        let policy = GrantedRbacPolicy::new().with_clock(clock.clone());
+        let contractor = User(vec![RoleGrant::new(Role::Editor)
+            .valid_from(days(5))
+            .valid_until(days(20))]);
+
+        assert!(policy.decide(&Page, &contractor, &()).is_permit());
+
+        clock.set(days(20));
+        let decision = policy.decide(&Page, &contractor, &());
+        assert!(decision.is_deny());
+        assert_eq!(
+            decision.reason(),
+            "Subject's grants of roles [Editor] which are allowed to perform this action are not \
+             active"
+        );
+
+        clock.set(days(4));
+        assert!(policy.decide(&Page, &contractor, &()).is_deny());
+    }
+
+    #[test]
+    fn conditional_grants() {
+        let clock = Arc::new(ManualClock::new(days(0)));
+        let policy = GrantedRbacPolicy::new().with_clock(clock.clone());
+
+        // On call for the first eight hours of each day
+        let engineer = User(vec![RoleGrant::new(Role::OnCall).active_when(|now| {
+            let seconds = now
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            seconds % 86_400 < 8 * 3_600
+        })]);
+
+        assert!(policy.decide(&Page, &engineer, &()).is_permit());
+
+        clock.advance(Duration::from_secs(9 * 3_600));
+        assert!(policy.decide(&Page, &engineer, &()).is_deny());
+
+        clock.advance(Duration::from_secs(16 * 3_600));
+        assert!(policy.decide(&Page, &engineer, &()).is_permit());
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use thiserror::Error;
+
+/// Expands the roles a subject holds to include every role they inherit, so that for example an
+/// admin is also treated as an editor and a viewer without each resource having to list all three.
+pub trait Hierarchy<Role> {
+    fn expand(&self, roles: HashSet<Role>) -> HashSet<Role>;
+}
+
+/// The default hierarchy, in which roles don't inherit from each other.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FlatRoles;
+
+impl<Role> Hierarchy<Role> for FlatRoles {
+    fn expand(&self, roles: HashSet<Role>) -> HashSet<Role> {
+        roles
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HierarchyError<Role>
+where
+    Role: Debug,
+{
+    #[error("Role hierarchy contains a cycle: {0:?}")]
+    Cycle(Vec<Role>),
+}
+
+/// A hierarchy in which a role inherits every role below it. Roles may have several parents and
+/// several children, but the hierarchy must not contain cycles, which is checked when it is built.
+#[derive(Debug, Clone)]
+pub struct RoleHierarchy<Role> {
+    inherited: HashMap<Role, HashSet<Role>>,
+}
+
+impl<Role> RoleHierarchy<Role>
+where
+    Role: Hash + Eq + Clone + Debug,
+{
+    pub fn builder() -> RoleHierarchyBuilder<Role> {
+        RoleHierarchyBuilder {
+            inherits: HashMap::new(),
+        }
+    }
+
+    /// Returns every role inherited by the given role, directly or indirectly, not including the
+    /// role itself.
+    pub fn inherited_roles(&self, role: &Role) -> HashSet<Role> {
+        self.inherited.get(role).cloned().unwrap_or_default()
+    }
+}
+
+impl<Role> Hierarchy<Role> for RoleHierarchy<Role>
+where
+    Role: Hash + Eq + Clone + Debug,
+{
+    fn expand(&self, roles: HashSet<Role>) -> HashSet<Role> {
+        let mut expanded = roles.clone();
+
+        for role in &roles {
+            if let Some(inherited) = self.inherited.get(role) {
+                expanded.extend(inherited.iter().cloned());
+            }
+        }
+
+        expanded
+    }
+}
+
+pub struct RoleHierarchyBuilder<Role> {
+    inherits: HashMap<Role, HashSet<Role>>,
+}
+
+impl<Role> RoleHierarchyBuilder<Role>
+where
+    Role: Hash + Eq + Clone + Debug,
+{
+    /// Declares that `senior` inherits everything granted to `junior`.
+    pub fn inherit(mut self, senior: Role, junior: Role) -> Self {
+        self.inherits.entry(senior).or_default().insert(junior);
+        self
+    }
+
+    pub fn build(self) -> Result<RoleHierarchy<Role>, HierarchyError<Role>> {
+        let mut visited = HashSet::new();
+
+        for role in self.inherits.keys() {
+            self.check_cycles(role, &mut Vec::new(), &mut visited)?;
+        }
+
+        let inherited = self
+            .inherits
+            .keys()
+            .map(|role| (role.clone(), self.collect_inherited(role)))
+            .collect();
+
+        Ok(RoleHierarchy { inherited })
+    }
+
+    fn check_cycles(
+        &self,
+        role: &Role,
+        path: &mut Vec<Role>,
+        visited: &mut HashSet<Role>,
+    ) -> Result<(), HierarchyError<Role>> {
+        if let Some(start) = path.iter().position(|r| r == role) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(role.clone());
+
+            return Err(HierarchyError::Cycle(cycle));
+        }
+
+        if visited.contains(role) {
+            return Ok(());
+        }
+
+        path.push(role.clone());
+
+        for junior in self.inherits.get(role).into_iter().flatten() {
+            self.check_cycles(junior, path, visited)?;
+        }
+
+        path.pop();
+        visited.insert(role.clone());
+
+        Ok(())
+    }
+
+    fn collect_inherited(&self, role: &Role) -> HashSet<Role> {
+        let mut inherited = HashSet::new();
+        let mut pending: Vec<_> = self.inherits.get(role).into_iter().flatten().collect();
+
+        while let Some(junior) = pending.pop() {
+            if inherited.insert(junior.clone()) {
+                pending.extend(self.inherits.get(junior).into_iter().flatten());
+            }
+        }
+
+        inherited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Admin,
+        Editor,
+        Commenter,
+        Viewer,
+        Guest,
+    }
+
+    #[test]
+    fn expand_through_multiple_parents() {
+        let hierarchy = RoleHierarchy::builder()
+            .inherit(Role::Admin, Role::Editor)
+            .inherit(Role::Admin, Role::Commenter)
+            .inherit(Role::Editor, Role::Viewer)
+            .inherit(Role::Commenter, Role::Viewer)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            hierarchy.expand(HashSet::from([Role::Admin])),
+            HashSet::from([Role::Admin, Role::Editor, Role::Commenter, Role::Viewer])
+        );
+        assert_eq!(
+            hierarchy.expand(HashSet::from([Role::Commenter, Role::Guest])),
+            HashSet::from([Role::Commenter, Role::Viewer, Role::Guest])
+        );
+        assert!(hierarchy.inherited_roles(&Role::Viewer).is_empty());
+    }
+
+    #[test]
+    fn reject_cycles() {
+        let result = RoleHierarchy::builder()
+            .inherit(Role::Admin, Role::Editor)
+            .inherit(Role::Editor, Role::Viewer)
+            .inherit(Role::Viewer, Role::Admin)
+            .build();
+
+        let Err(HierarchyError::Cycle(cycle)) = result else {
+            panic!("expected a cycle to be detected");
+        };
+
+        assert_eq!(cycle.first(), cycle.last());
+        assert_eq!(cycle.len(), 4);
+    }
+}
@@ -1,17 +1,30 @@
-use crate::{Error, Policy, Resource, Subject};
+use crate::decision::format_roles;
+use crate::{Decision, Policy, Resource, Subject, SubjectDecider};
 use std::collections::HashSet;
+use std::fmt::Debug;
 use std::hash::Hash;
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
+#[cfg(feature = "config")]
 pub mod config;
+mod grant;
+mod hierarchy;
+mod permission;
+mod query;
+mod tenant;
 
-// pub struct Permission<Act, Res> {
-//     action: Act,
-//     resource: Res,
-// }
-//
-// pub struct Role {
-//     permissions: Vec<Permission>,
-// }
+#[cfg(feature = "derive")]
+pub use author_derive::{GlobalRbacSubject, RbacAction, RbacResource};
+pub use grant::{
+    Clock, GrantedRbacPolicy, GrantedRbacSubject, ManualClock, RoleGrant, SystemClock,
+};
+pub use hierarchy::{FlatRoles, Hierarchy, HierarchyError, RoleHierarchy, RoleHierarchyBuilder};
+pub use permission::{
+    Permission, PermissionParseError, PermissionRbacPolicy, PermissionResource, Role,
+};
+pub use query::{RbacQueryResource, RbacQuerySubject};
+pub use tenant::{TenantRbacPolicy, TenantRbacSubject, TenantResource};
 
 pub trait GlobalRbacSubject: Subject {
     type GlobalRole: Hash + Eq;
@@ -21,11 +34,80 @@ pub trait GlobalRbacSubject: Subject {
     }
 }
 
-pub trait RbacSubject: Subject {
-    fn resource_roles<Res>(&self, resource: &Res) -> HashSet<Res::Role>
-    where
-        Res: RbacResourceWithRole,
-    {
+/// A subject with a hypothetical set of global roles, for previewing what a change to their
+/// roles would allow before making it:
+///
+/// ```ignore
+/// let promoted = RoleOverride::of(&user).grant(Role::Manager);
+/// let newly_permitted = policy.permitted_actions(&invoice, &promoted);
+/// ```
+pub struct RoleOverride<'a, Subj>
+where
+    Subj: GlobalRbacSubject,
+{
+    subject: &'a Subj,
+    roles: HashSet<Subj::GlobalRole>,
+}
+
+impl<'a, Subj> RoleOverride<'a, Subj>
+where
+    Subj: GlobalRbacSubject,
+{
+    /// Starts from the roles the subject currently holds.
+    pub fn of(subject: &'a Subj) -> Self {
+        RoleOverride {
+            subject,
+            roles: subject.global_roles(),
+        }
+    }
+
+    /// Replaces the subject's roles entirely.
+    pub fn with_roles(
+        subject: &'a Subj,
+        roles: impl IntoIterator<Item = Subj::GlobalRole>,
+    ) -> Self {
+        RoleOverride {
+            subject,
+            roles: roles.into_iter().collect(),
+        }
+    }
+
+    pub fn grant(mut self, role: Subj::GlobalRole) -> Self {
+        self.roles.insert(role);
+        self
+    }
+
+    pub fn revoke(mut self, role: &Subj::GlobalRole) -> Self {
+        self.roles.remove(role);
+        self
+    }
+
+    pub fn subject(&self) -> &'a Subj {
+        self.subject
+    }
+}
+
+impl<Subj> Subject for RoleOverride<'_, Subj> where Subj: GlobalRbacSubject {}
+
+impl<Subj> GlobalRbacSubject for RoleOverride<'_, Subj>
+where
+    Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Clone,
+{
+    type GlobalRole = Subj::GlobalRole;
+
+    fn global_roles(&self) -> HashSet<Self::GlobalRole> {
+        self.roles.clone()
+    }
+}
+
+/// A subject that can hold roles on individual resource instances, such as being the owner of one
+/// particular document but only a viewer of another.
+pub trait RbacSubject<Res>: Subject
+where
+    Res: RbacResourceWithRole,
+{
+    fn resource_roles(&self, _resource: &Res) -> HashSet<Res::Role> {
         HashSet::new()
     }
 }
@@ -35,110 +117,349 @@ pub trait RbacResourceWithRole: RbacResource<Self::Role> {
 }
 
 pub trait RbacResource<Role>: Resource {
-    fn allowed_roles(&self, action: &Self::Action) -> HashSet<Role> {
+    fn allowed_roles(&self, _action: &Self::Action) -> HashSet<Role> {
         HashSet::new()
     }
 }
 
-pub struct GlobalRbacPolicy {}
+/// An action that is allowed to the same roles whatever resource it is performed on, which
+/// resources can defer to when implementing [`RbacResource`].
+pub trait RbacAction<Role> {
+    fn allowed_roles(&self) -> HashSet<Role>;
+}
+
+#[derive(Default)]
+pub struct GlobalRbacPolicy<H = FlatRoles> {
+    hierarchy: H,
+}
 
 impl GlobalRbacPolicy {
     pub fn new() -> Self {
-        GlobalRbacPolicy {}
+        GlobalRbacPolicy {
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<H> GlobalRbacPolicy<H> {
+    /// Creates a policy in which subjects are also granted every role inherited from the global
+    /// roles they hold.
+    pub fn with_hierarchy(hierarchy: H) -> Self {
+        GlobalRbacPolicy { hierarchy }
     }
 }
 
-impl<Res, Subj> Policy<Res, Subj> for GlobalRbacPolicy
+impl<Res, Subj, H> Policy<Res, Subj> for GlobalRbacPolicy<H>
 where
     Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Debug,
     Res: RbacResource<Subj::GlobalRole>,
+    H: Hierarchy<Subj::GlobalRole>,
 {
-    fn authorise(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Result<(), Error> {
-        let subject_global_roles = subject.global_roles();
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let subject_global_roles = self.hierarchy.expand(subject.global_roles());
         let allowed_global_roles = RbacResource::allowed_roles(resource, action);
 
-        let matching_roles: HashSet<_> = allowed_global_roles
-            .intersection(&subject_global_roles)
+        decide_on_roles("global", &subject_global_roles, &allowed_global_roles)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let subject_global_roles = self.hierarchy.expand(subject.global_roles());
+
+        Box::new(move |resource, action| {
+            let allowed_global_roles = RbacResource::allowed_roles(resource, action);
+
+            decide_on_roles("global", &subject_global_roles, &allowed_global_roles)
+        })
+    }
+}
+
+/// Policy that grants access based on the roles a subject holds on the specific resource instance
+/// being accessed, as returned by [`RbacSubject::resource_roles`], rather than on any global roles.
+#[derive(Default)]
+pub struct ResourceRbacPolicy<H = FlatRoles> {
+    hierarchy: H,
+}
+
+impl ResourceRbacPolicy {
+    pub fn new() -> Self {
+        ResourceRbacPolicy {
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<H> ResourceRbacPolicy<H> {
+    /// Creates a policy in which subjects are also granted every resource role inherited from the
+    /// roles they hold on the resource.
+    pub fn with_hierarchy(hierarchy: H) -> Self {
+        ResourceRbacPolicy { hierarchy }
+    }
+}
+
+impl<Res, Subj, H> Policy<Res, Subj> for ResourceRbacPolicy<H>
+where
+    Subj: RbacSubject<Res>,
+    Res: RbacResourceWithRole,
+    Res::Role: Debug,
+    H: Hierarchy<Res::Role>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let subject_resource_roles = self.hierarchy.expand(subject.resource_roles(resource));
+        let allowed_resource_roles = RbacResource::<Res::Role>::allowed_roles(resource, action);
+
+        decide_on_roles("resource", &subject_resource_roles, &allowed_resource_roles)
+    }
+}
+
+/// Permits if the subject holds at least one of the allowed roles, naming the matching roles in
+/// the decision so it is clear which grant was used.
+fn decide_on_roles<Role>(kind: &str, held: &HashSet<Role>, allowed: &HashSet<Role>) -> Decision
+where
+    Role: Hash + Eq + Debug,
+{
+    let matching_roles: HashSet<_> = allowed.intersection(held).collect();
+
+    if matching_roles.is_empty() {
+        return Decision::deny(format!(
+            "Subject holds none of the {} roles allowed to perform this action; allowed roles are {}",
+            kind,
+            format_roles(allowed),
+        ));
+    }
+
+    let matching_roles = format_roles(matching_roles);
+
+    Decision::permit(format!(
+        "Subject holds {} roles {} which are allowed to perform this action",
+        kind, matching_roles,
+    ))
+    .with_rule(matching_roles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuthorisedIterator;
+    use std::cell::Cell;
+
+    struct Product {
+        restricted: bool,
+    }
+
+    impl Resource for Product {
+        type Action = ();
+    }
+
+    impl RbacResource<Role> for Product {
+        fn allowed_roles(&self, _action: &()) -> HashSet<Role> {
+            if self.restricted {
+                HashSet::from([Role::Admin])
+            } else {
+                HashSet::from([Role::Admin, Role::Customer])
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Admin,
+        Customer,
+    }
+
+    struct Customer {
+        role_lookups: Cell<usize>,
+    }
+
+    impl Subject for Customer {}
+
+    impl GlobalRbacSubject for Customer {
+        type GlobalRole = Role;
+
+        fn global_roles(&self) -> HashSet<Role> {
+            self.role_lookups.set(self.role_lookups.get() + 1);
+            HashSet::from([Role::Customer])
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    enum ProductAction {
+        View,
+        Edit,
+    }
+
+    impl crate::Enumerable for ProductAction {
+        fn all() -> Vec<Self> {
+            vec![ProductAction::View, ProductAction::Edit]
+        }
+    }
+
+    struct Catalogue;
+
+    impl Resource for Catalogue {
+        type Action = ProductAction;
+    }
+
+    impl RbacResource<Role> for Catalogue {
+        fn allowed_roles(&self, action: &ProductAction) -> HashSet<Role> {
+            match action {
+                ProductAction::View => HashSet::from([Role::Admin, Role::Customer]),
+                ProductAction::Edit => HashSet::from([Role::Admin]),
+            }
+        }
+    }
+
+    #[test]
+    fn permitted_actions_with_role_override() {
+        let policy = GlobalRbacPolicy::new();
+        let customer = Customer {
+            role_lookups: Cell::new(0),
+        };
+
+        let explained = policy.explain_actions(&Catalogue, &customer);
+        assert_eq!(explained.len(), 2);
+        assert!(explained[0].1.is_permit());
+        assert_eq!(
+            explained[1].1.reason(),
+            "Subject holds none of the global roles allowed to perform this action; allowed roles are [Admin]"
+        );
+
+        assert_eq!(
+            policy.permitted_actions(&Catalogue, &customer),
+            vec![ProductAction::View]
+        );
+
+        let promoted = RoleOverride::of(&customer).grant(Role::Admin);
+        assert_eq!(
+            policy.permitted_actions(&Catalogue, &promoted),
+            vec![ProductAction::View, ProductAction::Edit]
+        );
+
+        let revoked = RoleOverride::of(&customer).revoke(&Role::Customer);
+        assert!(policy.permitted_actions(&Catalogue, &revoked).is_empty());
+    }
+
+    #[test]
+    fn load_roles_once_per_batch() {
+        let products: Vec<_> = (0..10)
+            .map(|i| Product {
+                restricted: i % 2 == 0,
+            })
             .collect();
+        let customer = Customer {
+            role_lookups: Cell::new(0),
+        };
+
+        let visible = products
+            .iter()
+            .filter_authorised(&GlobalRbacPolicy::new(), &customer, &())
+            .count();
+
+        assert_eq!(visible, 5);
+        assert_eq!(customer.role_lookups.get(), 1);
+    }
+
+    mod derive {
+        use crate::rbac::{GlobalRbacPolicy, RbacResource};
+        use crate::{Enumerable, Named, Policy};
+        use std::collections::HashSet;
 
-        if matching_roles.len() == 0 {
-            return Err(Error::Forbidden);
+        #[derive(author_derive::Subject, author_derive::GlobalRbacSubject)]
+        #[author(role = Role)]
+        struct User(#[author(global_roles)] Vec<Role>);
+
+        #[derive(author_derive::Resource, author_derive::RbacResource)]
+        #[author(action = Action, role = Role, name = "invoice")]
+        #[author(allow(Read, roles = [Clerk, Auditor]))]
+        #[author(allow(Approve, Void, roles = [Manager]))]
+        struct Invoice;
+
+        // Actions named in several groups are allowed to the roles of all of them
+        #[derive(author_derive::Resource, author_derive::RbacResource)]
+        #[author(action = Action, role = Role)]
+        #[author(allow(Read, roles = [Clerk]))]
+        #[author(allow(Read, Approve, Void, Archive, roles = [Manager, Clerk]))]
+        struct Ledger;
+
+        #[derive(PartialEq, Eq, Hash, Debug, author_derive::Named, author_derive::Enumerable)]
+        enum Action {
+            Read,
+            Approve,
+            #[author(name = "cancel")]
+            Void,
+            Archive,
+        }
+
+        #[derive(PartialEq, Eq, Hash, Clone, Debug)]
+        enum Role {
+            Clerk,
+            Auditor,
+            Manager,
+        }
+
+        // Without any `allow`, the roles come from the action
+        #[derive(author_derive::Resource, author_derive::RbacResource)]
+        #[author(action = Payment, role = Role)]
+        struct Account;
+
+        #[derive(PartialEq, Eq, Hash, author_derive::RbacAction)]
+        #[author(role = Role)]
+        enum Payment {
+            #[author(roles = [Clerk, Manager])]
+            Request {
+                amount: u32,
+            },
+            #[author(roles = [Manager])]
+            #[author(roles = [Manager, Auditor])]
+            Refund(u32),
+            Cancel,
         }
 
-        Ok(())
-    }
-}
-
-// pub struct ResourceRbacPolicy<Res, Role, Act>
-// where
-//     Res: Resource, {}
-//
-// impl<Res, Subj, Act> Policy<Res, Subj> for ResourceRbacPolicy<Res, Subj, Act> {}
-
-// pub struct RbacPolicy<Res, Subj, Act>
-// where
-//     Res: Object,
-//     Subj: Subject,
-// {
-//     config: RbacPolicyConfig<Res::Identifier, Subj::Role, Act>,
-// }
-//
-// impl<Res, Subj, Act> RbacPolicy<Res, Subj, Act>
-// where
-//     Res: Object,
-//     Subj: Subject,
-// {
-//     pub fn new() -> Self {
-//         RbacPolicy {
-//             config: RbacPolicyConfig::new(),
-//         }
-//     }
-// }
-//
-// impl<Res, Subj, Act> Policy for RbacPolicy<Res, Subj, Act>
-// where
-//     Res: Object,
-//     <Res as Object>::Identifier: Hash + Eq,
-//     Subj: Subject,
-//     <Subj as Subject>::Role: Hash + Eq,
-//     Act: Hash + Eq,
-// {
-//     fn authorise<Res, Subj>(
-//         &self,
-//         resource: &Res,
-//         subject: &Subj,
-//         action: &Res::Action,
-//     ) -> Result<(), Error> {
-//         let resource_id = resource.identifier();
-//         let subject_roles = subject.roles();
-//
-//         let allowed_roles = self
-//             .config
-//             .allowed
-//             .get(&resource_id)
-//             .and_then(|a| a.get(&action));
-//
-//         let allowed_roles = match allowed_roles {
-//             Some(a) => a,
-//             None => {
-//                 return Err(Error::Forbidden);
-//             }
-//         };
-//
-//         let matching_roles: HashSet<_> = allowed_roles.intersection(&subject_roles).collect();
-//
-//         if matching_roles.len() == 0 {
-//             return Err(Error::Forbidden);
-//         }
-//
-//         Ok(())
-//     }
-// }
-
-// impl<R1, R2, Subject, A1, A2> Policy<Resource, Subject, Action>
-//     for RbacPolicy<Resource, Subject, Action>
-// {
-//     fn authorise(resource: Resource, subject: Subject, action: Action) -> Result<Resource, Error> {
-//         todo!()
-//     }
-// }
+        #[test]
+        fn derived_traits() {
+            assert_eq!(<Invoice as crate::NamedResource>::RESOURCE_NAME, "invoice");
+            assert_eq!(
+                Action::all().iter().map(Named::name).collect::<Vec<_>>(),
+                vec!["read", "approve", "cancel", "archive"]
+            );
+            assert_eq!(
+                Invoice.allowed_roles(&Action::Read),
+                HashSet::from([Role::Clerk, Role::Auditor])
+            );
+            assert!(Invoice.allowed_roles(&Action::Archive).is_empty());
+
+            let policy = GlobalRbacPolicy::new();
+            let clerk = User(vec![Role::Clerk]);
+            let manager = User(vec![Role::Manager]);
+
+            assert!(policy.decide(&Invoice, &clerk, &Action::Read).is_permit());
+            assert!(policy.decide(&Invoice, &clerk, &Action::Void).is_deny());
+            assert!(policy.decide(&Invoice, &manager, &Action::Void).is_permit());
+
+            assert_eq!(
+                Ledger.allowed_roles(&Action::Read),
+                HashSet::from([Role::Clerk, Role::Manager])
+            );
+            assert!(policy.decide(&Ledger, &manager, &Action::Read).is_permit());
+
+            assert_eq!(
+                Account.allowed_roles(&Payment::Request { amount: 10 }),
+                HashSet::from([Role::Clerk, Role::Manager])
+            );
+            assert_eq!(
+                Account.allowed_roles(&Payment::Refund(10)),
+                HashSet::from([Role::Manager, Role::Auditor])
+            );
+            assert!(Account.allowed_roles(&Payment::Cancel).is_empty());
+            assert!(policy
+                .decide(&Account, &clerk, &Payment::Request { amount: 10 })
+                .is_permit());
+            assert!(policy
+                .decide(&Account, &clerk, &Payment::Refund(10))
+                .is_deny());
+        }
+    }
+}
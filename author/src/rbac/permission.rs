@@ -0,0 +1,397 @@
+use crate::rbac::{FlatRoles, GlobalRbacSubject, Hierarchy};
+use crate::{Decision, Named, NamedResource, Policy, SubjectDecider};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+const WILDCARD: &str = "*";
+
+/// Permission to perform an action on a type of resource, written `resource:action`. Either part
+/// may be `*`, so `product:*` grants every action on products and `*:read` grants reading
+/// anything.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Permission {
+    resource: String,
+    action: String,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid permission '{0}', expected 'resource:action'")]
+pub struct PermissionParseError(String);
+
+impl Permission {
+    pub fn new(resource: impl Into<String>, action: impl Into<String>) -> Self {
+        Permission {
+            resource: resource.into(),
+            action: action.into(),
+        }
+    }
+
+    /// The permission needed to perform the given action on resources of type `Res`.
+    pub fn of<Res>(action: &Res::Action) -> Self
+    where
+        Res: NamedResource<Action: Named> + ?Sized,
+    {
+        Permission::new(Res::RESOURCE_NAME, action.name())
+    }
+
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Whether holding this permission also grants `other`, taking wildcards into account.
+    pub fn implies(&self, other: &Permission) -> bool {
+        fn matches(pattern: &str, value: &str) -> bool {
+            pattern == WILDCARD || pattern == value
+        }
+
+        matches(&self.resource, &other.resource) && matches(&self.action, &other.action)
+    }
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.resource, self.action)
+    }
+}
+
+impl FromStr for Permission {
+    type Err = PermissionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((resource, action))
+                if !resource.is_empty() && !action.is_empty() && !action.contains(':') =>
+            {
+                Ok(Permission::new(resource, action))
+            }
+            _ => Err(PermissionParseError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+impl serde::Serialize for Permission {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "config")]
+impl<'de> serde::Deserialize<'de> for Permission {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let permission = String::deserialize(deserializer)?;
+        permission.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A named bundle of permissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Role {
+    name: String,
+    permissions: HashSet<Permission>,
+}
+
+impl Role {
+    pub fn new(name: impl Into<String>) -> Self {
+        Role {
+            name: name.into(),
+            permissions: HashSet::new(),
+        }
+    }
+
+    pub fn with_permission(mut self, permission: Permission) -> Self {
+        self.permissions.insert(permission);
+        self
+    }
+
+    /// Adds permissions written as `resource:action` strings.
+    pub fn with_permissions<I, P>(mut self, permissions: I) -> Result<Self, PermissionParseError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        for permission in permissions {
+            self.permissions.insert(permission.as_ref().parse()?);
+        }
+
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn permissions(&self) -> &HashSet<Permission> {
+        &self.permissions
+    }
+
+    /// Returns the permission held by this role that grants `required`, if any.
+    pub fn grant(&self, required: &Permission) -> Option<&Permission> {
+        self.permissions.iter().find(|p| p.implies(required))
+    }
+}
+
+/// A resource that requires permissions, rather than particular roles, to act on. By default an
+/// action requires the single permission `resource:action`.
+pub trait PermissionResource: NamedResource<Action: Named> {
+    fn required_permissions(&self, action: &Self::Action) -> Vec<Permission> {
+        vec![Permission::of::<Self>(action)]
+    }
+}
+
+/// Policy that permits an action if the subject's global roles, matched to [`Role`]s by name,
+/// between them hold every permission the resource requires for it.
+pub struct PermissionRbacPolicy<H = FlatRoles> {
+    roles: HashMap<String, Role>,
+    hierarchy: H,
+}
+
+impl PermissionRbacPolicy {
+    pub fn new(roles: impl IntoIterator<Item = Role>) -> Self {
+        PermissionRbacPolicy::with_hierarchy(roles, FlatRoles)
+    }
+}
+
+impl<H> PermissionRbacPolicy<H> {
+    /// Creates a policy in which subjects also hold the permissions of every role inherited from
+    /// their global roles.
+    pub fn with_hierarchy(roles: impl IntoIterator<Item = Role>, hierarchy: H) -> Self {
+        PermissionRbacPolicy {
+            roles: roles.into_iter().map(|r| (r.name.clone(), r)).collect(),
+            hierarchy,
+        }
+    }
+
+    pub fn role(&self, name: &str) -> Option<&Role> {
+        self.roles.get(name)
+    }
+}
+
+impl<Res, Subj, H> Policy<Res, Subj> for PermissionRbacPolicy<H>
+where
+    Res: PermissionResource,
+    Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Named,
+    H: Hierarchy<String>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        decide_on_permissions(
+            &self.subject_roles(subject),
+            resource.required_permissions(action),
+        )
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let subject_roles = self.subject_roles(subject);
+
+        Box::new(move |resource, action| {
+            decide_on_permissions(&subject_roles, resource.required_permissions(action))
+        })
+    }
+}
+
+impl<H> PermissionRbacPolicy<H>
+where
+    H: Hierarchy<String>,
+{
+    /// Returns the roles held by the subject, including inherited ones, in order of name.
+    fn subject_roles<Subj>(&self, subject: &Subj) -> Vec<&Role>
+    where
+        Subj: GlobalRbacSubject,
+        Subj::GlobalRole: Named,
+    {
+        let subject_roles = self.hierarchy.expand(
+            subject
+                .global_roles()
+                .iter()
+                .map(|r| r.name().to_string())
+                .collect(),
+        );
+
+        let mut subject_roles: Vec<_> = subject_roles
+            .iter()
+            .filter_map(|name| self.roles.get(name))
+            .collect();
+        subject_roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        subject_roles
+    }
+}
+
+fn decide_on_permissions(
+    subject_roles: &[&Role],
+    required_permissions: Vec<Permission>,
+) -> Decision {
+    if required_permissions.is_empty() {
+        return Decision::not_applicable("No permissions are required for this action");
+    }
+
+    let mut grants = Vec::new();
+    let mut missing = Vec::new();
+
+    for required in &required_permissions {
+        let grant = subject_roles
+            .iter()
+            .find_map(|role| role.grant(required).map(|p| (role.name(), p)));
+
+        match grant {
+            Some((role, permission)) => grants.push(format!("{} grants {}", role, permission)),
+            None => missing.push(required.to_string()),
+        }
+    }
+
+    if !missing.is_empty() {
+        return Decision::deny(format!(
+            "Subject's roles do not grant the required permissions [{}]",
+            missing.join(", ")
+        ));
+    }
+
+    Decision::permit(format!(
+        "Subject's roles grant all required permissions: {}",
+        grants.join(", ")
+    ))
+    .with_rule(grants.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Resource, Subject};
+
+    #[test]
+    fn wildcards() {
+        let read_customer = Permission::new("customer", "read");
+
+        assert!(Permission::new("customer", "read").implies(&read_customer));
+        assert!(Permission::new("customer", "*").implies(&read_customer));
+        assert!(Permission::new("*", "read").implies(&read_customer));
+        assert!(Permission::new("*", "*").implies(&read_customer));
+        assert!(!Permission::new("product", "*").implies(&read_customer));
+        assert!(!Permission::new("customer", "write").implies(&read_customer));
+        assert!(!read_customer.implies(&Permission::new("customer", "*")));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("product:*".parse(), Ok(Permission::new("product", "*")));
+        assert!("product".parse::<Permission>().is_err());
+        assert!("product:".parse::<Permission>().is_err());
+        assert!("a:b:c".parse::<Permission>().is_err());
+    }
+
+    struct Customer;
+
+    impl Resource for Customer {
+        type Action = CustomerAction;
+    }
+
+    impl NamedResource for Customer {
+        const RESOURCE_NAME: &'static str = "customer";
+    }
+
+    impl PermissionResource for Customer {}
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum CustomerAction {
+        Read,
+        Merge,
+    }
+
+    impl Named for CustomerAction {
+        fn name(&self) -> &str {
+            match self {
+                CustomerAction::Read => "read",
+                CustomerAction::Merge => "merge",
+            }
+        }
+    }
+
+    struct Invoice;
+
+    impl Resource for Invoice {
+        type Action = CustomerAction;
+    }
+
+    impl NamedResource for Invoice {
+        const RESOURCE_NAME: &'static str = "invoice";
+    }
+
+    impl PermissionResource for Invoice {
+        fn required_permissions(&self, action: &CustomerAction) -> Vec<Permission> {
+            // Touching an invoice also needs the same access to the customer it belongs to
+            vec![
+                Permission::of::<Self>(action),
+                Permission::of::<Customer>(action),
+            ]
+        }
+    }
+
+    struct User(Vec<&'static str>);
+
+    impl Subject for User {}
+
+    impl GlobalRbacSubject for User {
+        type GlobalRole = RoleName;
+
+        fn global_roles(&self) -> HashSet<RoleName> {
+            self.0.iter().map(|r| RoleName(r)).collect()
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    struct RoleName(&'static str);
+
+    impl Named for RoleName {
+        fn name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn authorise_with_permissions() {
+        let policy = PermissionRbacPolicy::new([
+            Role::new("support")
+                .with_permissions(["customer:read", "invoice:read"])
+                .unwrap(),
+            Role::new("billing")
+                .with_permissions(["invoice:*"])
+                .unwrap(),
+            Role::new("admin").with_permissions(["*:*"]).unwrap(),
+        ]);
+
+        let support = User(vec!["support"]);
+        let billing = User(vec!["billing"]);
+        let admin = User(vec!["admin"]);
+
+        let decision = policy.decide(&Customer, &support, &CustomerAction::Read);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("support grants customer:read"));
+
+        assert!(policy
+            .decide(&Customer, &support, &CustomerAction::Merge)
+            .is_deny());
+        assert!(policy
+            .decide(&Invoice, &billing, &CustomerAction::Read)
+            .is_deny());
+        assert!(policy
+            .decide(
+                &Invoice,
+                &User(vec!["billing", "support"]),
+                &CustomerAction::Read
+            )
+            .is_permit());
+        assert!(policy
+            .decide(&Invoice, &admin, &CustomerAction::Merge)
+            .is_permit());
+    }
+}
@@ -0,0 +1,240 @@
+//! Translating role-based policies into query predicates.
+//!
+//! Global roles don't depend on the resource, so a [`GlobalRbacPolicy`] either permits every row
+//! or none of them. Roles held on individual resources are usually recorded in the resource's own
+//! table, such as an owner column or the team a resource belongs to, so a [`RbacQuerySubject`]
+//! describes where it holds each role as a [`Predicate`] and a [`ResourceRbacPolicy`] permits the
+//! rows where it holds any of the allowed roles.
+
+use crate::query::{Predicate, QueryError, QueryPolicy, QueryResource, Residual};
+use crate::rbac::{
+    decide_on_roles, GlobalRbacPolicy, GlobalRbacSubject, Hierarchy, RbacResource,
+    RbacResourceWithRole, RbacSubject, ResourceRbacPolicy,
+};
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+/// A resource whose allowed roles are the same for every instance, so that they are known before
+/// any resources are loaded.
+pub trait RbacQueryResource<Role>: RbacResource<Role> + QueryResource {
+    fn allowed_roles_for(action: &Self::Action) -> HashSet<Role>;
+}
+
+/// A subject whose roles on resources can be read from the resources' table.
+pub trait RbacQuerySubject<Res>: RbacSubject<Res>
+where
+    Res: RbacResourceWithRole + QueryResource,
+{
+    /// The roles the subject may hold on resources, each with the condition on the resource's
+    /// columns under which they hold it, such as `(Role::Owner, owner_id = 7)` or
+    /// `(Role::Member, team_id IN (1, 2))`.
+    fn resource_role_predicates(&self) -> Vec<(Res::Role, Predicate)>;
+}
+
+impl<Res, Subj, H> QueryPolicy<Res, Subj> for GlobalRbacPolicy<H>
+where
+    Subj: GlobalRbacSubject,
+    Subj::GlobalRole: Debug,
+    Res: RbacQueryResource<Subj::GlobalRole>,
+    H: Hierarchy<Subj::GlobalRole>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let subject_global_roles = self.hierarchy.expand(subject.global_roles());
+        let allowed_global_roles = Res::allowed_roles_for(action);
+
+        Ok(Residual::from_decision(&decide_on_roles(
+            "global",
+            &subject_global_roles,
+            &allowed_global_roles,
+        )))
+    }
+}
+
+impl<Res, Subj, H> QueryPolicy<Res, Subj> for ResourceRbacPolicy<H>
+where
+    Subj: RbacQuerySubject<Res>,
+    Res: RbacResourceWithRole + RbacQueryResource<<Res as RbacResourceWithRole>::Role>,
+    H: Hierarchy<Res::Role>,
+{
+    fn residual(&self, subject: &Subj, action: &Res::Action) -> Result<Residual, QueryError> {
+        let allowed_resource_roles = Res::allowed_roles_for(action);
+
+        // A role grants access wherever it, or any role it inherits, is allowed
+        let permit = Predicate::any(subject.resource_role_predicates().into_iter().filter_map(
+            |(role, predicate)| {
+                let roles = self.hierarchy.expand(HashSet::from([role]));
+
+                (!roles.is_disjoint(&allowed_resource_roles)).then_some(predicate)
+            },
+        ));
+
+        Ok(Residual::permit_or_deny(permit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinator::AnyOf;
+    use crate::rbac::RoleHierarchy;
+    use crate::{Resource, Subject};
+
+    struct Project;
+
+    impl Resource for Project {
+        type Action = ProjectAction;
+    }
+
+    impl QueryResource for Project {}
+
+    impl RbacResourceWithRole for Project {
+        type Role = ProjectRole;
+    }
+
+    impl RbacResource<ProjectRole> for Project {
+        fn allowed_roles(&self, action: &ProjectAction) -> HashSet<ProjectRole> {
+            <Project as RbacQueryResource<ProjectRole>>::allowed_roles_for(action)
+        }
+    }
+
+    impl RbacQueryResource<ProjectRole> for Project {
+        fn allowed_roles_for(action: &ProjectAction) -> HashSet<ProjectRole> {
+            match action {
+                ProjectAction::List => HashSet::from([ProjectRole::Member]),
+                ProjectAction::Delete => HashSet::from([ProjectRole::Owner]),
+            }
+        }
+    }
+
+    impl RbacResource<GlobalRole> for Project {
+        fn allowed_roles(&self, action: &ProjectAction) -> HashSet<GlobalRole> {
+            <Project as RbacQueryResource<GlobalRole>>::allowed_roles_for(action)
+        }
+    }
+
+    impl RbacQueryResource<GlobalRole> for Project {
+        fn allowed_roles_for(_action: &ProjectAction) -> HashSet<GlobalRole> {
+            HashSet::from([GlobalRole::Admin])
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum ProjectAction {
+        List,
+        Delete,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum ProjectRole {
+        Owner,
+        Member,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    enum GlobalRole {
+        Admin,
+    }
+
+    struct User {
+        id: i64,
+        teams: Vec<i64>,
+        admin: bool,
+    }
+
+    impl Subject for User {}
+
+    impl GlobalRbacSubject for User {
+        type GlobalRole = GlobalRole;
+
+        fn global_roles(&self) -> HashSet<GlobalRole> {
+            if self.admin {
+                HashSet::from([GlobalRole::Admin])
+            } else {
+                HashSet::new()
+            }
+        }
+    }
+
+    impl RbacSubject<Project> for User {}
+
+    impl RbacQuerySubject<Project> for User {
+        fn resource_role_predicates(&self) -> Vec<(ProjectRole, Predicate)> {
+            vec![
+                (ProjectRole::Owner, Predicate::eq("owner_id", self.id)),
+                (
+                    ProjectRole::Member,
+                    Predicate::is_in("team_id", self.teams.clone()),
+                ),
+            ]
+        }
+    }
+
+    fn user(admin: bool) -> User {
+        User {
+            id: 7,
+            teams: vec![1, 2],
+            admin,
+        }
+    }
+
+    #[test]
+    fn query_resource_roles() {
+        let policy = ResourceRbacPolicy::new();
+
+        assert_eq!(
+            QueryPolicy::<Project, _>::query(&policy, &user(false), &ProjectAction::List)
+                .unwrap()
+                .to_string(),
+            "team_id IN (1, 2)"
+        );
+        assert_eq!(
+            QueryPolicy::<Project, _>::query(&policy, &user(false), &ProjectAction::Delete)
+                .unwrap()
+                .to_string(),
+            "owner_id = 7"
+        );
+
+        // Owners are also members of their own projects
+        let hierarchy = RoleHierarchy::builder()
+            .inherit(ProjectRole::Owner, ProjectRole::Member)
+            .build()
+            .unwrap();
+        let policy = ResourceRbacPolicy::with_hierarchy(hierarchy);
+
+        assert_eq!(
+            QueryPolicy::<Project, _>::query(&policy, &user(false), &ProjectAction::List)
+                .unwrap()
+                .to_string(),
+            "owner_id = 7 OR team_id IN (1, 2)"
+        );
+    }
+
+    #[test]
+    fn query_global_roles() {
+        let policy = GlobalRbacPolicy::new();
+
+        let residual =
+            QueryPolicy::<Project, _>::residual(&policy, &user(true), &ProjectAction::Delete)
+                .unwrap();
+        assert_eq!(residual, Residual::new(Predicate::True, Predicate::False));
+
+        let residual =
+            QueryPolicy::<Project, _>::residual(&policy, &user(false), &ProjectAction::Delete)
+                .unwrap();
+        assert_eq!(residual, Residual::new(Predicate::False, Predicate::True));
+
+        // Admins can delete any project, and everyone else only their own
+        let policy = AnyOf((GlobalRbacPolicy::new(), ResourceRbacPolicy::new()));
+
+        assert_eq!(
+            QueryPolicy::<Project, _>::query(&policy, &user(true), &ProjectAction::Delete).unwrap(),
+            Predicate::True
+        );
+        assert_eq!(
+            QueryPolicy::<Project, _>::query(&policy, &user(false), &ProjectAction::Delete)
+                .unwrap()
+                .to_string(),
+            "owner_id = 7"
+        );
+    }
+}
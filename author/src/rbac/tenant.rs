@@ -0,0 +1,315 @@
+use crate::decision::format_roles;
+use crate::rbac::{decide_on_roles, FlatRoles, Hierarchy, RbacResource};
+use crate::{Decision, Policy, Resource, Subject, SubjectDecider};
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// A resource that belongs to a single tenant, such as an organisation.
+pub trait TenantResource: Resource {
+    type TenantId: Eq + Debug;
+
+    fn tenant_id(&self) -> Self::TenantId;
+}
+
+/// A subject that holds different roles in each tenant it belongs to, and acts within one of them
+/// at a time.
+pub trait TenantRbacSubject: Subject {
+    type TenantId: Eq + Debug;
+    type TenantRole: Hash + Eq;
+
+    /// The tenant the subject is currently acting in, if any.
+    fn active_tenant(&self) -> Option<Self::TenantId>;
+
+    /// The roles the subject holds in the given tenant.
+    fn tenant_roles(&self, _tenant: &Self::TenantId) -> HashSet<Self::TenantRole> {
+        HashSet::new()
+    }
+
+    /// Roles that apply in every tenant, such as for support staff, and so also allow access to
+    /// the resources of tenants other than the active one.
+    fn cross_tenant_roles(&self) -> HashSet<Self::TenantRole> {
+        HashSet::new()
+    }
+}
+
+/// Policy that grants access based on the roles a subject holds in the tenant a resource belongs
+/// to.
+///
+/// Access to a resource in any tenant other than the subject's active tenant is denied, unless it
+/// is allowed by one of the subject's cross-tenant roles.
+#[derive(Default)]
+pub struct TenantRbacPolicy<H = FlatRoles> {
+    hierarchy: H,
+}
+
+impl TenantRbacPolicy {
+    pub fn new() -> Self {
+        TenantRbacPolicy {
+            hierarchy: FlatRoles,
+        }
+    }
+}
+
+impl<H> TenantRbacPolicy<H> {
+    /// Creates a policy in which subjects are also granted every role inherited from the roles
+    /// they hold.
+    pub fn with_hierarchy(hierarchy: H) -> Self {
+        TenantRbacPolicy { hierarchy }
+    }
+
+    fn subject_roles<Subj>(&self, subject: &Subj) -> SubjectRoles<Subj::TenantId, Subj::TenantRole>
+    where
+        Subj: TenantRbacSubject,
+        H: Hierarchy<Subj::TenantRole>,
+    {
+        let active_tenant = subject.active_tenant();
+        let active_tenant_roles = match &active_tenant {
+            Some(tenant) => {
+                let mut roles = subject.tenant_roles(tenant);
+                roles.extend(subject.cross_tenant_roles());
+
+                self.hierarchy.expand(roles)
+            }
+            None => HashSet::new(),
+        };
+
+        SubjectRoles {
+            active_tenant,
+            active_tenant_roles,
+            cross_tenant_roles: self.hierarchy.expand(subject.cross_tenant_roles()),
+        }
+    }
+}
+
+/// The roles a subject holds, worked out once so that they can be used for many resources.
+struct SubjectRoles<TenantId, Role> {
+    active_tenant: Option<TenantId>,
+    /// The roles held in the active tenant, including cross-tenant roles.
+    active_tenant_roles: HashSet<Role>,
+    cross_tenant_roles: HashSet<Role>,
+}
+
+fn decide_on_tenant_roles<Res, TenantId, Role>(
+    roles: &SubjectRoles<TenantId, Role>,
+    resource: &Res,
+    action: &Res::Action,
+) -> Decision
+where
+    Res: TenantResource<TenantId = TenantId> + RbacResource<Role>,
+    TenantId: Eq + Debug,
+    Role: Hash + Eq + Debug,
+{
+    let resource_tenant = resource.tenant_id();
+    let allowed_roles = RbacResource::allowed_roles(resource, action);
+
+    match &roles.active_tenant {
+        Some(active_tenant) if *active_tenant == resource_tenant => {
+            decide_on_roles("tenant", &roles.active_tenant_roles, &allowed_roles)
+        }
+        active_tenant => {
+            let matching_roles: HashSet<_> = allowed_roles
+                .intersection(&roles.cross_tenant_roles)
+                .collect();
+
+            if matching_roles.is_empty() {
+                let active_tenant = match active_tenant {
+                    Some(tenant) => format!("the active tenant is {:?}", tenant),
+                    None => "there is no active tenant".to_string(),
+                };
+
+                return Decision::deny(format!(
+                    "Resource belongs to tenant {:?} but {}, and subject holds none of the \
+                     cross-tenant roles allowed to perform this action",
+                    resource_tenant, active_tenant
+                ));
+            }
+
+            let matching_roles = format_roles(matching_roles);
+
+            Decision::permit(format!(
+                "Subject holds cross-tenant roles {} which are allowed to perform this action \
+                 in tenant {:?}",
+                matching_roles, resource_tenant
+            ))
+            .with_rule(matching_roles)
+        }
+    }
+}
+
+impl<Res, Subj, H> Policy<Res, Subj> for TenantRbacPolicy<H>
+where
+    Subj: TenantRbacSubject,
+    Subj::TenantRole: Debug,
+    Res: TenantResource<TenantId = Subj::TenantId> + RbacResource<Subj::TenantRole>,
+    H: Hierarchy<Subj::TenantRole>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        decide_on_tenant_roles(&self.subject_roles(subject), resource, action)
+    }
+
+    fn for_subject<'a>(&'a self, subject: &'a Subj) -> SubjectDecider<'a, Res>
+    where
+        Res: 'a,
+    {
+        let roles = self.subject_roles(subject);
+
+        Box::new(move |resource, action| decide_on_tenant_roles(&roles, resource, action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuthorisedIterator;
+    use std::cell::Cell;
+    use std::collections::HashMap;
+
+    struct Project {
+        organisation: u32,
+    }
+
+    impl Resource for Project {
+        type Action = ProjectAction;
+    }
+
+    impl TenantResource for Project {
+        type TenantId = u32;
+
+        fn tenant_id(&self) -> u32 {
+            self.organisation
+        }
+    }
+
+    impl RbacResource<Role> for Project {
+        fn allowed_roles(&self, action: &ProjectAction) -> HashSet<Role> {
+            match action {
+                ProjectAction::View => HashSet::from([Role::Member, Role::Admin, Role::Support]),
+                ProjectAction::Delete => HashSet::from([Role::Admin]),
+            }
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash)]
+    enum ProjectAction {
+        View,
+        Delete,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Role {
+        Member,
+        Admin,
+        Support,
+    }
+
+    struct User {
+        active_organisation: Option<u32>,
+        memberships: HashMap<u32, HashSet<Role>>,
+        support: bool,
+        role_lookups: Cell<usize>,
+    }
+
+    impl Subject for User {}
+
+    impl TenantRbacSubject for User {
+        type TenantId = u32;
+        type TenantRole = Role;
+
+        fn active_tenant(&self) -> Option<u32> {
+            self.active_organisation
+        }
+
+        fn tenant_roles(&self, tenant: &u32) -> HashSet<Role> {
+            self.role_lookups.set(self.role_lookups.get() + 1);
+            self.memberships.get(tenant).cloned().unwrap_or_default()
+        }
+
+        fn cross_tenant_roles(&self) -> HashSet<Role> {
+            if self.support {
+                HashSet::from([Role::Support])
+            } else {
+                HashSet::new()
+            }
+        }
+    }
+
+    #[test]
+    fn roles_apply_in_their_own_tenant() {
+        let policy = TenantRbacPolicy::new();
+        let user = User {
+            active_organisation: Some(1),
+            memberships: HashMap::from([
+                (1, HashSet::from([Role::Member])),
+                (2, HashSet::from([Role::Admin])),
+            ]),
+            support: false,
+            role_lookups: Cell::new(0),
+        };
+
+        let own = Project { organisation: 1 };
+        assert!(policy.decide(&own, &user, &ProjectAction::View).is_permit());
+        assert!(policy.decide(&own, &user, &ProjectAction::Delete).is_deny());
+
+        // Being an admin of another organisation doesn't help while acting in this one
+        let other = Project { organisation: 2 };
+        let decision = policy.decide(&other, &user, &ProjectAction::Delete);
+        assert!(decision.is_deny());
+        assert_eq!(
+            decision.reason(),
+            "Resource belongs to tenant 2 but the active tenant is 1, and subject holds none of \
+             the cross-tenant roles allowed to perform this action"
+        );
+    }
+
+    #[test]
+    fn cross_tenant_roles_apply_in_every_tenant() {
+        let policy = TenantRbacPolicy::new();
+        let support = User {
+            active_organisation: None,
+            memberships: HashMap::new(),
+            support: true,
+            role_lookups: Cell::new(0),
+        };
+        let project = Project { organisation: 3 };
+
+        let decision = policy.decide(&project, &support, &ProjectAction::View);
+        assert!(decision.is_permit());
+        assert_eq!(decision.rule(), Some("[Support]"));
+        assert!(policy
+            .decide(&project, &support, &ProjectAction::Delete)
+            .is_deny());
+    }
+
+    #[test]
+    fn load_roles_once_per_batch() {
+        let policy = TenantRbacPolicy::new();
+        let user = User {
+            active_organisation: Some(1),
+            memberships: HashMap::from([(1, HashSet::from([Role::Admin]))]),
+            support: false,
+            role_lookups: Cell::new(0),
+        };
+        let projects: Vec<_> = (0..10)
+            .map(|i| Project {
+                organisation: i % 2 + 1,
+            })
+            .collect();
+
+        let deletable = projects
+            .iter()
+            .filter_authorised(&policy, &user, &ProjectAction::Delete)
+            .count();
+        assert_eq!(deletable, 5);
+        assert_eq!(user.role_lookups.get(), 1);
+
+        let decisions = policy.decide_many(
+            &user,
+            projects
+                .iter()
+                .map(|project| (project, &ProjectAction::View)),
+        );
+        assert_eq!(decisions.iter().filter(|d| d.is_permit()).count(), 5);
+        assert_eq!(user.role_lookups.get(), 2);
+    }
+}
@@ -0,0 +1,725 @@
+//! Relationship-based access control, in the style of Google's Zanzibar.
+//!
+//! Access is derived from relation tuples such as `document:42#viewer@user:7` ("user 7 is a
+//! viewer of document 42") or `document:42#parent@folder:3` ("folder 3 is the parent of document
+//! 42"). Each namespace declares how its relations are computed from stored tuples, so that for
+//! example the viewers of a document can include everyone who can view its parent folder:
+//!
+//! ```ignore
+//! let document = Namespace::new("document")
+//!     .relation("parent", Rewrite::This)
+//!     .relation("editor", Rewrite::This)
+//!     .relation(
+//!         "viewer",
+//!         Rewrite::union([
+//!             Rewrite::This,
+//!             Rewrite::computed("editor"),
+//!             Rewrite::tuple_to_userset("parent", "viewer"),
+//!         ]),
+//!     );
+//! ```
+
+use crate::{Decision, Policy, Resource, Subject};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use store::TupleStore;
+use thiserror::Error;
+
+pub mod store;
+
+const DEFAULT_MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum RebacError {
+    #[error("Unknown namespace '{0}'")]
+    UnknownNamespace(String),
+    #[error("Unknown relation '{relation}' in namespace '{namespace}'")]
+    UnknownRelation { namespace: String, relation: String },
+    #[error("Maximum depth exceeded while checking '{0}'")]
+    DepthExceeded(String),
+    #[error("Tuple store error: {0}")]
+    Store(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid {kind} '{value}'")]
+pub struct ParseError {
+    kind: &'static str,
+    value: String,
+}
+
+impl ParseError {
+    fn new(kind: &'static str, value: &str) -> Self {
+        ParseError {
+            kind,
+            value: value.to_string(),
+        }
+    }
+}
+
+/// A reference to an object, written `namespace:id`, such as `document:42` or `user:7`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectRef {
+    pub namespace: String,
+    pub id: String,
+}
+
+impl ObjectRef {
+    pub fn new(namespace: impl Into<String>, id: impl ToString) -> Self {
+        ObjectRef {
+            namespace: namespace.into(),
+            id: id.to_string(),
+        }
+    }
+}
+
+impl Display for ObjectRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.id)
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((namespace, id)) if !namespace.is_empty() && !id.is_empty() => {
+                Ok(ObjectRef::new(namespace, id))
+            }
+            _ => Err(ParseError::new("object", s)),
+        }
+    }
+}
+
+/// The subject of a relation tuple: either a single object such as `user:7`, or everyone with a
+/// relation to another object, such as `group:3#member`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TupleSubject {
+    Object(ObjectRef),
+    Userset { object: ObjectRef, relation: String },
+}
+
+impl TupleSubject {
+    /// The object this subject refers to, ignoring any relation.
+    pub fn object(&self) -> &ObjectRef {
+        match self {
+            TupleSubject::Object(object) => object,
+            TupleSubject::Userset { object, .. } => object,
+        }
+    }
+}
+
+impl From<ObjectRef> for TupleSubject {
+    fn from(object: ObjectRef) -> Self {
+        TupleSubject::Object(object)
+    }
+}
+
+impl Display for TupleSubject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TupleSubject::Object(object) => write!(f, "{}", object),
+            TupleSubject::Userset { object, relation } => write!(f, "{}#{}", object, relation),
+        }
+    }
+}
+
+impl FromStr for TupleSubject {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('#') {
+            Some((object, relation)) if !relation.is_empty() => Ok(TupleSubject::Userset {
+                object: object.parse()?,
+                relation: relation.to_string(),
+            }),
+            Some(_) => Err(ParseError::new("subject", s)),
+            None => Ok(TupleSubject::Object(s.parse()?)),
+        }
+    }
+}
+
+/// A stored relationship, written `object#relation@subject`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RelationTuple {
+    pub object: ObjectRef,
+    pub relation: String,
+    pub subject: TupleSubject,
+}
+
+impl RelationTuple {
+    pub fn new(
+        object: ObjectRef,
+        relation: impl Into<String>,
+        subject: impl Into<TupleSubject>,
+    ) -> Self {
+        RelationTuple {
+            object,
+            relation: relation.into(),
+            subject: subject.into(),
+        }
+    }
+}
+
+impl Display for RelationTuple {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}#{}@{}", self.object, self.relation, self.subject)
+    }
+}
+
+impl FromStr for RelationTuple {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (object_relation, subject) = s
+            .split_once('@')
+            .ok_or_else(|| ParseError::new("tuple", s))?;
+        let (object, relation) = object_relation
+            .split_once('#')
+            .filter(|(_, relation)| !relation.is_empty())
+            .ok_or_else(|| ParseError::new("tuple", s))?;
+
+        Ok(RelationTuple {
+            object: object.parse()?,
+            relation: relation.to_string(),
+            subject: subject.parse()?,
+        })
+    }
+}
+
+/// How the members of a relation are computed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rewrite {
+    /// Subjects stored directly in tuples for this relation.
+    This,
+    /// Everyone with another relation on the same object, such as editors also being viewers.
+    ComputedUserset(String),
+    /// Everyone with `computed_userset` on the objects related via `tupleset`, such as viewers of
+    /// the document's parent folder.
+    TupleToUserset {
+        tupleset: String,
+        computed_userset: String,
+    },
+    Union(Vec<Rewrite>),
+    Intersection(Vec<Rewrite>),
+    /// Members of the first rewrite who are not members of the second.
+    Exclusion(Box<Rewrite>, Box<Rewrite>),
+}
+
+impl Rewrite {
+    pub fn computed(relation: impl Into<String>) -> Self {
+        Rewrite::ComputedUserset(relation.into())
+    }
+
+    pub fn tuple_to_userset(
+        tupleset: impl Into<String>,
+        computed_userset: impl Into<String>,
+    ) -> Self {
+        Rewrite::TupleToUserset {
+            tupleset: tupleset.into(),
+            computed_userset: computed_userset.into(),
+        }
+    }
+
+    pub fn union(rewrites: impl IntoIterator<Item = Rewrite>) -> Self {
+        Rewrite::Union(rewrites.into_iter().collect())
+    }
+
+    pub fn intersection(rewrites: impl IntoIterator<Item = Rewrite>) -> Self {
+        Rewrite::Intersection(rewrites.into_iter().collect())
+    }
+
+    pub fn exclusion(base: Rewrite, excluded: Rewrite) -> Self {
+        Rewrite::Exclusion(Box::new(base), Box::new(excluded))
+    }
+}
+
+/// The relations defined on a type of object.
+#[derive(Debug, Clone)]
+pub struct Namespace {
+    name: String,
+    relations: HashMap<String, Rewrite>,
+}
+
+impl Namespace {
+    pub fn new(name: impl Into<String>) -> Self {
+        Namespace {
+            name: name.into(),
+            relations: HashMap::new(),
+        }
+    }
+
+    pub fn relation(mut self, relation: impl Into<String>, rewrite: Rewrite) -> Self {
+        self.relations.insert(relation.into(), rewrite);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A resource that corresponds to an object in the relation graph.
+pub trait RebacResource: Resource {
+    fn rebac_object(&self) -> ObjectRef;
+
+    /// The relation the subject must have with this object to perform the action.
+    fn relation(&self, action: &Self::Action) -> String;
+}
+
+/// A subject that corresponds to an object in the relation graph, typically `user:<id>`.
+pub trait RebacSubject: Subject {
+    fn rebac_subject(&self) -> ObjectRef;
+}
+
+/// Policy that permits an action if the subject has the required relation with the resource,
+/// following the userset rewrites declared in each namespace.
+pub struct RebacPolicy<S> {
+    namespaces: HashMap<String, Namespace>,
+    store: S,
+    max_depth: usize,
+}
+
+impl<S> RebacPolicy<S>
+where
+    S: TupleStore,
+{
+    pub fn new(store: S) -> Self {
+        RebacPolicy {
+            namespaces: HashMap::new(),
+            store,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: Namespace) -> Self {
+        self.namespaces.insert(namespace.name.clone(), namespace);
+        self
+    }
+
+    /// Limits how many relations may be followed in a single check, guarding against unbounded
+    /// recursion through cyclic data.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Checks whether `subject` has `relation` with `object`.
+    pub fn check(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        subject: &ObjectRef,
+    ) -> Result<bool, RebacError> {
+        self.check_relation(object, relation, subject, 0)
+    }
+
+    fn check_relation(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        subject: &ObjectRef,
+        depth: usize,
+    ) -> Result<bool, RebacError> {
+        if depth > self.max_depth {
+            return Err(RebacError::DepthExceeded(format!(
+                "{}#{}",
+                object, relation
+            )));
+        }
+
+        let namespace = self
+            .namespaces
+            .get(&object.namespace)
+            .ok_or_else(|| RebacError::UnknownNamespace(object.namespace.clone()))?;
+
+        let rewrite =
+            namespace
+                .relations
+                .get(relation)
+                .ok_or_else(|| RebacError::UnknownRelation {
+                    namespace: object.namespace.clone(),
+                    relation: relation.to_string(),
+                })?;
+
+        self.check_rewrite(object, relation, rewrite, subject, depth)
+    }
+
+    fn check_rewrite(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        rewrite: &Rewrite,
+        subject: &ObjectRef,
+        depth: usize,
+    ) -> Result<bool, RebacError> {
+        match rewrite {
+            Rewrite::This => {
+                any_of(self.read(object, relation)?.iter().map(
+                    |tuple_subject| match tuple_subject {
+                        TupleSubject::Object(o) => Ok(o == subject),
+                        TupleSubject::Userset {
+                            object: o,
+                            relation: r,
+                        } => self.check_relation(o, r, subject, depth + 1),
+                    },
+                ))
+            }
+            Rewrite::ComputedUserset(computed) => {
+                self.check_relation(object, computed, subject, depth + 1)
+            }
+            Rewrite::TupleToUserset {
+                tupleset,
+                computed_userset,
+            } => any_of(self.read(object, tupleset)?.iter().map(|tuple_subject| {
+                self.check_relation(tuple_subject.object(), computed_userset, subject, depth + 1)
+            })),
+            Rewrite::Union(rewrites) => any_of(
+                rewrites
+                    .iter()
+                    .map(|rewrite| self.check_rewrite(object, relation, rewrite, subject, depth)),
+            ),
+            Rewrite::Intersection(rewrites) => {
+                if rewrites.is_empty() {
+                    return Ok(false);
+                }
+
+                all_of(
+                    rewrites.iter().map(|rewrite| {
+                        self.check_rewrite(object, relation, rewrite, subject, depth)
+                    }),
+                )
+            }
+            Rewrite::Exclusion(base, excluded) => {
+                let excluded = self
+                    .check_rewrite(object, relation, excluded, subject, depth)
+                    .map(|excluded| !excluded);
+
+                all_of([
+                    self.check_rewrite(object, relation, base, subject, depth),
+                    excluded,
+                ])
+            }
+        }
+    }
+
+    fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<TupleSubject>, RebacError> {
+        self.store
+            .read(object, relation)
+            .map_err(|e| RebacError::Store(Box::new(e)))
+    }
+}
+
+/// Whether any of the checks hold. An error is only returned if none of them do, so that a
+/// failure in one branch doesn't deny access granted by another.
+fn any_of(checks: impl IntoIterator<Item = Result<bool, RebacError>>) -> Result<bool, RebacError> {
+    let mut error = None;
+
+    for check in checks {
+        match check {
+            Ok(true) => return Ok(true),
+            Ok(false) => {}
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    error.map_or(Ok(false), Err)
+}
+
+/// Whether all of the checks hold. An error is only returned if none of them fail outright, as
+/// one failing is enough to know the answer.
+fn all_of(checks: impl IntoIterator<Item = Result<bool, RebacError>>) -> Result<bool, RebacError> {
+    let mut error = None;
+
+    for check in checks {
+        match check {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    error.map_or(Ok(true), Err)
+}
+
+impl<Res, Subj, S> Policy<Res, Subj> for RebacPolicy<S>
+where
+    Res: RebacResource,
+    Subj: RebacSubject,
+    S: TupleStore,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let object = resource.rebac_object();
+        let relation = resource.relation(action);
+        let subject = subject.rebac_subject();
+        let rule = format!("{}#{}", object, relation);
+
+        match self.check(&object, &relation, &subject) {
+            Ok(true) => Decision::permit(format!(
+                "Subject {} has relation '{}' with {}",
+                subject, relation, object
+            ))
+            .with_rule(rule),
+            Ok(false) => Decision::deny(format!(
+                "Subject {} does not have relation '{}' with {}",
+                subject, relation, object
+            ))
+            .with_rule(rule),
+            Err(e) => Decision::deny(format!("Failed to check relation: {}", e)).with_rule(rule),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::store::in_memory::InMemoryTupleStore;
+    use super::*;
+
+    fn tuple(s: &str) -> RelationTuple {
+        s.parse().unwrap()
+    }
+
+    fn object(s: &str) -> ObjectRef {
+        s.parse().unwrap()
+    }
+
+    fn policy() -> RebacPolicy<InMemoryTupleStore> {
+        let store = InMemoryTupleStore::from_iter([
+            tuple("org:acme#member@user:1"),
+            tuple("org:acme#admin@user:2"),
+            tuple("project:rocket#org@org:acme"),
+            tuple("project:rocket#editor@group:eng#member"),
+            tuple("group:eng#member@user:3"),
+            tuple("document:42#parent@project:rocket"),
+            tuple("document:42#owner@user:4"),
+            tuple("document:42#banned@user:1"),
+        ]);
+
+        RebacPolicy::new(store)
+            .with_namespace(Namespace::new("user"))
+            .with_namespace(Namespace::new("group").relation("member", Rewrite::This))
+            .with_namespace(
+                Namespace::new("org")
+                    .relation("admin", Rewrite::This)
+                    .relation(
+                        "member",
+                        Rewrite::union([Rewrite::This, Rewrite::computed("admin")]),
+                    ),
+            )
+            .with_namespace(
+                Namespace::new("project")
+                    .relation("org", Rewrite::This)
+                    .relation(
+                        "editor",
+                        Rewrite::union([Rewrite::This, Rewrite::tuple_to_userset("org", "admin")]),
+                    )
+                    .relation(
+                        "viewer",
+                        Rewrite::union([
+                            Rewrite::computed("editor"),
+                            Rewrite::tuple_to_userset("org", "member"),
+                        ]),
+                    ),
+            )
+            .with_namespace(
+                Namespace::new("document")
+                    .relation("parent", Rewrite::This)
+                    .relation("owner", Rewrite::This)
+                    .relation("banned", Rewrite::This)
+                    .relation(
+                        "editor",
+                        Rewrite::union([
+                            Rewrite::computed("owner"),
+                            Rewrite::tuple_to_userset("parent", "editor"),
+                        ]),
+                    )
+                    .relation(
+                        "viewer",
+                        Rewrite::exclusion(
+                            Rewrite::union([
+                                Rewrite::computed("editor"),
+                                Rewrite::tuple_to_userset("parent", "viewer"),
+                            ]),
+                            Rewrite::computed("banned"),
+                        ),
+                    ),
+            )
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let t = tuple("project:rocket#editor@group:eng#member");
+
+        assert_eq!(t.object, object("project:rocket"));
+        assert_eq!(t.relation, "editor");
+        assert_eq!(
+            t.subject,
+            TupleSubject::Userset {
+                object: object("group:eng"),
+                relation: "member".to_string(),
+            }
+        );
+        assert_eq!(t.to_string(), "project:rocket#editor@group:eng#member");
+
+        assert!("document:42#viewer".parse::<RelationTuple>().is_err());
+        assert!("document#viewer@user:1".parse::<RelationTuple>().is_err());
+    }
+
+    #[test]
+    fn check_through_hierarchy() {
+        let policy = policy();
+        let document = object("document:42");
+
+        // Owner directly
+        assert!(policy
+            .check(&document, "editor", &object("user:4"))
+            .unwrap());
+        // Org admin, through project org
+        assert!(policy
+            .check(&document, "editor", &object("user:2"))
+            .unwrap());
+        // Group member, through project editor userset
+        assert!(policy
+            .check(&document, "editor", &object("user:3"))
+            .unwrap());
+        assert!(policy
+            .check(&document, "viewer", &object("user:3"))
+            .unwrap());
+        // Org member can't edit, and is banned from viewing this document
+        assert!(!policy
+            .check(&document, "editor", &object("user:1"))
+            .unwrap());
+        assert!(!policy
+            .check(&document, "viewer", &object("user:1"))
+            .unwrap());
+        assert!(policy
+            .check(&object("project:rocket"), "viewer", &object("user:1"))
+            .unwrap());
+        // Stranger
+        assert!(!policy
+            .check(&document, "viewer", &object("user:9"))
+            .unwrap());
+    }
+
+    #[test]
+    fn unknown_relations_and_cycles() {
+        let policy = policy();
+
+        assert!(matches!(
+            policy.check(&object("document:42"), "commenter", &object("user:1")),
+            Err(RebacError::UnknownRelation { .. })
+        ));
+
+        policy
+            .store()
+            .write(tuple("group:eng#member@group:eng#member"))
+            .unwrap();
+
+        assert!(matches!(
+            policy.check(&object("group:eng"), "member", &object("user:9")),
+            Err(RebacError::DepthExceeded(_))
+        ));
+    }
+
+    #[derive(Debug, Error)]
+    #[error("connection lost")]
+    struct ConnectionLost;
+
+    /// Fails to read one relation, as a database might if a query times out.
+    struct FlakyStore {
+        tuples: InMemoryTupleStore,
+        failing: &'static str,
+    }
+
+    impl TupleStore for FlakyStore {
+        type Error = ConnectionLost;
+
+        fn read(
+            &self,
+            object: &ObjectRef,
+            relation: &str,
+        ) -> Result<Vec<TupleSubject>, ConnectionLost> {
+            if relation == self.failing {
+                return Err(ConnectionLost);
+            }
+
+            Ok(self.tuples.read(object, relation).unwrap())
+        }
+
+        fn write(&self, tuple: RelationTuple) -> Result<(), ConnectionLost> {
+            self.tuples.write(tuple).unwrap();
+            Ok(())
+        }
+
+        fn delete(&self, tuple: &RelationTuple) -> Result<bool, ConnectionLost> {
+            Ok(self.tuples.delete(tuple).unwrap())
+        }
+    }
+
+    #[test]
+    fn check_other_branches_after_store_errors() {
+        let policy = RebacPolicy::new(FlakyStore {
+            tuples: InMemoryTupleStore::from_iter([
+                tuple("document:42#owner@user:4"),
+                tuple("document:42#parent@project:rocket"),
+            ]),
+            failing: "parent",
+        })
+        .with_namespace(
+            Namespace::new("document")
+                .relation("owner", Rewrite::This)
+                .relation("parent", Rewrite::This)
+                .relation(
+                    "editor",
+                    Rewrite::union([
+                        Rewrite::tuple_to_userset("parent", "editor"),
+                        Rewrite::computed("owner"),
+                    ]),
+                ),
+        );
+        let document = object("document:42");
+
+        // The owner is an editor whether or not the parent can be read
+        assert!(policy
+            .check(&document, "editor", &object("user:4"))
+            .unwrap());
+
+        // Anyone else might be an editor through the parent, so the error is returned
+        let error = policy
+            .check(&document, "editor", &object("user:9"))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Tuple store error: connection lost");
+    }
+
+    #[test]
+    fn write_and_delete() {
+        let policy = policy();
+        let document = object("document:42");
+
+        policy
+            .store()
+            .write(tuple("document:42#owner@user:9"))
+            .unwrap();
+        assert!(policy
+            .check(&document, "viewer", &object("user:9"))
+            .unwrap());
+
+        assert!(policy
+            .store()
+            .delete(&tuple("document:42#owner@user:9"))
+            .unwrap());
+        assert!(!policy
+            .check(&document, "viewer", &object("user:9"))
+            .unwrap());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::rebac::store::TupleStore;
+use crate::rebac::{ObjectRef, RelationTuple, TupleSubject};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+
+type TupleKey = (ObjectRef, String);
+
+pub struct InMemoryTupleStore {
+    tuples: Mutex<HashMap<TupleKey, HashSet<TupleSubject>>>,
+}
+
+impl InMemoryTupleStore {
+    pub fn new() -> Self {
+        InMemoryTupleStore {
+            tuples: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryTupleStore {
+    fn default() -> Self {
+        InMemoryTupleStore::new()
+    }
+}
+
+impl FromIterator<RelationTuple> for InMemoryTupleStore {
+    fn from_iter<I: IntoIterator<Item = RelationTuple>>(tuples: I) -> Self {
+        let mut map: HashMap<TupleKey, HashSet<TupleSubject>> = HashMap::new();
+
+        for tuple in tuples {
+            map.entry((tuple.object, tuple.relation))
+                .or_default()
+                .insert(tuple.subject);
+        }
+
+        InMemoryTupleStore {
+            tuples: Mutex::new(map),
+        }
+    }
+}
+
+impl TupleStore for InMemoryTupleStore {
+    type Error = Infallible;
+
+    fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<TupleSubject>, Infallible> {
+        Ok(self
+            .tuples
+            .lock()
+            .get(&(object.clone(), relation.to_string()))
+            .map(|subjects| subjects.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn write(&self, tuple: RelationTuple) -> Result<(), Infallible> {
+        self.tuples
+            .lock()
+            .entry((tuple.object, tuple.relation))
+            .or_default()
+            .insert(tuple.subject);
+        Ok(())
+    }
+
+    fn delete(&self, tuple: &RelationTuple) -> Result<bool, Infallible> {
+        let mut tuples = self.tuples.lock();
+        let key = (tuple.object.clone(), tuple.relation.clone());
+
+        let Some(subjects) = tuples.get_mut(&key) else {
+            return Ok(false);
+        };
+
+        let removed = subjects.remove(&tuple.subject);
+
+        if subjects.is_empty() {
+            tuples.remove(&key);
+        }
+
+        Ok(removed)
+    }
+}
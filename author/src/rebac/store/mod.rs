@@ -0,0 +1,19 @@
+use crate::rebac::{ObjectRef, RelationTuple, TupleSubject};
+
+pub mod in_memory;
+
+/// Storage for relation tuples, consulted by [`RebacPolicy`](crate::rebac::RebacPolicy) when
+/// checking relations.
+pub trait TupleStore {
+    /// The error returned when the store can't be read or written, such as a database error.
+    /// Stores that can't fail use [`Infallible`](std::convert::Infallible).
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Returns the subjects of every tuple with the given object and relation.
+    fn read(&self, object: &ObjectRef, relation: &str) -> Result<Vec<TupleSubject>, Self::Error>;
+
+    fn write(&self, tuple: RelationTuple) -> Result<(), Self::Error>;
+
+    /// Deletes the tuple, returning whether it existed.
+    fn delete(&self, tuple: &RelationTuple) -> Result<bool, Self::Error>;
+}
@@ -0,0 +1,361 @@
+//! Policies that can be replaced at runtime without restarting.
+//!
+//! A [`PolicyStore`] holds the current version of a policy and makes its decisions. A new version
+//! is only swapped in once it has loaded successfully, so a broken configuration file leaves the
+//! previous policy in place, and the version before the current one is kept so it can be rolled
+//! back to.
+
+#[cfg(feature = "async")]
+use crate::AsyncPolicy;
+use crate::{Decision, Policy, Resource, Subject};
+use arc_swap::ArcSwap;
+use parking_lot::Mutex;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Failed to load policy: {0}")]
+    Load(#[source] Box<dyn Error + Send + Sync>),
+    #[error("Policy store has no loader to reload from")]
+    NoLoader,
+    #[error("Policy store has no previous version to roll back to")]
+    NoPreviousVersion,
+}
+
+/// A version of the policy held by a [`PolicyStore`].
+#[derive(Debug)]
+pub struct PolicyVersion<P> {
+    version: u64,
+    loaded_at: SystemTime,
+    policy: P,
+}
+
+impl<P> PolicyVersion<P> {
+    /// The version number, which starts at 1 and increases each time a new policy is stored.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn loaded_at(&self) -> SystemTime {
+        self.loaded_at
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+}
+
+type Loader<P> = Box<dyn Fn() -> Result<P, Box<dyn Error + Send + Sync>> + Send + Sync>;
+type ReloadListener = Box<dyn Fn(&Result<u64, StoreError>) + Send + Sync>;
+
+/// The versions other than the current one, only used while changing versions.
+struct History<P> {
+    previous: Option<Arc<PolicyVersion<P>>>,
+    latest: u64,
+}
+
+/// Holds the current version of a policy, which can be replaced, reloaded or rolled back while
+/// decisions are being made. Decisions already in progress finish with the version they started
+/// with.
+pub struct PolicyStore<P> {
+    current: ArcSwap<PolicyVersion<P>>,
+    // Serialises replacing, reloading and rolling back, without blocking decisions
+    history: Mutex<History<P>>,
+    loader: Option<Loader<P>>,
+    on_reload: Option<ReloadListener>,
+}
+
+impl<P> PolicyStore<P> {
+    /// Creates a store holding a policy, which can be replaced but not reloaded.
+    pub fn new(policy: P) -> Self {
+        PolicyStore {
+            current: ArcSwap::from_pointee(PolicyVersion {
+                version: 1,
+                loaded_at: SystemTime::now(),
+                policy,
+            }),
+            history: Mutex::new(History {
+                previous: None,
+                latest: 1,
+            }),
+            loader: None,
+            on_reload: None,
+        }
+    }
+
+    /// Creates a store holding the policy returned by the loader, which is called again on each
+    /// reload. The loader should validate the policy, returning an error if it can't be used.
+    pub fn with_loader<F, E>(loader: F) -> Result<Self, StoreError>
+    where
+        F: Fn() -> Result<P, E> + Send + Sync + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let loader: Loader<P> = Box::new(move || loader().map_err(Into::into));
+        let policy = loader().map_err(StoreError::Load)?;
+
+        let mut store = PolicyStore::new(policy);
+        store.loader = Some(loader);
+
+        Ok(store)
+    }
+
+    /// Sets a function called with the result of every reload, including those made by a
+    /// [`FileWatcher`], for example to log failures.
+    pub fn on_reload<F>(mut self, listener: F) -> Self
+    where
+        F: Fn(&Result<u64, StoreError>) + Send + Sync + 'static,
+    {
+        self.on_reload = Some(Box::new(listener));
+        self
+    }
+
+    pub fn current(&self) -> Arc<PolicyVersion<P>> {
+        self.current.load_full()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.current.load().version
+    }
+
+    pub fn previous(&self) -> Option<Arc<PolicyVersion<P>>> {
+        self.history.lock().previous.clone()
+    }
+
+    /// Stores a new version of the policy, returning its version number.
+    pub fn replace(&self, policy: P) -> u64 {
+        self.store(&mut self.history.lock(), policy)
+    }
+
+    fn store(&self, history: &mut History<P>, policy: P) -> u64 {
+        history.latest += 1;
+
+        let current = Arc::new(PolicyVersion {
+            version: history.latest,
+            loaded_at: SystemTime::now(),
+            policy,
+        });
+        history.previous = Some(self.current.swap(current));
+
+        history.latest
+    }
+
+    /// Loads the policy again and stores it as a new version, returning its version number. If
+    /// the policy can't be loaded the current version is kept.
+    pub fn reload(&self) -> Result<u64, StoreError> {
+        let result = self.load_and_replace();
+
+        if let Some(on_reload) = &self.on_reload {
+            on_reload(&result);
+        }
+
+        result
+    }
+
+    fn load_and_replace(&self) -> Result<u64, StoreError> {
+        let loader = self.loader.as_ref().ok_or(StoreError::NoLoader)?;
+
+        let mut history = self.history.lock();
+        let policy = loader().map_err(StoreError::Load)?;
+
+        Ok(self.store(&mut history, policy))
+    }
+
+    /// Restores the previous version, returning its version number. The version rolled back from
+    /// becomes the previous version, so a rollback can itself be undone.
+    pub fn rollback(&self) -> Result<u64, StoreError> {
+        let mut history = self.history.lock();
+        let previous = history
+            .previous
+            .take()
+            .ok_or(StoreError::NoPreviousVersion)?;
+
+        let version = previous.version;
+        history.previous = Some(self.current.swap(previous));
+
+        Ok(version)
+    }
+}
+
+impl<P> PolicyStore<P>
+where
+    P: Send + Sync + 'static,
+{
+    /// Reloads the store whenever the file at the path is modified, checking for changes at the
+    /// given interval. The file is only watched until the returned [`FileWatcher`] is dropped, or
+    /// the store itself is.
+    pub fn watch(self: &Arc<Self>, path: impl Into<PathBuf>, interval: Duration) -> FileWatcher {
+        let store = Arc::downgrade(self);
+        let path = path.into();
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let modified = |path: &PathBuf| {
+            std::fs::metadata(path)
+                .ok()
+                .map(|metadata| (metadata.modified().ok(), metadata.len()))
+        };
+        // Taken before the thread starts so changes made straight after watching aren't missed
+        let mut last_modified = modified(&path);
+
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let Some(store) = Weak::upgrade(&store) else {
+                    break;
+                };
+
+                let current_modified = modified(&path);
+                if current_modified.is_some() && current_modified != last_modified {
+                    last_modified = current_modified;
+                    let _ = store.reload();
+                }
+            }
+        });
+
+        FileWatcher {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Reloads a [`PolicyStore`] when a file changes, until dropped.
+pub struct FileWatcher {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the watching thread up so it can exit
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<Res, Subj, P> Policy<Res, Subj> for PolicyStore<P>
+where
+    Res: Resource,
+    Subj: Subject,
+    P: Policy<Res, Subj>,
+{
+    fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        self.current.load().policy.decide(resource, subject, action)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<Res, Subj, P> AsyncPolicy<Res, Subj> for PolicyStore<P>
+where
+    Res: Resource<Action: Sync> + Sync,
+    Subj: Subject + Sync,
+    P: AsyncPolicy<Res, Subj> + Send + Sync,
+{
+    async fn decide(&self, resource: &Res, subject: &Subj, action: &Res::Action) -> Decision {
+        let current = self.current();
+        current.policy.decide(resource, subject, action).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Document;
+
+    impl Resource for Document {
+        type Action = ();
+    }
+
+    struct User;
+
+    impl Subject for User {}
+
+    struct Fixed(bool);
+
+    impl Policy<Document, User> for Fixed {
+        fn decide(&self, _document: &Document, _user: &User, _action: &()) -> Decision {
+            match self.0 {
+                true => Decision::permit("Allowed"),
+                false => Decision::deny("Not allowed"),
+            }
+        }
+    }
+
+    #[test]
+    fn reload_and_roll_back() {
+        let config = Arc::new(Mutex::new(Some(false)));
+        let loader_config = config.clone();
+        let store = PolicyStore::with_loader(move || {
+            loader_config
+                .lock()
+                .map(Fixed)
+                .ok_or("Invalid policy config")
+        })
+        .unwrap();
+
+        assert_eq!(store.version(), 1);
+        assert!(store.decide(&Document, &User, &()).is_deny());
+
+        *config.lock() = Some(true);
+        assert_eq!(store.reload().unwrap(), 2);
+        assert!(store.decide(&Document, &User, &()).is_permit());
+
+        // A policy that fails to load doesn't replace the current one
+        *config.lock() = None;
+        assert!(matches!(store.reload(), Err(StoreError::Load(_))));
+        assert_eq!(store.version(), 2);
+        assert!(store.decide(&Document, &User, &()).is_permit());
+
+        assert_eq!(store.rollback().unwrap(), 1);
+        assert!(store.decide(&Document, &User, &()).is_deny());
+        assert_eq!(store.previous().unwrap().version(), 2);
+
+        assert_eq!(store.replace(Fixed(true)), 3);
+    }
+
+    #[test]
+    fn reload_when_file_changes() {
+        let path = std::env::temp_dir().join(format!("author-store-{}.txt", std::process::id()));
+        std::fs::write(&path, "deny").unwrap();
+
+        let reloaded = Arc::new(AtomicBool::new(false));
+        let loader_path = path.clone();
+        let store = Arc::new(
+            PolicyStore::with_loader(move || {
+                std::fs::read_to_string(&loader_path).map(|s| Fixed(s == "permit"))
+            })
+            .unwrap()
+            .on_reload({
+                let reloaded = reloaded.clone();
+                move |result| reloaded.store(result.is_ok(), Ordering::SeqCst)
+            }),
+        );
+        let watcher = store.watch(&path, Duration::from_millis(10));
+
+        std::fs::write(&path, "permit").unwrap();
+
+        for _ in 0..200 {
+            if reloaded.load(Ordering::SeqCst) {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        drop(watcher);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(store.version(), 2);
+        assert!(store.decide(&Document, &User, &()).is_permit());
+    }
+}
@@ -0,0 +1,450 @@
+//! Table-driven tests of policies. A [`DecisionMatrix`] lists the outcome expected for each
+//! combination of subject, resource and action, checks all of them against a policy and reports
+//! every mismatch at once:
+//!
+//! ```text
+//! 2 of 6 expectations failed:
+//!
+//! subject | resource | action | expected | actual | reason
+//! --------+----------+--------+----------+--------+-------------------------------------------
+//! user    | product  | write  | allow    | Deny   | Subject holds none of the global roles ...
+//! admin   | product  | delete | deny     | Permit | Subject holds global roles [Admin] which ...
+//! ```
+//!
+//! With the `yaml` feature, expectations can also be loaded from fixtures that map subject names
+//! to resource names to action names to `allow` or `deny`:
+//!
+//! ```yaml
+//! admin:
+//!   product:
+//!     read: allow
+//!     delete: allow
+//! user:
+//!   product:
+//!     read: allow
+//!     delete: deny
+//! ```
+
+use crate::{Decision, Named, Policy, Resource, Subject};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "yaml")]
+use {crate::Enumerable, std::path::Path, thiserror::Error};
+
+/// The outcome expected of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "yaml", derive(serde::Deserialize))]
+#[cfg_attr(feature = "yaml", serde(rename_all = "snake_case"))]
+pub enum Outcome {
+    /// The policy permits the request.
+    Allow,
+    /// The policy doesn't permit the request, either denying it or not applying to it.
+    Deny,
+}
+
+impl Outcome {
+    fn matches(self, decision: &Decision) -> bool {
+        decision.is_permit() == (self == Outcome::Allow)
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Allow => write!(f, "allow"),
+            Outcome::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[cfg(feature = "yaml")]
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("Failed to read fixture: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid YAML fixture: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Unknown subject '{0}' in fixture")]
+    UnknownSubject(String),
+    #[error("Unknown resource '{0}' in fixture")]
+    UnknownResource(String),
+    #[error("Unknown action '{0}' in fixture")]
+    UnknownAction(String),
+}
+
+struct Expectation<Action> {
+    subject: String,
+    resource: String,
+    action: Action,
+    outcome: Outcome,
+}
+
+/// A set of named subjects and resources of one type, and the outcomes expected when the
+/// subjects perform actions on the resources.
+pub struct DecisionMatrix<'a, Res, Subj>
+where
+    Res: Resource,
+{
+    subjects: BTreeMap<String, &'a Subj>,
+    resources: BTreeMap<String, &'a Res>,
+    expectations: Vec<Expectation<Res::Action>>,
+}
+
+impl<'a, Res, Subj> Default for DecisionMatrix<'a, Res, Subj>
+where
+    Res: Resource,
+{
+    fn default() -> Self {
+        DecisionMatrix {
+            subjects: BTreeMap::new(),
+            resources: BTreeMap::new(),
+            expectations: Vec::new(),
+        }
+    }
+}
+
+impl<'a, Res, Subj> DecisionMatrix<'a, Res, Subj>
+where
+    Res: Resource,
+    Res::Action: Named,
+    Subj: Subject,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subject(mut self, name: impl Into<String>, subject: &'a Subj) -> Self {
+        self.subjects.insert(name.into(), subject);
+        self
+    }
+
+    pub fn resource(mut self, name: impl Into<String>, resource: &'a Res) -> Self {
+        self.resources.insert(name.into(), resource);
+        self
+    }
+
+    /// Expects the outcome when the named subject performs an action on the named resource.
+    ///
+    /// # Panics
+    ///
+    /// If the subject or resource hasn't been added to the matrix.
+    pub fn expect(
+        mut self,
+        subject: &str,
+        resource: &str,
+        action: Res::Action,
+        outcome: Outcome,
+    ) -> Self {
+        assert!(
+            self.subjects.contains_key(subject),
+            "Unknown subject '{}' in decision matrix",
+            subject
+        );
+        assert!(
+            self.resources.contains_key(resource),
+            "Unknown resource '{}' in decision matrix",
+            resource
+        );
+
+        self.expectations.push(Expectation {
+            subject: subject.to_string(),
+            resource: resource.to_string(),
+            action,
+            outcome,
+        });
+        self
+    }
+
+    /// Adds the expectations from a YAML fixture, looking up actions by name.
+    #[cfg(feature = "yaml")]
+    pub fn expect_yaml(mut self, fixture: &str) -> Result<Self, FixtureError>
+    where
+        Res::Action: Enumerable,
+    {
+        type Fixture = BTreeMap<String, BTreeMap<String, BTreeMap<String, Outcome>>>;
+
+        let fixture: Fixture = serde_yaml::from_str(fixture)?;
+
+        for (subject, resources) in fixture {
+            if !self.subjects.contains_key(&subject) {
+                return Err(FixtureError::UnknownSubject(subject));
+            }
+
+            for (resource, actions) in resources {
+                if !self.resources.contains_key(&resource) {
+                    return Err(FixtureError::UnknownResource(resource));
+                }
+
+                for (action, outcome) in actions {
+                    let Some(action) = Res::Action::all().into_iter().find(|a| a.name() == action)
+                    else {
+                        return Err(FixtureError::UnknownAction(action));
+                    };
+
+                    self.expectations.push(Expectation {
+                        subject: subject.clone(),
+                        resource: resource.clone(),
+                        action,
+                        outcome,
+                    });
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Adds the expectations from a YAML fixture file.
+    #[cfg(feature = "yaml")]
+    pub fn load(self, path: impl AsRef<Path>) -> Result<Self, FixtureError>
+    where
+        Res::Action: Enumerable,
+    {
+        self.expect_yaml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Checks every expectation against the policy, returning all that weren't met.
+    pub fn check<P>(&self, policy: &P) -> Result<(), MatrixFailure>
+    where
+        P: Policy<Res, Subj>,
+    {
+        let mismatches: Vec<_> = self
+            .expectations
+            .iter()
+            .filter_map(|expectation| {
+                let decision = policy.decide(
+                    self.resources[&expectation.resource],
+                    self.subjects[&expectation.subject],
+                    &expectation.action,
+                );
+
+                (!expectation.outcome.matches(&decision)).then(|| Mismatch {
+                    subject: expectation.subject.clone(),
+                    resource: expectation.resource.clone(),
+                    action: expectation.action.name().to_string(),
+                    expected: expectation.outcome,
+                    actual: decision,
+                })
+            })
+            .collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(MatrixFailure {
+                expectations: self.expectations.len(),
+                mismatches,
+            })
+        }
+    }
+
+    /// Checks every expectation against the policy.
+    ///
+    /// # Panics
+    ///
+    /// With a table of the mismatches if any expectations weren't met.
+    #[track_caller]
+    pub fn assert<P>(&self, policy: &P)
+    where
+        P: Policy<Res, Subj>,
+    {
+        if let Err(failure) = self.check(policy) {
+            panic!("{}", failure);
+        }
+    }
+}
+
+/// An expectation that a policy didn't meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub subject: String,
+    pub resource: String,
+    pub action: String,
+    pub expected: Outcome,
+    pub actual: Decision,
+}
+
+/// The expectations of a [`DecisionMatrix`] that a policy didn't meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixFailure {
+    /// The total number of expectations checked.
+    pub expectations: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl std::error::Error for MatrixFailure {}
+
+impl Display for MatrixFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} of {} expectations failed:",
+            self.mismatches.len(),
+            self.expectations
+        )?;
+        writeln!(f)?;
+
+        let header = [
+            "subject", "resource", "action", "expected", "actual", "reason",
+        ];
+        let rows: Vec<[String; 6]> = self
+            .mismatches
+            .iter()
+            .map(|m| {
+                [
+                    m.subject.clone(),
+                    m.resource.clone(),
+                    m.action.clone(),
+                    m.expected.to_string(),
+                    m.actual.effect().to_string(),
+                    m.actual.reason().to_string(),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let format_row = |cells: &[&str]| {
+            cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .trim_end()
+                .to_string()
+        };
+
+        writeln!(f, "{}", format_row(&header))?;
+        write!(f, "{}", widths.map(|width| "-".repeat(width)).join("-+-"))?;
+
+        for row in &rows {
+            write!(f, "\n{}", format_row(&row.each_ref().map(String::as_str)))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Enumerable;
+
+    struct Document {
+        public: bool,
+    }
+
+    impl Resource for Document {
+        type Action = DocumentAction;
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    enum DocumentAction {
+        Read,
+        Delete,
+    }
+
+    impl Named for DocumentAction {
+        fn name(&self) -> &str {
+            match self {
+                DocumentAction::Read => "read",
+                DocumentAction::Delete => "delete",
+            }
+        }
+    }
+
+    impl Enumerable for DocumentAction {
+        fn all() -> Vec<Self> {
+            vec![DocumentAction::Read, DocumentAction::Delete]
+        }
+    }
+
+    struct User {
+        admin: bool,
+    }
+
+    impl Subject for User {}
+
+    struct AdminsOrPublicReads;
+
+    impl Policy<Document, User> for AdminsOrPublicReads {
+        fn decide(&self, document: &Document, user: &User, action: &DocumentAction) -> Decision {
+            if user.admin {
+                Decision::permit("Admins can do anything")
+            } else if document.public && *action == DocumentAction::Read {
+                Decision::permit("Public documents can be read")
+            } else {
+                Decision::deny("Only admins can do that")
+            }
+        }
+    }
+
+    #[test]
+    fn report_every_mismatch() {
+        let (admin, user) = (User { admin: true }, User { admin: false });
+        let (public, private) = (Document { public: true }, Document { public: false });
+
+        let matrix = DecisionMatrix::new()
+            .subject("admin", &admin)
+            .subject("user", &user)
+            .resource("public", &public)
+            .resource("private", &private)
+            .expect("admin", "private", DocumentAction::Delete, Outcome::Allow)
+            .expect("user", "public", DocumentAction::Read, Outcome::Allow)
+            .expect("user", "public", DocumentAction::Delete, Outcome::Allow)
+            .expect("user", "private", DocumentAction::Read, Outcome::Allow);
+
+        let failure = matrix.check(&AdminsOrPublicReads).unwrap_err();
+
+        assert_eq!(
+            failure.to_string(),
+            "2 of 4 expectations failed:
+
+subject | resource | action | expected | actual | reason
+--------+----------+--------+----------+--------+------------------------
+user    | public   | delete | allow    | Deny   | Only admins can do that
+user    | private  | read   | allow    | Deny   | Only admins can do that"
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn load_yaml_fixture() {
+        let (admin, user) = (User { admin: true }, User { admin: false });
+        let public = Document { public: true };
+
+        let matrix = DecisionMatrix::new()
+            .subject("admin", &admin)
+            .subject("user", &user)
+            .resource("public", &public)
+            .expect_yaml(
+                r#"
+admin:
+  public:
+    read: allow
+    delete: allow
+user:
+  public:
+    read: allow
+    delete: deny
+"#,
+            )
+            .unwrap();
+
+        matrix.assert(&AdminsOrPublicReads);
+
+        let error = DecisionMatrix::new()
+            .subject("user", &user)
+            .resource("public", &public)
+            .expect_yaml("user: { public: { publish: allow } }")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Unknown action 'publish' in fixture");
+    }
+}
@@ -3,57 +3,159 @@ use author_web::session::store::in_memory::{
 };
 use author_web::session::SessionConfig;
 
-use crate::schema::{auth_session, auth_user};
+use async_trait::async_trait;
+use author::combinator::AnyOf;
+use author::owner::{IdentifiedSubject, Owned, OwnerPolicy, QueryOwned};
+use author::query::{QueryPolicy, QueryResource};
+use author::rbac::asynchronous::{AsyncRbacSubject, AsyncResourceRbacPolicy};
+use author::rbac::{
+    GlobalRbacPolicy, GlobalRbacSubject, RbacQueryResource, RbacResource, RbacResourceWithRole,
+};
+use author::{Resource, Subject};
+use author_axum::policy::authorise;
 use author_axum::session::{Session, SessionManagerLayer};
 use author_axum::user::User;
 use author_web::session::store::SessionDataValueStorage;
 use author_web::user::UserSession;
 use axum::debug_handler;
-use axum::extract::Path;
+use axum::extract::{Path, State};
 use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::Router;
-use sea_orm::sea_query::Table;
-use sea_orm::{ConnectionTrait, Database, DbBackend, DbConn, DbErr, Schema};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, QueryFilter,
+    Schema, Set,
+};
+use std::collections::HashSet;
+use std::convert::Infallible;
 use std::net::{Ipv4Addr, SocketAddr};
 use tracing::debug;
 
 mod schema;
 
+use schema::report;
+
+#[derive(PartialEq, Eq, Hash, Debug)]
 enum Roles {
     User,
     Admin,
 }
 
+struct Report {
+    title: String,
+    owner: String,
+}
+
+impl From<report::Model> for Report {
+    fn from(model: report::Model) -> Self {
+        Report {
+            title: model.title,
+            owner: model.owner,
+        }
+    }
+}
+
+impl Resource for Report {
+    type Action = ReportAction;
+}
+
+impl RbacResourceWithRole for Report {
+    type Role = ReportRole;
+}
+
+impl RbacResource<ReportRole> for Report {
+    fn allowed_roles(&self, _action: &ReportAction) -> HashSet<ReportRole> {
+        HashSet::from([ReportRole::Reader])
+    }
+}
+
+// Reports are stored in the `report` table, so listing them can be filtered in the database
+impl QueryResource for Report {}
+
+impl Owned<String> for Report {
+    fn owner_id(&self) -> Option<String> {
+        Some(self.owner.clone())
+    }
+}
+
+impl QueryOwned<String> for Report {
+    fn owner_column() -> String {
+        "owner".to_string()
+    }
+}
+
+impl RbacResource<Roles> for Report {
+    fn allowed_roles(&self, action: &ReportAction) -> HashSet<Roles> {
+        Report::allowed_roles_for(action)
+    }
+}
+
+impl RbacQueryResource<Roles> for Report {
+    fn allowed_roles_for(_action: &ReportAction) -> HashSet<Roles> {
+        HashSet::from([Roles::Admin])
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ReportAction {
+    Read,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum ReportRole {
+    Reader,
+}
+
+struct Reader(String);
+
+impl Subject for Reader {}
+
+impl IdentifiedSubject for Reader {
+    type Id = String;
+
+    fn subject_id(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl GlobalRbacSubject for Reader {
+    type GlobalRole = Roles;
+
+    fn global_roles(&self) -> HashSet<Roles> {
+        if self.0 == "admin" {
+            HashSet::from([Roles::Admin])
+        } else {
+            HashSet::from([Roles::User])
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncRbacSubject<Report> for Reader {
+    type Error = Infallible;
+
+    async fn resource_roles(&self, report: &Report) -> Result<HashSet<ReportRole>, Infallible> {
+        // In a real application this might also query a table of readers each report is shared with
+        if self.0 == "admin" || report.owner == self.0 {
+            Ok(HashSet::from([ReportRole::Reader]))
+        } else {
+            Ok(HashSet::new())
+        }
+    }
+}
+
 // struct ExampleSession {
 //
 // }
-//
-// async fn setup_schema(db: &DbConn) -> Result<(), DbErr> {
-//     let schema = Schema::new(DbBackend::Sqlite);
-//
-//     // Derive from Entity
-//     let stmt = schema.create_table_from_entity(auth_session::Entity);
-//
-//     // Execute create table statement
-//     db.execute(db.get_database_backend().build(&stmt)).await?;
-//
-//     // Derive from Entity
-//     let stmt = schema.create_table_from_entity(auth_user::Entity);
-//
-//     // Execute create table statement
-//     db.execute(db.get_database_backend().build(&stmt)).await?;
-//
-//     Ok(())
-// }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialise tracing
     tracing_subscriber::fmt::init();
 
-    // let db: DbConn = Database::connect("sqlite::memory:").await?;
-    // setup_schema(&db)?;
+    let db = Database::connect("sqlite::memory:").await?;
+    setup_reports(&db).await?;
 
     // Create the session config
     let session_config = SessionConfig::default();
@@ -71,11 +173,14 @@ async fn main() -> anyhow::Result<()> {
             // .layer(RoleGuardLayer)
             .route("/session", get(session_handler))
             .route("/user", get(user_handler))
-            .route("/set_user/:name", get(set_user_handler))
+            .route("/set_user/{name}", get(set_user_handler))
+            .route("/report/{id}", get(report_handler))
+            .route("/reports", get(reports_handler))
             .layer(SessionManagerLayer::new(
                 session_config.clone(),
                 string_session_store,
-            )),
+            ))
+            .with_state(db),
     );
 
     // Run our app
@@ -94,7 +199,7 @@ async fn no_session_handler() -> String {
 
 #[debug_handler]
 async fn session_handler(
-    Session(mut session): Session<InMemorySession>,
+    Session(session): Session<InMemorySession>,
 ) -> Result<String, (StatusCode, &'static str)> {
     let value = {
         session
@@ -119,7 +224,7 @@ async fn user_handler(User(user, _): User<String, InMemorySession>) -> String {
 
 #[debug_handler]
 async fn set_user_handler(
-    Session(mut session): Session<InMemorySession>,
+    Session(session): Session<InMemorySession>,
     Path(name): Path<String>,
 ) -> Result<String, (StatusCode, &'static str)> {
     session
@@ -130,11 +235,87 @@ async fn set_user_handler(
     Ok(format!("User set to: {:?}", name))
 }
 
+#[debug_handler]
+async fn report_handler(
+    State(db): State<DatabaseConnection>,
+    User(user, _): User<String, InMemorySession>,
+    Path(id): Path<i32>,
+) -> Result<String, Response> {
+    let report: Report = report::Entity::find_by_id(id)
+        .one(&db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())?
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?
+        .into();
+
+    authorise(
+        &AsyncResourceRbacPolicy::new(),
+        &report,
+        &Reader(user),
+        &ReportAction::Read,
+    )
+    .await
+    .map_err(IntoResponse::into_response)?;
+
+    Ok(report.title)
+}
+
+/// Lists the reports the user owns, or every report for admins. Rather than loading every report
+/// and checking each one, the policy is turned into a filter so the database only returns the
+/// reports the user may read.
+#[debug_handler]
+async fn reports_handler(
+    State(db): State<DatabaseConnection>,
+    User(user, _): User<String, InMemorySession>,
+) -> Result<String, (StatusCode, String)> {
+    let policy = AnyOf((
+        OwnerPolicy::new([ReportAction::Read]),
+        GlobalRbacPolicy::new(),
+    ));
+
+    let filter = QueryPolicy::<Report, _>::query(&policy, &Reader(user), &ReportAction::Read)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let reports = report::Entity::find()
+        .filter(filter.to_sea_query())
+        .all(&db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(reports
+        .into_iter()
+        .map(|report| report.title)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+async fn setup_reports(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let schema = Schema::new(db.get_database_backend());
+    let stmt = schema.create_table_from_entity(report::Entity);
+    db.execute(db.get_database_backend().build(&stmt)).await?;
+
+    for (title, owner) in [
+        ("Quarterly sales", "alice"),
+        ("Marketing budget", "bob"),
+        ("Hiring plan", "alice"),
+    ] {
+        report::ActiveModel {
+            title: Set(title.to_string()),
+            owner: Set(owner.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
 // #[debug_handler]
 // async fn role_handler() -> String {}
 
 // #[debug_handler]
-// async fn make_me_admin(Session(mut session): Session<InMemorySession>) -> String {}
+// async fn make_me_admin(Session(session): Session<InMemorySession>) -> String {}
 //
 // #[debug_handler]
 // async fn user_with_role_handler(user: UserWithRole<Role>) -> String {}
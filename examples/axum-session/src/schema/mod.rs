@@ -1,2 +1 @@
-pub mod auth_session;
-pub mod auth_user;
+pub mod report;
@@ -1,13 +1,14 @@
-//! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.1
+//! `SeaORM` Entity for the reports that users can list.
 
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
-#[sea_orm(table_name = "auth_user")]
+#[sea_orm(table_name = "report")]
 pub struct Model {
     #[sea_orm(primary_key)]
-    pub id: u32,
-    pub name: Option<String>,
+    pub id: i32,
+    pub title: String,
+    pub owner: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,158 @@
+use assert_matches::assert_matches;
+use author::abac::{AbacPolicy, AbacResource, AbacSubject, Environment, Rule, Value};
+use author::combinator::FirstApplicable;
+use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+use author::{Named, Policy, Resource, Subject};
+use std::collections::HashSet;
+
+struct User {
+    department: String,
+    roles: HashSet<GlobalRole>,
+}
+
+impl Subject for User {}
+
+impl AbacSubject for User {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "department" => Some(Value::from(&self.department)),
+            _ => None,
+        }
+    }
+}
+
+impl GlobalRbacSubject for User {
+    type GlobalRole = GlobalRole;
+
+    fn global_roles(&self) -> HashSet<Self::GlobalRole> {
+        self.roles.clone()
+    }
+}
+
+struct Invoice {
+    department: String,
+    status: InvoiceStatus,
+    amount: f64,
+}
+
+enum InvoiceStatus {
+    Draft,
+    Sent,
+}
+
+impl Resource for Invoice {
+    type Action = InvoiceAction;
+}
+
+impl AbacResource for Invoice {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "department" => Some(Value::from(&self.department)),
+            "status" => Some(Value::from(match self.status {
+                InvoiceStatus::Draft => "draft",
+                InvoiceStatus::Sent => "sent",
+            })),
+            "amount" => Some(Value::from(self.amount)),
+            _ => None,
+        }
+    }
+}
+
+impl RbacResource<GlobalRole> for Invoice {
+    fn allowed_roles(&self, action: &InvoiceAction) -> HashSet<GlobalRole> {
+        match action {
+            InvoiceAction::Read => HashSet::from([GlobalRole::Auditor]),
+            InvoiceAction::Edit => HashSet::new(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum InvoiceAction {
+    Read,
+    Edit,
+}
+
+impl Named for InvoiceAction {
+    fn name(&self) -> &str {
+        match self {
+            InvoiceAction::Read => "read",
+            InvoiceAction::Edit => "edit",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum GlobalRole {
+    Employee,
+    Auditor,
+}
+
+fn main() -> anyhow::Result<()> {
+    let abac = AbacPolicy::new([
+        Rule::permit(
+            "read-own-department",
+            "subject.department == resource.department",
+        )?
+        .for_actions(["read"]),
+        Rule::permit(
+            "edit-own-department-drafts",
+            r#"subject.department == resource.department && resource.status == "draft""#,
+        )?
+        .for_actions(["edit"]),
+        Rule::forbid(
+            "large-invoices-outside-office-hours",
+            "resource.amount >= 10000 && !env.office_hours",
+        )?
+        .for_actions(["edit"]),
+    ])
+    .with_environment(|| Environment::from([("office_hours".to_string(), Value::from(true))]));
+
+    // Auditors can read any invoice regardless of department
+    let policy = FirstApplicable((abac, GlobalRbacPolicy::new()));
+
+    let sales = User {
+        department: "sales".to_string(),
+        roles: HashSet::from([GlobalRole::Employee]),
+    };
+
+    let auditor = User {
+        department: "finance".to_string(),
+        roles: HashSet::from([GlobalRole::Auditor]),
+    };
+
+    let draft = Invoice {
+        department: "sales".to_string(),
+        status: InvoiceStatus::Draft,
+        amount: 120.0,
+    };
+
+    let sent = Invoice {
+        department: "sales".to_string(),
+        status: InvoiceStatus::Sent,
+        amount: 120.0,
+    };
+
+    assert_matches!(
+        policy.authorise(&draft, &sales, &InvoiceAction::Edit),
+        Ok(_)
+    );
+    assert_matches!(policy.authorise(&sent, &sales, &InvoiceAction::Read), Ok(_));
+    assert_matches!(
+        policy.authorise(&sent, &sales, &InvoiceAction::Edit),
+        Err(_)
+    );
+    assert_matches!(
+        policy.authorise(&sent, &auditor, &InvoiceAction::Read),
+        Ok(_)
+    );
+    assert_matches!(
+        policy.authorise(&draft, &auditor, &InvoiceAction::Edit),
+        Err(_)
+    );
+
+    let decision = policy.decide(&draft, &sales, &InvoiceAction::Edit);
+    assert_eq!(decision.rule(), Some("edit-own-department-drafts"));
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use assert_matches::assert_matches;
+use author::abac::Value;
+use author::casbin::{CasbinPolicy, CasbinResource, CasbinSubject};
+use author::{Named, Policy, Resource, Subject};
+
+#[derive(Subject)]
+struct User {
+    name: String,
+}
+
+impl CasbinSubject for User {
+    fn casbin_subject(&self) -> Value {
+        Value::from(&self.name)
+    }
+}
+
+#[derive(Resource)]
+#[author(action = CustomerAction)]
+struct Customer {
+    id: u32,
+}
+
+impl CasbinResource for Customer {
+    fn casbin_object(&self) -> Value {
+        Value::from(format!("/customers/{}", self.id))
+    }
+}
+
+#[derive(Resource)]
+#[author(action = ProductAction)]
+struct Product {
+    id: u32,
+}
+
+impl CasbinResource for Product {
+    fn casbin_object(&self) -> Value {
+        Value::from(format!("/products/{}", self.id))
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Named)]
+enum CustomerAction {
+    Read,
+    Write,
+}
+
+#[derive(PartialEq, Eq, Hash, Named)]
+enum ProductAction {
+    Read,
+    Write,
+    Delete,
+}
+
+fn main() -> anyhow::Result<()> {
+    // The model and policy are unchanged from the Casbin files they were migrated from
+    let policy = CasbinPolicy::parse(include_str!("model.conf"), include_str!("policy.csv"))?;
+
+    let user = |name: &str| User {
+        name: name.to_string(),
+    };
+    let (alice, bob, carol, dave) = (user("alice"), user("bob"), user("carol"), user("dave"));
+
+    let customer = Customer { id: 42 };
+    let product = Product { id: 7 };
+
+    // Customer assertions
+    assert_matches!(
+        policy.authorise(&customer, &alice, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&customer, &bob, &CustomerAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&customer, &bob, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&customer, &carol, &CustomerAction::Read),
+        Err(_)
+    );
+
+    // Dave is support staff, but contractors are explicitly denied access to customers
+    let decision = policy.decide(&customer, &dave, &CustomerAction::Read);
+    assert!(decision.is_deny());
+    assert_eq!(
+        decision.rule(),
+        Some("p, contractor, /customers/:id, read, deny")
+    );
+
+    // Product assertions
+    assert_matches!(
+        policy.authorise(&product, &carol, &ProductAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&product, &carol, &ProductAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&product, &alice, &ProductAction::Delete),
+        Ok(_)
+    );
+
+    // Requests can also be given as Casbin would receive them
+    assert!(policy
+        .enforce(&["bob".into(), "/products/7".into(), "read".into()])
+        .is_permit());
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use assert_matches::assert_matches;
+use author::combinator::{AllOf, AnyOf, Not};
+use author::rbac::{
+    GlobalRbacPolicy, GlobalRbacSubject, RbacResource, RbacResourceWithRole, RbacSubject,
+    ResourceRbacPolicy,
+};
+use author::{Decision, Policy, Resource, Subject};
+use std::collections::HashSet;
+
+struct User {
+    name: String,
+    roles: HashSet<GlobalRole>,
+}
+
+impl Subject for User {}
+
+impl GlobalRbacSubject for User {
+    type GlobalRole = GlobalRole;
+
+    fn global_roles(&self) -> HashSet<Self::GlobalRole> {
+        self.roles.clone()
+    }
+}
+
+impl RbacSubject<Document> for User {
+    fn resource_roles(&self, resource: &Document) -> HashSet<DocumentRole> {
+        if resource.owner == self.name {
+            HashSet::from([DocumentRole::Owner])
+        } else {
+            HashSet::new()
+        }
+    }
+}
+
+struct Document {
+    owner: String,
+}
+
+impl Resource for Document {
+    type Action = DocumentAction;
+}
+
+impl RbacResourceWithRole for Document {
+    type Role = DocumentRole;
+}
+
+impl RbacResource<DocumentRole> for Document {
+    fn allowed_roles(&self, action: &Self::Action) -> HashSet<DocumentRole> {
+        match action {
+            DocumentAction::Read => HashSet::from([DocumentRole::Owner]),
+            DocumentAction::Write => HashSet::from([DocumentRole::Owner]),
+        }
+    }
+}
+
+impl RbacResource<GlobalRole> for Document {
+    fn allowed_roles(&self, action: &Self::Action) -> HashSet<GlobalRole> {
+        match action {
+            DocumentAction::Read => HashSet::from([GlobalRole::Admin, GlobalRole::Auditor]),
+            DocumentAction::Write => HashSet::from([GlobalRole::Admin]),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum DocumentAction {
+    Read,
+    Write,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum GlobalRole {
+    User,
+    Admin,
+    Auditor,
+    Suspended,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum DocumentRole {
+    Owner,
+}
+
+/// Denies everything to suspended users, regardless of any other roles they hold.
+struct SuspendedPolicy;
+
+impl Policy<Document, User> for SuspendedPolicy {
+    fn decide(&self, _resource: &Document, subject: &User, _action: &DocumentAction) -> Decision {
+        if subject.roles.contains(&GlobalRole::Suspended) {
+            Decision::permit("User is suspended").with_rule("suspended")
+        } else {
+            Decision::deny("User is not suspended")
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    // Global admin OR resource owner, as long as the user isn't suspended
+    let policy = AllOf::new((
+        AnyOf::new((GlobalRbacPolicy::new(), ResourceRbacPolicy::new())),
+        Not::new(SuspendedPolicy),
+    ));
+
+    let owner = User {
+        name: "Owner".to_string(),
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let suspended_owner = User {
+        name: "Owner".to_string(),
+        roles: HashSet::from([GlobalRole::User, GlobalRole::Suspended]),
+    };
+
+    let auditor = User {
+        name: "Auditor".to_string(),
+        roles: HashSet::from([GlobalRole::User, GlobalRole::Auditor]),
+    };
+
+    let admin_user = User {
+        name: "Admin".to_string(),
+        roles: HashSet::from([GlobalRole::User, GlobalRole::Admin]),
+    };
+
+    let stranger = User {
+        name: "Stranger".to_string(),
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let document = Document {
+        owner: "Owner".to_string(),
+    };
+
+    assert_matches!(
+        policy.authorise(&document, &owner, &DocumentAction::Write),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&document, &suspended_owner, &DocumentAction::Read),
+        Err(_)
+    );
+
+    let decision = policy.decide(&document, &suspended_owner, &DocumentAction::Read);
+    assert_eq!(decision.rule(), Some("NOT suspended"));
+
+    assert_matches!(
+        policy.authorise(&document, &auditor, &DocumentAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&document, &auditor, &DocumentAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&document, &admin_user, &DocumentAction::Write),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&document, &stranger, &DocumentAction::Read),
+        Err(_)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use assert_matches::assert_matches;
+use author::rbac::config::{ConfigError, ConfigRbacPolicy, RbacPolicyConfig, RbacSchema};
+use author::rbac::GlobalRbacSubject;
+use author::{Enumerable, Named, Policy, Resource, Subject};
+use std::collections::HashSet;
+
+#[derive(Subject, GlobalRbacSubject)]
+#[author(role = GlobalRole)]
+struct User {
+    #[author(global_roles)]
+    roles: HashSet<GlobalRole>,
+}
+
+#[derive(Resource)]
+#[author(action = CustomerAction, name = "customer")]
+struct Customer;
+
+#[derive(Resource)]
+#[author(action = ProductAction, name = "product")]
+struct Product;
+
+#[derive(PartialEq, Eq, Hash, Named, Enumerable)]
+enum CustomerAction {
+    Read,
+    Write,
+}
+
+#[derive(PartialEq, Eq, Hash, Named, Enumerable)]
+enum ProductAction {
+    Read,
+    Write,
+    Delete,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Named, Enumerable)]
+enum GlobalRole {
+    User,
+    Support,
+    Admin,
+}
+
+fn main() -> anyhow::Result<()> {
+    let schema = RbacSchema::new()
+        .resource::<Customer>()
+        .resource::<Product>()
+        .roles::<GlobalRole>();
+
+    let config = RbacPolicyConfig::from_yaml(include_str!("policy.yaml"))?;
+    let policy = ConfigRbacPolicy::new(config, &schema)?;
+
+    let user = User {
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let support_user = User {
+        roles: HashSet::from([GlobalRole::User, GlobalRole::Support]),
+    };
+
+    let admin_user = User {
+        roles: HashSet::from([GlobalRole::User, GlobalRole::Admin]),
+    };
+
+    assert_matches!(
+        policy.authorise(&Customer, &user, &CustomerAction::Read),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Customer, &support_user, &CustomerAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Customer, &support_user, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Customer, &admin_user, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Product, &user, &ProductAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Product, &support_user, &ProductAction::Delete),
+        Err(_)
+    );
+
+    // Mistakes in the config are caught when it is loaded rather than silently denying access
+    let config = RbacPolicyConfig::from_yaml(
+        r#"
+        resources:
+          customer:
+            actions:
+              read:
+                allowed roles: [suport]
+        "#,
+    )?;
+
+    assert_matches!(
+        ConfigRbacPolicy::new(config, &schema),
+        Err(ConfigError::Invalid(_))
+    );
+
+    Ok(())
+}
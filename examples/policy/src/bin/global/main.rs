@@ -1,99 +1,70 @@
 use assert_matches::assert_matches;
-use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
-use author::{Policy, Resource, Subject};
+use author::rbac::{
+    GlobalRbacPolicy, GlobalRbacSubject, RbacAction, RbacResource, RoleHierarchy, RoleOverride,
+};
+use author::{Enumerable, Policy, Resource, Subject};
 use std::collections::HashSet;
 
+#[derive(Subject, GlobalRbacSubject)]
+#[author(role = GlobalRole)]
 struct User {
-    name: String,
+    #[author(global_roles)]
     roles: HashSet<GlobalRole>,
 }
 
-impl Subject for User {}
+#[derive(Resource, RbacResource)]
+#[author(action = CustomerAction, role = GlobalRole)]
+struct Customer;
 
-impl GlobalRbacSubject for User {
-    type GlobalRole = GlobalRole;
+#[derive(Resource, RbacResource)]
+#[author(action = ProductAction, role = GlobalRole)]
+#[author(allow(Read, roles = [User]))]
+#[author(allow(Write, Delete, roles = [Admin]))]
+struct Product;
 
-    fn global_roles(&self) -> HashSet<Self::GlobalRole> {
-        self.roles.clone()
-    }
-}
-
-struct Customer {
-    name: String,
-}
-
-impl Resource for Customer {
-    type Action = CustomerAction;
-}
-
-impl RbacResource<GlobalRole> for Customer {
-    fn allowed_roles(&self, action: &Self::Action) -> HashSet<GlobalRole> {
-        match action {
-            CustomerAction::Read => HashSet::from([GlobalRole::Admin]),
-            CustomerAction::Write => HashSet::from([GlobalRole::Admin]),
-        }
-    }
-}
-
-struct Product {
-    name: String,
-    cost: f32,
-}
-
-impl Resource for Product {
-    type Action = ProductAction;
-}
-
-impl RbacResource<GlobalRole> for Product {
-    fn allowed_roles(&self, action: &Self::Action) -> HashSet<GlobalRole> {
-        match action {
-            ProductAction::Read => HashSet::from([GlobalRole::Admin, GlobalRole::User]),
-            ProductAction::Write => HashSet::from([GlobalRole::Admin]),
-            ProductAction::Delete => HashSet::from([GlobalRole::Admin]),
-        }
-    }
-}
-
-#[derive(PartialEq, Eq, Hash)]
+// Customers take their roles from the actions, since no `allow` is given
+#[derive(PartialEq, Eq, Hash, RbacAction)]
+#[author(role = GlobalRole)]
 enum CustomerAction {
+    #[author(roles = [Admin])]
     Read,
+    #[author(roles = [Admin])]
     Write,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Debug, Enumerable)]
 enum ProductAction {
     Read,
     Write,
     Delete,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 enum GlobalRole {
     User,
     Admin,
 }
 
 fn main() -> anyhow::Result<()> {
-    let policy = GlobalRbacPolicy::new();
+    // Admins inherit everything users can do, so resources only need to list the least
+    // privileged role allowed to perform each action
+    let hierarchy = RoleHierarchy::builder()
+        .inherit(GlobalRole::Admin, GlobalRole::User)
+        .build()?;
+
+    let policy = GlobalRbacPolicy::with_hierarchy(hierarchy);
 
     let user = User {
-        name: "User".to_string(),
         roles: HashSet::from([GlobalRole::User]),
     };
 
     let admin_user = User {
-        name: "Admin".to_string(),
-        roles: HashSet::from([GlobalRole::User, GlobalRole::Admin]),
+        roles: HashSet::from([GlobalRole::Admin]),
     };
 
-    let customer = Customer {
-        name: "Customer".to_string(),
-    };
+    let customer = Customer;
 
-    let product = Product {
-        name: "Product".to_string(),
-        cost: 0.50,
-    };
+    let product = Product;
 
     // Customer assertions
     assert_matches!(
@@ -116,6 +87,17 @@ fn main() -> anyhow::Result<()> {
         Ok(_)
     );
 
+    let decision = policy.decide(&customer, &user, &CustomerAction::Read);
+    assert!(decision.is_deny());
+    assert_eq!(
+        decision.reason(),
+        "Subject holds none of the global roles allowed to perform this action; allowed roles are [Admin]"
+    );
+
+    let decision = policy.decide(&customer, &admin_user, &CustomerAction::Read);
+    assert!(decision.is_permit());
+    assert_eq!(decision.rule(), Some("[Admin]"));
+
     // Product assertions
     assert_matches!(
         policy.authorise(&product, &user, &ProductAction::Read),
@@ -147,5 +129,22 @@ fn main() -> anyhow::Result<()> {
         Ok(_)
     );
 
+    // List everything a subject may do, such as to decide which buttons to show
+    assert_eq!(
+        policy.permitted_actions(&product, &user),
+        vec![ProductAction::Read]
+    );
+
+    // Preview what a promotion would allow before making it
+    let promoted = RoleOverride::of(&user).grant(GlobalRole::Admin);
+    assert_eq!(
+        policy.permitted_actions(&product, &promoted),
+        vec![
+            ProductAction::Read,
+            ProductAction::Write,
+            ProductAction::Delete
+        ]
+    );
+
     Ok(())
 }
@@ -0,0 +1,180 @@
+use assert_matches::assert_matches;
+use author::abac::{AbacResource, AbacSubject, Environment, Value};
+use author::lang::{EntityUid, LangError, PolicyDocument, Principal, Schema, Type};
+use author::{Enumerable, Named, Policy, Resource, Subject};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Subject)]
+struct User {
+    name: String,
+    region: String,
+    roles: Vec<GlobalRole>,
+}
+
+impl AbacSubject for User {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "region" => Some(Value::from(&self.region)),
+            _ => None,
+        }
+    }
+}
+
+impl Principal for User {
+    fn uid(&self) -> EntityUid {
+        EntityUid::new("User", &self.name)
+    }
+
+    fn parents(&self) -> Vec<EntityUid> {
+        self.roles
+            .iter()
+            .map(|role| EntityUid::new("Role", role.name()))
+            .collect()
+    }
+}
+
+#[derive(Resource)]
+#[author(action = CustomerAction, name = "customer")]
+struct Customer {
+    region: String,
+}
+
+impl AbacResource for Customer {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "region" => Some(Value::from(&self.region)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource)]
+#[author(action = ProductAction, name = "product")]
+struct Product {
+    published: bool,
+}
+
+impl AbacResource for Product {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "published" => Some(Value::from(self.published)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Named, Enumerable)]
+enum CustomerAction {
+    Read,
+    Write,
+    Delete,
+}
+
+#[derive(PartialEq, Eq, Hash, Named, Enumerable)]
+enum ProductAction {
+    Read,
+    Write,
+}
+
+#[derive(Named, Enumerable)]
+enum GlobalRole {
+    Support,
+    Admin,
+}
+
+fn main() -> anyhow::Result<()> {
+    let schema = Schema::new()
+        .resource::<Customer>([("region", Type::String)])
+        .resource::<Product>([("published", Type::Bool)])
+        .principal("User", [("region", Type::String)])
+        .roles::<GlobalRole>("Role")
+        .context([("mfa", Type::Bool)]);
+
+    let mfa = Arc::new(AtomicBool::new(false));
+    let mfa_context = mfa.clone();
+
+    let policy = PolicyDocument::parse_with_schema(include_str!("policies.txt"), &schema)?
+        .with_context(move || {
+            Environment::from([("mfa".to_string(), mfa_context.load(Ordering::SeqCst).into())])
+        });
+
+    let support = User {
+        name: "sam".to_string(),
+        region: "eu".to_string(),
+        roles: vec![GlobalRole::Support],
+    };
+
+    let admin = User {
+        name: "alex".to_string(),
+        region: "us".to_string(),
+        roles: vec![GlobalRole::Admin],
+    };
+
+    let eu_customer = Customer {
+        region: "eu".to_string(),
+    };
+
+    let us_customer = Customer {
+        region: "us".to_string(),
+    };
+
+    // Customer assertions
+    let decision = policy.decide(&eu_customer, &support, &CustomerAction::Read);
+    assert!(decision.is_permit());
+    assert_eq!(decision.rule(), Some("support-read"));
+
+    assert_matches!(
+        policy.authorise(&us_customer, &support, &CustomerAction::Read),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&eu_customer, &support, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&us_customer, &admin, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    let decision = policy.decide(&us_customer, &admin, &CustomerAction::Delete);
+    assert!(decision.is_deny());
+    assert_eq!(decision.rule(), Some("delete-requires-mfa"));
+
+    mfa.store(true, Ordering::SeqCst);
+    assert_matches!(
+        policy.authorise(&us_customer, &admin, &CustomerAction::Delete),
+        Ok(_)
+    );
+
+    // Product assertions
+    assert_matches!(
+        policy.authorise(&Product { published: true }, &support, &ProductAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(
+            &Product { published: false },
+            &support,
+            &ProductAction::Read
+        ),
+        Err(_)
+    );
+
+    // Mistakes are reported against the schema with the location of the problem
+    let source = "permit (principal in Role::\"support\", action == Action::\"read\", resource is product)\n    when { resource.region == principal.region };";
+    let Err(LangError::Invalid(errors)) = PolicyDocument::parse_with_schema(source, &schema) else {
+        panic!("expected policy to be invalid");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message,
+        "unknown attribute 'region' on resource type 'product'"
+    );
+    assert_eq!(errors[0].span.line_column(source), (2, 12));
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use assert_matches::assert_matches;
+use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource, RoleHierarchy};
+use author::testing::{DecisionMatrix, Outcome};
+use author::{Enumerable, Named, Resource, Subject};
+use std::collections::HashSet;
+
+#[derive(Subject, GlobalRbacSubject)]
+#[author(role = GlobalRole)]
+struct User {
+    #[author(global_roles)]
+    roles: HashSet<GlobalRole>,
+}
+
+#[derive(Resource, RbacResource)]
+#[author(action = ProductAction, role = GlobalRole)]
+#[author(allow(Read, roles = [User]))]
+#[author(allow(Write, Delete, roles = [Admin]))]
+struct Product {
+    name: String,
+}
+
+#[derive(PartialEq, Eq, Hash, Named, Enumerable)]
+enum ProductAction {
+    Read,
+    Write,
+    Delete,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum GlobalRole {
+    User,
+    Admin,
+}
+
+fn main() -> anyhow::Result<()> {
+    let hierarchy = RoleHierarchy::builder()
+        .inherit(GlobalRole::Admin, GlobalRole::User)
+        .build()?;
+
+    let policy = GlobalRbacPolicy::with_hierarchy(hierarchy);
+
+    let user = User {
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let admin = User {
+        roles: HashSet::from([GlobalRole::Admin]),
+    };
+
+    let public = Product {
+        name: "public".to_string(),
+    };
+
+    let discontinued = Product {
+        name: "discontinued".to_string(),
+    };
+
+    // Every combination of subject, resource and action is checked, rather than stopping at the
+    // first that fails. Products are labelled by name in the expectations.
+    DecisionMatrix::new()
+        .subject("user", &user)
+        .subject("admin", &admin)
+        .resource(&public.name, &public)
+        .resource(&discontinued.name, &discontinued)
+        .expect_yaml(include_str!("expectations.yaml"))?
+        .assert(&policy);
+
+    // Mismatches are reported together in a table
+    let failure = DecisionMatrix::new()
+        .subject("user", &user)
+        .resource(&public.name, &public)
+        .expect("user", "public", ProductAction::Write, Outcome::Allow)
+        .expect("user", "public", ProductAction::Delete, Outcome::Allow)
+        .check(&policy);
+
+    assert_matches!(failure, Err(failure) if failure.mismatches.len() == 2);
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use assert_matches::assert_matches;
+use author::combinator::AnyOf;
+use author::owner::{IdentifiedSubject, Owned, OwnerPolicy};
+use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+use author::{Policy, Resource, Subject};
+use std::collections::HashSet;
+
+#[derive(Subject, GlobalRbacSubject)]
+#[author(role = GlobalRole)]
+struct User {
+    id: u64,
+    #[author(global_roles)]
+    roles: HashSet<GlobalRole>,
+}
+
+impl IdentifiedSubject for User {
+    type Id = u64;
+
+    fn subject_id(&self) -> u64 {
+        self.id
+    }
+}
+
+#[derive(Resource, RbacResource)]
+#[author(action = PostAction, role = GlobalRole)]
+#[author(allow(Read, roles = [Reader, Moderator]))]
+#[author(allow(Delete, roles = [Moderator]))]
+struct Post {
+    author_id: u64,
+}
+
+impl Owned<u64> for Post {
+    fn owner_id(&self) -> Option<u64> {
+        Some(self.author_id)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum PostAction {
+    Read,
+    Edit,
+    Delete,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum GlobalRole {
+    Reader,
+    Moderator,
+}
+
+fn main() -> anyhow::Result<()> {
+    // Authors can edit and delete their own posts, and moderators can delete anyone's, but nobody
+    // can edit someone else's
+    let policy = AnyOf::new((
+        OwnerPolicy::new([PostAction::Read, PostAction::Edit, PostAction::Delete]),
+        GlobalRbacPolicy::new(),
+    ));
+
+    let author = User {
+        id: 1,
+        roles: HashSet::from([GlobalRole::Reader]),
+    };
+
+    let reader = User {
+        id: 2,
+        roles: HashSet::from([GlobalRole::Reader]),
+    };
+
+    let moderator = User {
+        id: 3,
+        roles: HashSet::from([GlobalRole::Moderator]),
+    };
+
+    let post = Post { author_id: 1 };
+
+    assert_matches!(policy.authorise(&post, &author, &PostAction::Edit), Ok(_));
+    assert_matches!(policy.authorise(&post, &author, &PostAction::Delete), Ok(_));
+
+    assert_matches!(policy.authorise(&post, &reader, &PostAction::Read), Ok(_));
+    assert_matches!(policy.authorise(&post, &reader, &PostAction::Edit), Err(_));
+    assert_matches!(
+        policy.authorise(&post, &reader, &PostAction::Delete),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&post, &moderator, &PostAction::Edit),
+        Err(_)
+    );
+    assert_matches!(
+        policy.authorise(&post, &moderator, &PostAction::Delete),
+        Ok(_)
+    );
+
+    let decision = policy.decide(&post, &author, &PostAction::Edit);
+    assert_eq!(decision.rule(), Some("owner"));
+
+    Ok(())
+}
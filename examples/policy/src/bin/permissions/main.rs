@@ -0,0 +1,169 @@
+use assert_matches::assert_matches;
+use author::rbac::{
+    GlobalRbacSubject, Permission, PermissionRbacPolicy, PermissionResource, Role, RoleHierarchy,
+};
+use author::{Named, NamedResource, Policy, Resource, Subject};
+use std::collections::HashSet;
+
+struct User {
+    roles: HashSet<GlobalRole>,
+}
+
+impl Subject for User {}
+
+impl GlobalRbacSubject for User {
+    type GlobalRole = GlobalRole;
+
+    fn global_roles(&self) -> HashSet<Self::GlobalRole> {
+        self.roles.clone()
+    }
+}
+
+struct Customer;
+
+impl Resource for Customer {
+    type Action = CustomerAction;
+}
+
+impl NamedResource for Customer {
+    const RESOURCE_NAME: &'static str = "customer";
+}
+
+impl PermissionResource for Customer {}
+
+struct Product;
+
+impl Resource for Product {
+    type Action = ProductAction;
+}
+
+impl NamedResource for Product {
+    const RESOURCE_NAME: &'static str = "product";
+}
+
+impl PermissionResource for Product {
+    fn required_permissions(&self, action: &ProductAction) -> Vec<Permission> {
+        match action {
+            // Deleting a product also removes it from customers' order history
+            ProductAction::Delete => vec![
+                Permission::of::<Self>(action),
+                Permission::new("customer", "write"),
+            ],
+            _ => vec![Permission::of::<Self>(action)],
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum CustomerAction {
+    Read,
+    Write,
+}
+
+impl Named for CustomerAction {
+    fn name(&self) -> &str {
+        match self {
+            CustomerAction::Read => "read",
+            CustomerAction::Write => "write",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ProductAction {
+    Read,
+    Write,
+    Delete,
+}
+
+impl Named for ProductAction {
+    fn name(&self) -> &str {
+        match self {
+            ProductAction::Read => "read",
+            ProductAction::Write => "write",
+            ProductAction::Delete => "delete",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+enum GlobalRole {
+    User,
+    Merchandiser,
+    Admin,
+}
+
+impl Named for GlobalRole {
+    fn name(&self) -> &str {
+        match self {
+            GlobalRole::User => "user",
+            GlobalRole::Merchandiser => "merchandiser",
+            GlobalRole::Admin => "admin",
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let roles = [
+        Role::new("user").with_permissions(["product:read"])?,
+        Role::new("merchandiser").with_permissions(["product:*"])?,
+        Role::new("admin").with_permissions(["customer:*"])?,
+    ];
+
+    let hierarchy = RoleHierarchy::builder()
+        .inherit("merchandiser".to_string(), "user".to_string())
+        .inherit("admin".to_string(), "merchandiser".to_string())
+        .build()?;
+
+    let policy = PermissionRbacPolicy::with_hierarchy(roles, hierarchy);
+
+    let user = User {
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let merchandiser = User {
+        roles: HashSet::from([GlobalRole::Merchandiser]),
+    };
+
+    let admin_user = User {
+        roles: HashSet::from([GlobalRole::Admin]),
+    };
+
+    assert_matches!(
+        policy.authorise(&Product, &user, &ProductAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Product, &user, &ProductAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Product, &merchandiser, &ProductAction::Write),
+        Ok(_)
+    );
+
+    // Merchandisers can manage products, but deleting one also needs customer write access
+    assert_matches!(
+        policy.authorise(&Product, &merchandiser, &ProductAction::Delete),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Customer, &merchandiser, &CustomerAction::Read),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Product, &admin_user, &ProductAction::Delete),
+        Ok(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&Customer, &admin_user, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    Ok(())
+}
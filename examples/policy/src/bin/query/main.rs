@@ -0,0 +1,115 @@
+use assert_matches::assert_matches;
+use author::abac::{AbacPolicy, AbacResource, AbacSubject, Rule, Value};
+use author::query::{Predicate, QueryPolicy, QueryResource};
+use author::{Named, Policy, Resource, Subject};
+use sea_query::{Alias, Asterisk, PostgresQueryBuilder, Query};
+
+struct User {
+    id: i64,
+    teams: Vec<i64>,
+}
+
+impl Subject for User {}
+
+impl AbacSubject for User {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "id" => Some(Value::from(self.id)),
+            "teams" => Some(Value::from(self.teams.clone())),
+            _ => None,
+        }
+    }
+}
+
+struct Project {
+    owner_id: i64,
+    team_id: i64,
+    archived: bool,
+}
+
+impl Resource for Project {
+    type Action = ProjectAction;
+}
+
+impl AbacResource for Project {
+    fn attribute(&self, name: &str) -> Option<Value> {
+        match name {
+            "owner" => Some(Value::from(self.owner_id)),
+            "team" => Some(Value::from(self.team_id)),
+            "archived" => Some(Value::from(self.archived)),
+            _ => None,
+        }
+    }
+}
+
+// Attributes are named differently to the columns that store them
+impl QueryResource for Project {
+    fn column(attribute: &str) -> Option<String> {
+        match attribute {
+            "owner" => Some("owner_id".to_string()),
+            "team" => Some("team_id".to_string()),
+            "archived" => Some("archived".to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ProjectAction {
+    List,
+    Edit,
+}
+
+impl Named for ProjectAction {
+    fn name(&self) -> &str {
+        match self {
+            ProjectAction::List => "list",
+            ProjectAction::Edit => "edit",
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let policy = AbacPolicy::new([
+        Rule::permit(
+            "owner-or-team-member",
+            "resource.owner == subject.id || resource.team in subject.teams",
+        )?,
+        Rule::forbid("archived", "resource.archived")?.for_actions(["edit"]),
+    ]);
+
+    let user = User {
+        id: 7,
+        teams: vec![3, 4],
+    };
+
+    // The same policy can still be checked against individual projects
+    let project = Project {
+        owner_id: 1,
+        team_id: 3,
+        archived: false,
+    };
+    assert_matches!(
+        policy.authorise(&project, &user, &ProjectAction::Edit),
+        Ok(_)
+    );
+
+    // Or turned into a filter for listing every project the user may see
+    let filter: Predicate = QueryPolicy::<Project, _>::query(&policy, &user, &ProjectAction::List)?;
+    assert_eq!(filter.to_string(), "owner_id = 7 OR team_id IN (3, 4)");
+
+    let filter = QueryPolicy::<Project, _>::query(&policy, &user, &ProjectAction::Edit)?;
+    let sql = Query::select()
+        .column(Asterisk)
+        .from(Alias::new("project"))
+        .cond_where(filter)
+        .to_string(PostgresQueryBuilder);
+
+    // As in memory, a project whose `archived` column is null fails the rule and is denied
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM "project" WHERE ("owner_id" = 7 OR "team_id" IN (3, 4)) AND ("archived" <> TRUE AND "archived" IS NOT NULL)"#
+    );
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use assert_matches::assert_matches;
+use author::field::{Field, FieldAction, FieldResource, Redactor};
+use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource};
+use author::{Policy, Resource, Subject};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+
+#[derive(Subject, GlobalRbacSubject)]
+#[author(role = GlobalRole)]
+struct User {
+    #[author(global_roles)]
+    roles: HashSet<GlobalRole>,
+}
+
+#[derive(Serialize, Resource, RbacResource)]
+#[author(action = CustomerAction, role = GlobalRole)]
+#[author(allow(Read, roles = [Support, Billing]))]
+struct Customer {
+    name: String,
+    payment: Payment,
+}
+
+#[derive(Serialize)]
+struct Payment {
+    card_number: String,
+    billing_address: String,
+}
+
+impl FieldResource for Customer {
+    fn sensitive_fields(&self) -> Vec<&'static str> {
+        vec!["payment.card_number"]
+    }
+}
+
+// Only billing staff can see or change card numbers
+impl RbacResource<GlobalRole> for Field<'_, Customer> {
+    fn allowed_roles(&self, _action: &FieldAction) -> HashSet<GlobalRole> {
+        HashSet::from([GlobalRole::Billing])
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum CustomerAction {
+    Read,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+enum GlobalRole {
+    Support,
+    Billing,
+}
+
+fn main() -> anyhow::Result<()> {
+    let policy = GlobalRbacPolicy::new();
+
+    let support = User {
+        roles: HashSet::from([GlobalRole::Support]),
+    };
+
+    let billing = User {
+        roles: HashSet::from([GlobalRole::Billing]),
+    };
+
+    let customer = Customer {
+        name: "Customer".to_string(),
+        payment: Payment {
+            card_number: "4111 1111 1111 1111".to_string(),
+            billing_address: "1 High Street".to_string(),
+        },
+    };
+
+    // Support can read the customer, but not their card number
+    assert_matches!(
+        policy.authorise(&customer, &support, &CustomerAction::Read),
+        Ok(_)
+    );
+
+    let redactor = Redactor::new(&policy).mask_with("[redacted]");
+
+    assert_eq!(
+        redactor.redact(&customer, &support)?,
+        json!({
+            "name": "Customer",
+            "payment": {
+                "card_number": "[redacted]",
+                "billing_address": "1 High Street",
+            },
+        })
+    );
+
+    assert_eq!(
+        redactor.redact(&customer, &billing)?["payment"]["card_number"],
+        "4111 1111 1111 1111"
+    );
+
+    // Writes to individual fields can be checked too
+    let card_number = Field::new(&customer, "payment.card_number");
+
+    assert_matches!(
+        policy.authorise(&card_number, &support, &FieldAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        policy.authorise(&card_number, &billing, &FieldAction::Write),
+        Ok(_)
+    );
+
+    Ok(())
+}
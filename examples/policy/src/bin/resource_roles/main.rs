@@ -1,5 +1,8 @@
 use assert_matches::assert_matches;
-use author::rbac::{GlobalRbacPolicy, GlobalRbacSubject, RbacResource, RbacResourceWithRole};
+use author::rbac::{
+    GlobalRbacPolicy, GlobalRbacSubject, RbacResource, RbacResourceWithRole, RbacSubject,
+    ResourceRbacPolicy,
+};
 use author::{Policy, Resource, Subject};
 use std::collections::HashSet;
 
@@ -18,8 +21,29 @@ impl GlobalRbacSubject for User {
     }
 }
 
+impl RbacSubject<Customer> for User {
+    fn resource_roles(&self, resource: &Customer) -> HashSet<CustomerRole> {
+        let mut roles = HashSet::new();
+
+        if self.roles.contains(&GlobalRole::Admin) {
+            roles.insert(CustomerRole::Admin);
+        }
+
+        if resource.owner == self.name {
+            roles.insert(CustomerRole::Owner);
+        }
+
+        if resource.account_managers.contains(&self.name) {
+            roles.insert(CustomerRole::Other);
+        }
+
+        roles
+    }
+}
+
 struct Customer {
-    name: String,
+    owner: String,
+    account_managers: HashSet<String>,
 }
 
 impl Resource for Customer {
@@ -52,10 +76,7 @@ impl RbacResource<GlobalRole> for Customer {
     }
 }
 
-struct Product {
-    name: String,
-    cost: f32,
-}
+struct Product;
 
 impl Resource for Product {
     type Action = ProductAction;
@@ -84,13 +105,13 @@ enum ProductAction {
     Delete,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 enum GlobalRole {
     User,
     Admin,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Debug)]
 enum CustomerRole {
     Admin,
     Owner,
@@ -98,10 +119,21 @@ enum CustomerRole {
 }
 
 fn main() -> anyhow::Result<()> {
-    let policy = GlobalRbacPolicy::new();
+    let global_policy = GlobalRbacPolicy::new();
+    let resource_policy = ResourceRbacPolicy::new();
+
+    let owner = User {
+        name: "Owner".to_string(),
+        roles: HashSet::from([GlobalRole::User]),
+    };
+
+    let account_manager = User {
+        name: "Account Manager".to_string(),
+        roles: HashSet::from([GlobalRole::User]),
+    };
 
-    let user = User {
-        name: "User".to_string(),
+    let stranger = User {
+        name: "Stranger".to_string(),
         roles: HashSet::from([GlobalRole::User]),
     };
 
@@ -111,16 +143,77 @@ fn main() -> anyhow::Result<()> {
     };
 
     let customer = Customer {
-        name: "Customer".to_string(),
+        owner: "Owner".to_string(),
+        account_managers: HashSet::from(["Account Manager".to_string()]),
     };
 
-    let product = Product {
-        name: "Product".to_string(),
-        cost: 0.50,
-    };
+    let product = Product;
+
+    // Global roles alone only let admins near the customer
+    assert_matches!(
+        global_policy.authorise(&customer, &owner, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        global_policy.authorise(&customer, &admin_user, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    // Products have no per-instance roles, so global roles are all that matter
+    assert_matches!(
+        global_policy.authorise(&product, &owner, &ProductAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        global_policy.authorise(&product, &owner, &ProductAction::Delete),
+        Err(_)
+    );
+
+    assert_matches!(
+        global_policy.authorise(&product, &admin_user, &ProductAction::Write),
+        Ok(_)
+    );
+
+    // Resource roles depend on the relationship between the user and this particular customer
+    assert_matches!(
+        resource_policy.authorise(&customer, &owner, &CustomerAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &owner, &CustomerAction::Write),
+        Ok(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &account_manager, &CustomerAction::Read),
+        Ok(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &account_manager, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &stranger, &CustomerAction::Read),
+        Err(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &stranger, &CustomerAction::Write),
+        Err(_)
+    );
+
+    assert_matches!(
+        resource_policy.authorise(&customer, &admin_user, &CustomerAction::Read),
+        Ok(_)
+    );
 
     assert_matches!(
-        policy.authorise(&customer, &user, &CustomerAction::Write),
+        resource_policy.authorise(&customer, &admin_user, &CustomerAction::Write),
         Ok(_)
     );
 